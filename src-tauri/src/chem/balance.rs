@@ -0,0 +1,721 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::chem::equation::{integer_coefficients, null_space};
+use crate::chem::linalg::{qr_solve, transpose};
+use crate::chem::rational::Rational;
+use crate::commands::calc_helpers::format_value;
+
+/// Square subsystems `solve_min_cost` will try before giving up.
+const MAX_SUBSETS: u64 = 20_000;
+
+#[derive(Clone)]
+pub struct Reagent {
+    pub name: String,
+    pub composition: HashMap<String, f64>,
+    pub molar_mass: f64,
+}
+
+/// Solves a square system by rank-revealing QR; a singular matrix is
+/// reported with its numerical rank.
+pub fn solve_square_system(a: Vec<Vec<f64>>, b: Vec<f64>) -> Result<Vec<f64>, String> {
+    qr_solve(&a, &b).map_err(|rank| format!("Singular system (rank {} of {})", rank, a.len()))
+}
+
+fn relation(names: &[String], coefficients: &[i64]) -> String {
+    let term = |name: &str, c: i64| {
+        if c == 1 {
+            name.to_string()
+        } else {
+            format!("{} {}", c, name)
+        }
+    };
+    let side = |positive: bool| {
+        names
+            .iter()
+            .zip(coefficients.iter())
+            .filter(|(_, c)| **c != 0 && (**c > 0) == positive)
+            .map(|(name, c)| term(name, c.abs()))
+            .collect::<Vec<_>>()
+            .join(" + ")
+    };
+    format!("{} = {}", side(true), side(false))
+}
+
+/// Explains why `matrix` (elements x reagents) is singular: combinations of
+/// reagents with the same element content, and elements whose amounts are in
+/// a fixed ratio in every reagent and so cannot be set independently.
+pub fn linear_dependencies(
+    matrix: &[Vec<f64>],
+    elements: &[String],
+    reagents: &[String],
+) -> Vec<String> {
+    let describe = |kind: &str, vector: &[f64], names: &[String], suffix: &str| -> String {
+        let used: Vec<String> = names
+            .iter()
+            .zip(vector.iter())
+            .filter(|(_, v)| v.abs() > 1e-9)
+            .map(|(n, _)| n.clone())
+            .collect();
+        let line = format!("{} {} are linearly dependent", kind, used.join(", "));
+        match integer_coefficients(vector) {
+            Some(coefficients) => {
+                format!("{} ({}{})", line, relation(names, &coefficients), suffix)
+            }
+            None => line,
+        }
+    };
+    let mut lines: Vec<String> = null_space(matrix, reagents.len())
+        .iter()
+        .map(|v| describe("reagents", v, reagents, ""))
+        .collect();
+    lines.extend(
+        null_space(&transpose(matrix, reagents.len()), elements.len())
+            .iter()
+            .map(|v| describe("elements", v, elements, " in every reagent")),
+    );
+    lines
+}
+
+/// Solves the element balance for `order`, first fixing reagents that are the
+/// only supplier of an element and then solving the remaining square system.
+/// Elements outside `order` are not balanced; callers check them afterwards.
+pub fn solve_element_balance(
+    reagents: &[Reagent],
+    order: &[String],
+    required: &mut HashMap<String, f64>,
+    explanation: &mut Vec<String>,
+) -> Result<Vec<f64>, String> {
+    let tol = 1e-10;
+    let mut fixed: Vec<Option<f64>> = vec![None; reagents.len()];
+
+    loop {
+        let remaining_indices: Vec<usize> = fixed
+            .iter()
+            .enumerate()
+            .filter_map(|(i, v)| if v.is_none() { Some(i) } else { None })
+            .collect();
+        let mut changed = false;
+        let remaining_elements: Vec<String> = order
+            .iter()
+            .filter_map(|el| {
+                if required.get(el).copied().unwrap_or(0.0).abs() > tol {
+                    Some(el.clone())
+                } else {
+                    None
+                }
+            })
+            .collect();
+
+        for element in remaining_elements {
+            let providers: Vec<usize> = remaining_indices
+                .iter()
+                .cloned()
+                .filter(|idx| fixed[*idx].is_none())
+                .filter(|idx| {
+                    reagents[*idx]
+                        .composition
+                        .get(&element)
+                        .map(|v| *v > 0.0)
+                        .unwrap_or(false)
+                })
+                .collect();
+            if providers.len() == 1 {
+                let idx = providers[0];
+                let coeff = reagents[idx]
+                    .composition
+                    .get(&element)
+                    .copied()
+                    .unwrap_or(0.0);
+                if coeff.abs() < tol {
+                    return Err(format!("Invalid coefficient for {}", element));
+                }
+                let amount = required.get(&element).copied().unwrap_or(0.0) / coeff;
+                if let Some(existing) = fixed[idx] {
+                    if (existing - amount).abs() > 1e-8 {
+                        return Err(format!(
+                            "Inconsistent requirement for {} in {}",
+                            element, reagents[idx].name
+                        ));
+                    }
+                } else {
+                    fixed[idx] = Some(amount);
+                    changed = true;
+                    explanation.push(format!(
+                        "Unique supplier: {} only in {} -> n({}) = {} mol",
+                        element,
+                        reagents[idx].name,
+                        reagents[idx].name,
+                        format_value(amount)
+                    ));
+                    for (el, coeff) in reagents[idx].composition.iter() {
+                        let entry = required.entry(el.clone()).or_insert(0.0);
+                        *entry -= amount * coeff;
+                    }
+                }
+            }
+        }
+        if !changed {
+            break;
+        }
+    }
+
+    for el in order {
+        let remaining = required.get(el).copied().unwrap_or(0.0);
+        if remaining.abs() > 1e-6 {
+            let providers = reagents
+                .iter()
+                .enumerate()
+                .filter(|(idx, _)| fixed[*idx].is_none())
+                .filter(|(_, reagent)| reagent.composition.contains_key(el))
+                .count();
+            if providers == 0 {
+                return Err(format!("No remaining reagent provides {}", el));
+            }
+        }
+    }
+
+    let remaining_indices: Vec<usize> = fixed
+        .iter()
+        .enumerate()
+        .filter_map(|(i, v)| if v.is_none() { Some(i) } else { None })
+        .collect();
+
+    let remaining_elements: Vec<String> = order
+        .iter()
+        .filter_map(|el| {
+            if required.get(el).copied().unwrap_or(0.0).abs() > 1e-8 {
+                Some(el.clone())
+            } else {
+                None
+            }
+        })
+        .collect();
+
+    if !remaining_elements.is_empty() && !remaining_indices.is_empty() {
+        if remaining_indices.len() < remaining_elements.len() {
+            return Err("Not enough independent reagents to solve".to_string());
+        }
+
+        let mut priority: Vec<usize> = remaining_indices.clone();
+        priority.sort_by(|a, b| {
+            let a_elemental = reagents[*a].composition.len() == 1;
+            let b_elemental = reagents[*b].composition.len() == 1;
+            match (a_elemental, b_elemental) {
+                (false, true) => std::cmp::Ordering::Less,
+                (true, false) => std::cmp::Ordering::Greater,
+                _ => a.cmp(b),
+            }
+        });
+
+        let mut selected = Vec::new();
+        let mut covered: HashSet<String> = HashSet::new();
+        for idx in &priority {
+            let contributes = reagents[*idx]
+                .composition
+                .keys()
+                .any(|el| remaining_elements.contains(el));
+            if contributes {
+                selected.push(*idx);
+                for el in reagents[*idx].composition.keys() {
+                    covered.insert(el.clone());
+                }
+                if remaining_elements.iter().all(|el| covered.contains(el)) {
+                    break;
+                }
+            }
+        }
+
+        if !remaining_elements.iter().all(|el| covered.contains(el)) {
+            return Err("Could not cover all remaining elements".to_string());
+        }
+
+        while selected.len() < remaining_elements.len() {
+            if let Some(next) = priority.iter().find(|idx| !selected.contains(idx)) {
+                selected.push(*next);
+            } else {
+                break;
+            }
+        }
+
+        if selected.len() != remaining_elements.len() {
+            return Err("Ambiguous system; provide additional constraints".to_string());
+        }
+
+        let mut matrix = Vec::new();
+        let mut rhs = Vec::new();
+        for element in &remaining_elements {
+            let mut row = Vec::new();
+            for idx in &selected {
+                row.push(
+                    reagents[*idx]
+                        .composition
+                        .get(element)
+                        .copied()
+                        .unwrap_or(0.0),
+                );
+            }
+            matrix.push(row);
+            rhs.push(required.get(element).copied().unwrap_or(0.0));
+        }
+
+        let solved = solve_square_system(matrix.clone(), rhs).map_err(|e| {
+            let names: Vec<String> = selected.iter().map(|i| reagents[*i].name.clone()).collect();
+            let dependencies = linear_dependencies(&matrix, &remaining_elements, &names);
+            if dependencies.is_empty() {
+                e
+            } else {
+                format!("{}: {}", e, dependencies.join("; "))
+            }
+        })?;
+        for (idx, amount) in selected.iter().zip(solved.iter()) {
+            fixed[*idx] = Some(*amount);
+        }
+
+        for idx in &remaining_indices {
+            if fixed[*idx].is_none() {
+                fixed[*idx] = Some(0.0);
+            }
+        }
+    } else {
+        for idx in &remaining_indices {
+            fixed[*idx] = Some(0.0);
+        }
+    }
+
+    Ok(fixed.iter().map(|v| v.unwrap_or(0.0)).collect())
+}
+
+/// Cheapest non-negative solution of the element balance. The optimum of
+/// this linear programme lies on a vertex, so every square subsystem picked
+/// from the reagents is solved and the cheapest feasible one kept, up to
+/// `MAX_SUBSETS` of them. `costs` are per mole of each reagent.
+pub fn solve_min_cost(
+    reagents: &[Reagent],
+    order: &[String],
+    required: &HashMap<String, f64>,
+    costs: &[f64],
+) -> Result<Vec<f64>, String> {
+    let size = order.len();
+    if size == 0 {
+        return Ok(vec![0.0; reagents.len()]);
+    }
+    if reagents.len() < size {
+        return Err("Not enough independent reagents to solve".to_string());
+    }
+    // C(n, size), saturating once it passes the limit.
+    let n = reagents.len() as u64;
+    let subsets = (0..(size as u64).min(n - size as u64)).try_fold(1_u64, |acc, i| {
+        let next = acc.checked_mul(n - i)? / (i + 1);
+        (next <= MAX_SUBSETS).then_some(next)
+    });
+    if subsets.is_none() {
+        return Err(format!(
+            "Too many reagent combinations for the minimum-cost search (more than {}); remove some starting materials",
+            MAX_SUBSETS
+        ));
+    }
+    let rhs: Vec<f64> = order
+        .iter()
+        .map(|el| required.get(el).copied().unwrap_or(0.0))
+        .collect();
+
+    let mut best: Option<(f64, Vec<f64>)> = None;
+    let mut subset: Vec<usize> = (0..size).collect();
+    loop {
+        let matrix: Vec<Vec<f64>> = order
+            .iter()
+            .map(|el| {
+                subset
+                    .iter()
+                    .map(|idx| reagents[*idx].composition.get(el).copied().unwrap_or(0.0))
+                    .collect()
+            })
+            .collect();
+        if let Ok(solved) = solve_square_system(matrix, rhs.clone()) {
+            if solved.iter().all(|n| *n > -1e-10) {
+                let mut amounts = vec![0.0; reagents.len()];
+                for (idx, n) in subset.iter().zip(solved.iter()) {
+                    amounts[*idx] = n.max(0.0);
+                }
+                let cost: f64 = amounts.iter().zip(costs.iter()).map(|(n, c)| n * c).sum();
+                if best.as_ref().is_none_or(|(c, _)| cost < *c - 1e-12) {
+                    best = Some((cost, amounts));
+                }
+            }
+        }
+
+        // Advance to the next combination in lexicographic order.
+        let Some(pos) = (0..size)
+            .rev()
+            .find(|i| subset[*i] < reagents.len() - size + i)
+        else {
+            break;
+        };
+        subset[pos] += 1;
+        for i in pos + 1..size {
+            subset[i] = subset[i - 1] + 1;
+        }
+    }
+
+    best.map(|(_, amounts)| amounts)
+        .ok_or_else(|| "No non-negative solution of the element balance".to_string())
+}
+
+/// Element balance in exact rational arithmetic, per formula unit of target.
+/// Reagents are picked by the rule `solve_element_balance` uses, decided on
+/// exact zeros instead of tolerances: unique suppliers first, then compounds
+/// before elements until every element still required is covered. Reagents
+/// not picked are zero. `elements` lists every balanced element, including
+/// those the reagents bring in but the target lacks (required amount zero).
+pub fn solve_element_balance_exact(
+    compositions: &[HashMap<String, Rational>],
+    names: &[String],
+    elements: &[String],
+    target: &HashMap<String, Rational>,
+) -> Result<Vec<Rational>, String> {
+    let overflow = || "Exact arithmetic overflow; use the floating-point solver".to_string();
+    let supplies = |idx: usize, el: &str| {
+        compositions[idx]
+            .get(el)
+            .is_some_and(|c| !c.is_zero() && !c.is_negative())
+    };
+    let mut required: HashMap<&str, Rational> = elements
+        .iter()
+        .map(|el| {
+            let amount = target.get(el).copied().unwrap_or(Rational::ZERO);
+            (el.as_str(), amount)
+        })
+        .collect();
+    let mut selected: Vec<usize> = Vec::new();
+
+    loop {
+        let mut changed = false;
+        for el in elements {
+            if required[el.as_str()].is_zero() {
+                continue;
+            }
+            let providers: Vec<usize> = (0..compositions.len())
+                .filter(|idx| !selected.contains(idx) && supplies(*idx, el))
+                .collect();
+            let [idx] = providers[..] else {
+                continue;
+            };
+            let amount = required[el.as_str()]
+                .checked_div(compositions[idx][el])
+                .ok_or_else(overflow)?;
+            for (other, coeff) in &compositions[idx] {
+                if let Some(left) = required.get_mut(other.as_str()) {
+                    let used = amount.checked_mul(*coeff).ok_or_else(overflow)?;
+                    *left = left.checked_sub(used).ok_or_else(overflow)?;
+                }
+            }
+            selected.push(idx);
+            changed = true;
+        }
+        if !changed {
+            break;
+        }
+    }
+
+    let remaining: Vec<&String> = elements
+        .iter()
+        .filter(|el| !required[el.as_str()].is_zero())
+        .collect();
+    if !remaining.is_empty() {
+        // Stable sort: compounds before elemental reagents, in input order.
+        let mut priority: Vec<usize> = (0..compositions.len())
+            .filter(|idx| !selected.contains(idx))
+            .collect();
+        priority.sort_by_key(|idx| compositions[*idx].len() == 1);
+        let mut picked: Vec<usize> = Vec::new();
+        let mut covered: HashSet<&String> = HashSet::new();
+        for idx in &priority {
+            if compositions[*idx].keys().any(|el| remaining.contains(&el)) {
+                picked.push(*idx);
+                covered.extend(compositions[*idx].keys());
+                if remaining.iter().all(|el| covered.contains(el)) {
+                    break;
+                }
+            }
+        }
+        while picked.len() < remaining.len() {
+            let Some(next) = priority.iter().find(|idx| !picked.contains(idx)) else {
+                break;
+            };
+            picked.push(*next);
+        }
+        selected.extend(picked);
+    }
+    selected.sort_unstable();
+
+    let solved = reduce_exact(
+        &selected
+            .iter()
+            .map(|idx| &compositions[*idx])
+            .collect::<Vec<_>>(),
+        &selected
+            .iter()
+            .map(|idx| names[*idx].as_str())
+            .collect::<Vec<_>>(),
+        elements,
+        target,
+    )?;
+    let mut amounts = vec![Rational::ZERO; compositions.len()];
+    for (idx, n) in selected.iter().zip(solved) {
+        amounts[*idx] = n;
+    }
+    Ok(amounts)
+}
+
+/// Solves the picked columns by reduced row echelon form; columns left free
+/// are set to zero.
+fn reduce_exact(
+    compositions: &[&HashMap<String, Rational>],
+    names: &[&str],
+    elements: &[String],
+    target: &HashMap<String, Rational>,
+) -> Result<Vec<Rational>, String> {
+    let overflow = || "Exact arithmetic overflow; use the floating-point solver".to_string();
+    let columns = compositions.len();
+    // Original element of each row, permuted along with the row swaps.
+    let mut origin: Vec<usize> = (0..elements.len()).collect();
+    let mut aug: Vec<Vec<Rational>> = elements
+        .iter()
+        .map(|el| {
+            compositions
+                .iter()
+                .map(|c| c.get(el).copied().unwrap_or(Rational::ZERO))
+                .chain(std::iter::once(
+                    target.get(el).copied().unwrap_or(Rational::ZERO),
+                ))
+                .collect()
+        })
+        .collect();
+
+    let mut pivots: Vec<usize> = Vec::new();
+    let mut row = 0;
+    for col in 0..columns {
+        if row == aug.len() {
+            break;
+        }
+        let Some(pivot_row) = (row..aug.len()).find(|r| !aug[*r][col].is_zero()) else {
+            continue;
+        };
+        aug.swap(row, pivot_row);
+        origin.swap(row, pivot_row);
+        let pivot = aug[row][col];
+        for c in 0..=columns {
+            aug[row][c] = aug[row][c].checked_div(pivot).ok_or_else(overflow)?;
+        }
+        for r in 0..aug.len() {
+            let factor = aug[r][col];
+            if r == row || factor.is_zero() {
+                continue;
+            }
+            for c in 0..=columns {
+                let delta = factor.checked_mul(aug[row][c]).ok_or_else(overflow)?;
+                aug[r][c] = aug[r][c].checked_sub(delta).ok_or_else(overflow)?;
+            }
+        }
+        pivots.push(col);
+        row += 1;
+    }
+
+    if let Some(r) = (row..aug.len()).find(|r| !aug[*r][columns].is_zero()) {
+        let element = &elements[origin[r]];
+        return Err(if target.contains_key(element) {
+            format!("Inconsistent requirement for {}", element)
+        } else {
+            format!("Reagent introduces element not in target: {}", element)
+        });
+    }
+
+    let mut amounts = vec![Rational::ZERO; columns];
+    for (r, col) in pivots.iter().enumerate() {
+        amounts[*col] = aug[r][columns];
+    }
+    if let Some(idx) = amounts.iter().position(|n| n.is_negative()) {
+        return Err(format!("Negative amount for {}", names[idx]));
+    }
+    Ok(amounts)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chem::mass::collapse_formula;
+    use crate::chem::parse::{parse_formula, parse_formula_exact};
+    use crate::chem::rational::collapse_exact;
+
+    fn exact(pairs: &[(&str, i128)]) -> HashMap<String, Rational> {
+        pairs
+            .iter()
+            .map(|(el, n)| (el.to_string(), Rational::from_integer(*n)))
+            .collect()
+    }
+
+    fn reagent(name: &str, pairs: &[(&str, f64)]) -> Reagent {
+        Reagent {
+            name: name.to_string(),
+            composition: pairs.iter().map(|(el, n)| (el.to_string(), *n)).collect(),
+            molar_mass: 0.0,
+        }
+    }
+
+    fn names(items: &[&str]) -> Vec<String> {
+        items.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn square_system_is_solved_or_reports_its_rank() {
+        let x = solve_square_system(vec![vec![2.0, 1.0], vec![1.0, 3.0]], vec![3.0, 5.0]).unwrap();
+        assert!((x[0] - 0.8).abs() < 1e-12 && (x[1] - 1.4).abs() < 1e-12);
+        assert_eq!(
+            solve_square_system(vec![vec![1.0, 2.0], vec![2.0, 4.0]], vec![1.0, 2.0]),
+            Err("Singular system (rank 1 of 2)".to_string())
+        );
+    }
+
+    #[test]
+    fn element_balance_fixes_unique_suppliers_first() {
+        let reagents = [
+            reagent("BaCO3", &[("Ba", 1.0), ("C", 1.0), ("O", 3.0)]),
+            reagent("TiO2", &[("Ti", 1.0), ("O", 2.0)]),
+        ];
+        let mut required: HashMap<String, f64> =
+            [("Ba".to_string(), 0.5), ("Ti".to_string(), 0.5)].into();
+        let mut explanation = Vec::new();
+        let amounts = solve_element_balance(
+            &reagents,
+            &names(&["Ba", "Ti"]),
+            &mut required,
+            &mut explanation,
+        )
+        .unwrap();
+        assert_eq!(amounts, vec![0.5, 0.5]);
+        assert_eq!(explanation.len(), 2);
+    }
+
+    #[test]
+    fn element_balance_solves_shared_elements_by_qr() {
+        // Both reagents supply Ba and Ti, so neither is a unique supplier.
+        let reagents = [
+            reagent("BaTiO3", &[("Ba", 1.0), ("Ti", 1.0), ("O", 3.0)]),
+            reagent("Ba2TiO4", &[("Ba", 2.0), ("Ti", 1.0), ("O", 4.0)]),
+        ];
+        let mut required: HashMap<String, f64> =
+            [("Ba".to_string(), 3.0), ("Ti".to_string(), 2.0)].into();
+        let amounts = solve_element_balance(
+            &reagents,
+            &names(&["Ba", "Ti"]),
+            &mut required,
+            &mut Vec::new(),
+        )
+        .unwrap();
+        assert!((amounts[0] - 1.0).abs() < 1e-12 && (amounts[1] - 1.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn singular_balance_lists_the_dependent_reagents() {
+        let reagents = [
+            reagent("BaO", &[("Ba", 1.0), ("O", 1.0)]),
+            reagent("Ba2O2", &[("Ba", 2.0), ("O", 2.0)]),
+        ];
+        let mut required: HashMap<String, f64> =
+            [("Ba".to_string(), 1.0), ("O".to_string(), 1.0)].into();
+        let error = solve_element_balance(
+            &reagents,
+            &names(&["Ba", "O"]),
+            &mut required,
+            &mut Vec::new(),
+        )
+        .unwrap_err();
+        assert!(error.starts_with("Singular system (rank 1 of 2)"));
+        assert!(error.contains("reagents BaO, Ba2O2 are linearly dependent (Ba2O2 = 2 BaO)"));
+    }
+
+    #[test]
+    fn exact_balance_is_free_of_float_noise() {
+        // Li1/3Mn2/3O2 from LiMn2O4 and Li4Mn5O12, oxygen volatile. At
+        // 1e9/7 mol the float solve leaves Li4Mn5O12 below the -1e-8 mol
+        // that `calculate` accepts as zero; the exact solve picks the same
+        // reagent and lands on zero.
+        let formulas = ["LiMn2O4", "Li4Mn5O12"];
+        let elements = names(&["Li", "Mn"]);
+        let target_moles = 1e9 / 7.0;
+        let reagents: Vec<Reagent> = formulas
+            .iter()
+            .map(|f| Reagent {
+                name: f.to_string(),
+                composition: collapse_formula(&parse_formula(f).unwrap()),
+                molar_mass: 0.0,
+            })
+            .collect();
+        let target = collapse_formula(&parse_formula("Li1/3Mn2/3O2").unwrap());
+        let mut required: HashMap<String, f64> = elements
+            .iter()
+            .map(|el| (el.clone(), target[el] * target_moles))
+            .collect();
+        let float =
+            solve_element_balance(&reagents, &elements, &mut required, &mut Vec::new()).unwrap();
+        assert!(float[1] < -1e-8);
+
+        let compositions: Vec<HashMap<String, Rational>> = formulas
+            .iter()
+            .map(|f| collapse_exact(&parse_formula_exact(f).unwrap()).unwrap())
+            .collect();
+        let target = collapse_exact(&parse_formula_exact("Li1/3Mn2/3O2").unwrap()).unwrap();
+        let amounts =
+            solve_element_balance_exact(&compositions, &names(&formulas), &elements, &target);
+        assert_eq!(
+            amounts,
+            Ok(vec![Rational::new(1, 3).unwrap(), Rational::ZERO])
+        );
+    }
+
+    #[test]
+    fn exact_balance_names_the_element_of_the_swapped_row() {
+        // The only reagent pivots on the Ti row, swapping it above Ba, so
+        // the unmet requirement left over belongs to Ba.
+        let elements = vec!["Ba".to_string(), "Ti".to_string()];
+        let result = solve_element_balance_exact(
+            &[exact(&[("Ti", 1)])],
+            &["TiO2".to_string()],
+            &elements,
+            &exact(&[("Ba", 1), ("Ti", 1)]),
+        );
+        assert_eq!(result, Err("Inconsistent requirement for Ba".to_string()));
+    }
+
+    #[test]
+    fn min_cost_keeps_the_cheapest_vertex() {
+        let reagents = [
+            reagent("BaCO3", &[("Ba", 1.0), ("C", 1.0), ("O", 3.0)]),
+            reagent("BaO", &[("Ba", 1.0), ("O", 1.0)]),
+            reagent("TiO2", &[("Ti", 1.0), ("O", 2.0)]),
+        ];
+        let order = vec!["Ba".to_string(), "Ti".to_string()];
+        let required: HashMap<String, f64> =
+            [("Ba".to_string(), 2.0), ("Ti".to_string(), 2.0)].into();
+        let amounts = solve_min_cost(&reagents, &order, &required, &[1.0, 5.0, 1.0]).unwrap();
+        assert_eq!(amounts, vec![2.0, 0.0, 2.0]);
+        let amounts = solve_min_cost(&reagents, &order, &required, &[5.0, 1.0, 1.0]).unwrap();
+        assert_eq!(amounts, vec![0.0, 2.0, 2.0]);
+    }
+
+    #[test]
+    fn min_cost_refuses_too_many_combinations() {
+        let elements = ["Li", "Na", "K", "Rb", "Cs"];
+        let reagents: Vec<Reagent> = (0..30)
+            .map(|idx| reagent(&format!("R{}", idx), &[(elements[idx % 5], 1.0)]))
+            .collect();
+        let order: Vec<String> = elements.iter().map(|el| el.to_string()).collect();
+        let required: HashMap<String, f64> = order.iter().map(|el| (el.clone(), 1.0)).collect();
+        // C(30, 5) = 142506 square subsystems.
+        assert_eq!(
+            solve_min_cost(&reagents, &order, &required, &[1.0; 30]).err(),
+            Some("Too many reagent combinations for the minimum-cost search (more than 20000); remove some starting materials".to_string())
+        );
+        assert!(solve_min_cost(&reagents[..10], &order, &required, &[1.0; 10]).is_ok());
+    }
+}
@@ -0,0 +1,215 @@
+use std::collections::HashMap;
+
+const TOL: f64 = 1e-9;
+const MAX_DENOMINATOR: i64 = 100_000;
+
+/// Basis of the null space of `matrix` (rows x columns), from its reduced
+/// row echelon form: one vector per free column.
+pub fn null_space(matrix: &[Vec<f64>], columns: usize) -> Vec<Vec<f64>> {
+    let mut rref: Vec<Vec<f64>> = matrix.to_vec();
+    let rows = rref.len();
+    let mut pivots: Vec<usize> = Vec::new();
+    let mut row = 0;
+    for col in 0..columns {
+        if row == rows {
+            break;
+        }
+        let Some(pivot_row) = (row..rows)
+            .filter(|r| rref[*r][col].abs() > TOL)
+            .max_by(|a, b| rref[*a][col].abs().total_cmp(&rref[*b][col].abs()))
+        else {
+            continue;
+        };
+        rref.swap(row, pivot_row);
+        let pivot = rref[row][col];
+        for c in 0..columns {
+            rref[row][c] /= pivot;
+        }
+        for r in 0..rows {
+            if r == row {
+                continue;
+            }
+            let factor = rref[r][col];
+            if factor.abs() > TOL {
+                for c in 0..columns {
+                    rref[r][c] -= factor * rref[row][c];
+                }
+            }
+        }
+        pivots.push(col);
+        row += 1;
+    }
+
+    (0..columns)
+        .filter(|col| !pivots.contains(col))
+        .map(|free| {
+            let mut vector = vec![0.0; columns];
+            vector[free] = 1.0;
+            for (r, pivot) in pivots.iter().enumerate() {
+                vector[*pivot] = -rref[r][free];
+            }
+            vector
+        })
+        .collect()
+}
+
+fn gcd(a: i64, b: i64) -> i64 {
+    if b == 0 {
+        a.abs()
+    } else {
+        gcd(b, a % b)
+    }
+}
+
+/// Best rational approximation of `value` by continued fractions.
+fn rationalize(value: f64) -> Option<(i64, i64)> {
+    let (mut h0, mut h1) = (0_i64, 1_i64);
+    let (mut k0, mut k1) = (1_i64, 0_i64);
+    let mut x = value;
+    for _ in 0..64 {
+        let a = x.floor();
+        if a.abs() > 1e12 {
+            return None;
+        }
+        let a = a as i64;
+        let (h2, k2) = (a * h1 + h0, a * k1 + k0);
+        if k2 > MAX_DENOMINATOR {
+            break;
+        }
+        (h0, h1, k0, k1) = (h1, h2, k1, k2);
+        let frac = x - a as f64;
+        if (h1 as f64 / k1 as f64 - value).abs() <= TOL * value.abs().max(1.0) || frac < 1e-12 {
+            return Some((h1, k1));
+        }
+        x = 1.0 / frac;
+    }
+    (k1 != 0 && (h1 as f64 / k1 as f64 - value).abs() <= 1e-6 * value.abs().max(1.0))
+        .then_some((h1, k1))
+}
+
+/// Scales a vector of rational-valued coefficients to the smallest integers
+/// with the same ratios.
+pub fn integer_coefficients(vector: &[f64]) -> Option<Vec<i64>> {
+    let fractions = vector
+        .iter()
+        .map(|v| rationalize(*v))
+        .collect::<Option<Vec<(i64, i64)>>>()?;
+    let lcm = fractions
+        .iter()
+        .try_fold(1_i64, |acc, (_, d)| (acc / gcd(acc, *d)).checked_mul(*d))?;
+    let integers = fractions
+        .iter()
+        .map(|(n, d)| n.checked_mul(lcm / d))
+        .collect::<Option<Vec<i64>>>()?;
+    let divisor = integers.iter().fold(0, |acc, n| gcd(acc, *n));
+    if divisor == 0 {
+        return None;
+    }
+    Some(integers.iter().map(|n| n / divisor).collect())
+}
+
+/// Element-by-species matrix with reactants counted positive and products
+/// negative, so that balanced coefficient vectors are its null space.
+pub fn element_matrix(
+    reactants: &[HashMap<String, f64>],
+    products: &[HashMap<String, f64>],
+) -> (Vec<String>, Vec<Vec<f64>>) {
+    let mut elements: Vec<String> = Vec::new();
+    for composition in reactants.iter().chain(products.iter()) {
+        for el in composition.keys() {
+            if !elements.contains(el) {
+                elements.push(el.clone());
+            }
+        }
+    }
+    elements.sort();
+    let matrix = elements
+        .iter()
+        .map(|el| {
+            reactants
+                .iter()
+                .map(|c| c.get(el).copied().unwrap_or(0.0))
+                .chain(products.iter().map(|c| -c.get(el).copied().unwrap_or(0.0)))
+                .collect()
+        })
+        .collect();
+    (elements, matrix)
+}
+
+/// Writes `coefficients` (reactants first, then products) as an equation.
+/// Negative coefficients move a species to the other side; zeros drop it.
+pub fn format_equation(names: &[String], reactant_count: usize, coefficients: &[i64]) -> String {
+    let term = |name: &str, c: i64| {
+        if c == 1 {
+            name.to_string()
+        } else {
+            format!("{} {}", c, name)
+        }
+    };
+    let mut left = Vec::new();
+    let mut right = Vec::new();
+    for (idx, (name, c)) in names.iter().zip(coefficients.iter()).enumerate() {
+        // Products carry negative signs in the matrix, so a positive
+        // coefficient keeps a species on its own side.
+        let reactant = idx < reactant_count;
+        match (*c > 0, reactant) {
+            _ if *c == 0 => {}
+            (true, true) | (false, false) => left.push(term(name, c.abs())),
+            _ => right.push(term(name, c.abs())),
+        }
+    }
+    format!("{} -> {}", left.join(" + "), right.join(" + "))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn null_space_has_one_vector_per_free_column() {
+        let basis = null_space(&[vec![1.0, -1.0, 0.0], vec![0.0, 1.0, -1.0]], 3);
+        assert_eq!(basis, vec![vec![1.0, 1.0, 1.0]]);
+        assert!(null_space(&[vec![1.0, 0.0], vec![0.0, 1.0]], 2).is_empty());
+        assert_eq!(null_space(&[vec![1.0, 2.0, 3.0]], 3).len(), 2);
+    }
+
+    #[test]
+    fn rationalizes_within_the_denominator_limit() {
+        assert_eq!(rationalize(0.75), Some((3, 4)));
+        assert_eq!(rationalize(-2.0 / 3.0), Some((-2, 3)));
+        assert_eq!(rationalize(1e13), None);
+    }
+
+    #[test]
+    fn integer_coefficients_are_the_smallest_with_the_same_ratios() {
+        assert_eq!(
+            integer_coefficients(&[0.5, 1.0 / 3.0, 1.0]),
+            Some(vec![3, 2, 6])
+        );
+        assert_eq!(integer_coefficients(&[2.0, -4.0]), Some(vec![1, -2]));
+        assert_eq!(integer_coefficients(&[0.0, 0.0]), None);
+    }
+
+    #[test]
+    fn products_enter_the_matrix_negated() {
+        let composition = |pairs: &[(&str, f64)]| -> HashMap<String, f64> {
+            pairs.iter().map(|(el, n)| (el.to_string(), *n)).collect()
+        };
+        let (elements, matrix) = element_matrix(
+            &[composition(&[("H", 2.0)]), composition(&[("O", 2.0)])],
+            &[composition(&[("H", 2.0), ("O", 1.0)])],
+        );
+        assert_eq!(elements, vec!["H", "O"]);
+        assert_eq!(matrix, vec![vec![2.0, 0.0, -2.0], vec![0.0, 2.0, -1.0]]);
+    }
+
+    #[test]
+    fn negative_coefficients_move_species_across() {
+        let names: Vec<String> = ["CaCO3", "CaO", "CO2"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        assert_eq!(format_equation(&names, 1, &[1, 1, 1]), "CaCO3 -> CaO + CO2");
+        assert_eq!(format_equation(&names, 2, &[-1, 1, 0]), "CaO -> CaCO3");
+    }
+}
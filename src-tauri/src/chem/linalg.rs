@@ -0,0 +1,194 @@
+const MAX_SWEEPS: usize = 60;
+
+/// Rank and 2-norm condition number of a matrix, from its singular values.
+pub struct Conditioning {
+    pub rank: usize,
+    /// Largest over smallest singular value; `None` when rank deficient.
+    pub condition: Option<f64>,
+    pub singular_values: Vec<f64>,
+}
+
+fn rank_tolerance(largest: f64, rows: usize, columns: usize) -> f64 {
+    largest * rows.max(columns) as f64 * f64::EPSILON
+}
+
+/// Singular values of `matrix` (rows x columns) in descending order, by
+/// one-sided Jacobi rotations on its columns.
+pub fn singular_values(matrix: &[Vec<f64>], columns: usize) -> Vec<f64> {
+    let rows = matrix.len();
+    let mut u: Vec<Vec<f64>> = (0..columns)
+        .map(|c| (0..rows).map(|r| matrix[r][c]).collect())
+        .collect();
+    for _ in 0..MAX_SWEEPS {
+        let mut rotated = false;
+        for p in 0..columns {
+            for q in p + 1..columns {
+                let alpha: f64 = u[p].iter().map(|x| x * x).sum();
+                let beta: f64 = u[q].iter().map(|x| x * x).sum();
+                let gamma: f64 = u[p].iter().zip(u[q].iter()).map(|(x, y)| x * y).sum();
+                if gamma.abs() <= f64::EPSILON * (alpha * beta).sqrt() || gamma == 0.0 {
+                    continue;
+                }
+                rotated = true;
+                let zeta = (beta - alpha) / (2.0 * gamma);
+                let t = zeta.signum() / (zeta.abs() + (1.0 + zeta * zeta).sqrt());
+                let c = 1.0 / (1.0 + t * t).sqrt();
+                let s = c * t;
+                for r in 0..rows {
+                    let (x, y) = (u[p][r], u[q][r]);
+                    u[p][r] = c * x - s * y;
+                    u[q][r] = s * x + c * y;
+                }
+            }
+        }
+        if !rotated {
+            break;
+        }
+    }
+    let mut values: Vec<f64> = u
+        .iter()
+        .map(|col| col.iter().map(|x| x * x).sum::<f64>().sqrt())
+        .collect();
+    values.sort_by(|a, b| b.total_cmp(a));
+    values.truncate(rows.min(columns));
+    values
+}
+
+pub fn conditioning(matrix: &[Vec<f64>], columns: usize) -> Conditioning {
+    let singular_values = singular_values(matrix, columns);
+    let largest = singular_values.first().copied().unwrap_or(0.0);
+    let tol = rank_tolerance(largest, matrix.len(), columns);
+    let rank = singular_values.iter().filter(|s| **s > tol).count();
+    let condition =
+        (rank > 0 && rank == singular_values.len()).then(|| largest / singular_values[rank - 1]);
+    Conditioning {
+        rank,
+        condition,
+        singular_values,
+    }
+}
+
+/// Least-squares solution of `matrix * x = rhs` by Householder QR with
+/// column pivoting. Returns the numerical rank as the error when it falls
+/// short of the number of columns.
+pub fn qr_solve(matrix: &[Vec<f64>], rhs: &[f64]) -> Result<Vec<f64>, usize> {
+    let rows = matrix.len();
+    let columns = matrix.first().map(|r| r.len()).unwrap_or(0);
+    if rows < columns {
+        return Err(conditioning(matrix, columns).rank);
+    }
+    let mut a: Vec<Vec<f64>> = matrix.to_vec();
+    let mut b: Vec<f64> = rhs.to_vec();
+    let mut perm: Vec<usize> = (0..columns).collect();
+    let mut first_pivot = 0.0;
+
+    for k in 0..rows.min(columns) {
+        let norm = |a: &[Vec<f64>], c: usize| (k..rows).map(|r| a[r][c] * a[r][c]).sum::<f64>();
+        let pivot = (k..columns)
+            .max_by(|x, y| norm(&a, *x).total_cmp(&norm(&a, *y)))
+            .unwrap();
+        if pivot != k {
+            for row in a.iter_mut() {
+                row.swap(k, pivot);
+            }
+            perm.swap(k, pivot);
+        }
+        let length = norm(&a, k).sqrt();
+        if k == 0 {
+            first_pivot = length;
+        }
+        if length <= rank_tolerance(first_pivot, rows, columns) || length == 0.0 {
+            return Err(k);
+        }
+
+        let alpha = if a[k][k] > 0.0 { -length } else { length };
+        let mut v: Vec<f64> = (k..rows).map(|r| a[r][k]).collect();
+        v[0] -= alpha;
+        let v_norm: f64 = v.iter().map(|x| x * x).sum();
+        for c in k..columns {
+            let s = 2.0 * (k..rows).map(|r| v[r - k] * a[r][c]).sum::<f64>() / v_norm;
+            for r in k..rows {
+                a[r][c] -= s * v[r - k];
+            }
+        }
+        let s = 2.0 * (k..rows).map(|r| v[r - k] * b[r]).sum::<f64>() / v_norm;
+        for r in k..rows {
+            b[r] -= s * v[r - k];
+        }
+    }
+    let mut y = vec![0.0; columns];
+    for k in (0..columns).rev() {
+        let tail: f64 = (k + 1..columns).map(|c| a[k][c] * y[c]).sum();
+        y[k] = (b[k] - tail) / a[k][k];
+    }
+    let mut x = vec![0.0; columns];
+    for (k, col) in perm.iter().enumerate() {
+        x[*col] = y[k];
+    }
+    Ok(x)
+}
+
+pub fn transpose(matrix: &[Vec<f64>], columns: usize) -> Vec<Vec<f64>> {
+    (0..columns)
+        .map(|c| matrix.iter().map(|row| row[c]).collect())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: &[f64], b: &[f64]) -> bool {
+        a.len() == b.len() && a.iter().zip(b).all(|(x, y)| (x - y).abs() < 1e-12)
+    }
+
+    #[test]
+    fn diagonal_matrix_has_its_entries_as_singular_values() {
+        let matrix = vec![
+            vec![0.5, 0.0, 0.0],
+            vec![0.0, 4.0, 0.0],
+            vec![0.0, 0.0, 2.0],
+        ];
+        let result = conditioning(&matrix, 3);
+        assert!(close(&result.singular_values, &[4.0, 2.0, 0.5]));
+        assert_eq!(result.rank, 3);
+        assert!((result.condition.unwrap() - 8.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn rank_deficient_matrix_has_no_condition_number() {
+        let matrix = vec![vec![1.0, 2.0], vec![2.0, 4.0], vec![3.0, 6.0]];
+        let result = conditioning(&matrix, 2);
+        assert_eq!(result.rank, 1);
+        assert_eq!(result.condition, None);
+    }
+
+    #[test]
+    fn solves_a_consistent_overdetermined_system() {
+        let matrix = vec![vec![1.0, 0.0], vec![0.0, 1.0], vec![1.0, 1.0]];
+        let x = qr_solve(&matrix, &[1.0, 2.0, 3.0]).unwrap();
+        assert!(close(&x, &[1.0, 2.0]));
+    }
+
+    #[test]
+    fn singular_systems_report_their_rank() {
+        assert_eq!(
+            qr_solve(&[vec![1.0, 2.0], vec![2.0, 4.0]], &[1.0, 2.0]),
+            Err(1)
+        );
+        // Fewer rows than columns, and the rows themselves dependent.
+        assert_eq!(
+            qr_solve(&[vec![1.0, 2.0, 3.0], vec![2.0, 4.0, 6.0]], &[1.0, 2.0]),
+            Err(1)
+        );
+    }
+
+    #[test]
+    fn transposes_rows_and_columns() {
+        let matrix = vec![vec![1.0, 2.0, 3.0], vec![4.0, 5.0, 6.0]];
+        assert_eq!(
+            transpose(&matrix, 3),
+            vec![vec![1.0, 4.0], vec![2.0, 5.0], vec![3.0, 6.0]]
+        );
+    }
+}
@@ -0,0 +1,23 @@
+use std::collections::HashMap;
+
+pub fn collapse_formula(formula: &[(String, f64)]) -> HashMap<String, f64> {
+    let mut map = HashMap::new();
+    for (el, coeff) in formula {
+        *map.entry(el.clone()).or_insert(0.0) += *coeff;
+    }
+    map
+}
+
+pub fn molar_mass(
+    composition: &HashMap<String, f64>,
+    masses: &HashMap<String, f64>,
+) -> Result<f64, String> {
+    let mut total = 0.0;
+    for (el, coeff) in composition {
+        let mass = masses
+            .get(el)
+            .ok_or_else(|| format!("Missing atomic mass for {}", el))?;
+        total += coeff * mass;
+    }
+    Ok(total)
+}
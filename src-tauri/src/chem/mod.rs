@@ -1 +1,6 @@
+pub mod balance;
+pub mod equation;
+pub mod linalg;
+pub mod mass;
 pub mod parse;
+pub mod rational;
@@ -1,40 +1,185 @@
 use std::collections::HashSet;
 
-pub fn parse_formula(formula: &str) -> Result<Vec<(String, f64)>, String> {
-    let chars: Vec<char> = formula.chars().collect();
-    if chars.is_empty() {
-        return Err("Formula is empty".to_string());
+use crate::chem::rational::Rational;
+
+const HYDRATE_SEPARATORS: [char; 2] = ['·', '*'];
+
+/// Number type the parser reads coefficients into: `f64` for the float
+/// path, `Rational` for the exact one.
+trait Coefficient: Copy {
+    const ONE: Self;
+    /// Parses `12`, `0.25` or `1/3`.
+    fn parse(text: &str) -> Option<Self>;
+    fn checked_mul(self, other: Self) -> Option<Self>;
+}
+
+impl Coefficient for f64 {
+    const ONE: Self = 1.0;
+
+    fn parse(text: &str) -> Option<Self> {
+        let value = match text.split_once('/') {
+            Some((num, den)) => num.parse::<f64>().ok()? / den.parse::<f64>().ok()?,
+            None => text.parse().ok()?,
+        };
+        value.is_finite().then_some(value)
     }
-    let mut idx = 0usize;
+
+    fn checked_mul(self, other: Self) -> Option<Self> {
+        Some(self * other).filter(|v| v.is_finite())
+    }
+}
+
+impl Coefficient for Rational {
+    const ONE: Self = Rational::ONE;
+
+    fn parse(text: &str) -> Option<Self> {
+        Rational::parse(text)
+    }
+
+    fn checked_mul(self, other: Self) -> Option<Self> {
+        Rational::checked_mul(self, other)
+    }
+}
+
+/// Reads a decimal (`0.25`) or fraction (`1/3`) coefficient.
+fn read_number<T: Coefficient>(
+    chars: &[char],
+    idx: &mut usize,
+    formula: &str,
+) -> Result<Option<T>, String> {
+    let mut num = String::new();
+    while *idx < chars.len() && (chars[*idx].is_ascii_digit() || chars[*idx] == '.') {
+        num.push(chars[*idx]);
+        *idx += 1;
+    }
+    if num.is_empty() {
+        return Ok(None);
+    }
+    if *idx + 1 < chars.len() && chars[*idx] == '/' && chars[*idx + 1].is_ascii_digit() {
+        num.push('/');
+        *idx += 1;
+        while *idx < chars.len() && (chars[*idx].is_ascii_digit() || chars[*idx] == '.') {
+            num.push(chars[*idx]);
+            *idx += 1;
+        }
+    }
+    T::parse(&num)
+        .map(Some)
+        .ok_or_else(|| format!("Invalid number in {}", formula))
+}
+
+fn scale<T: Coefficient>(
+    items: Vec<(String, T)>,
+    multiplier: T,
+    formula: &str,
+) -> Result<Vec<(String, T)>, String> {
+    items
+        .into_iter()
+        .map(|(el, coeff)| {
+            coeff
+                .checked_mul(multiplier)
+                .map(|c| (el, c))
+                .ok_or_else(|| format!("Coefficient too large in {}", formula))
+        })
+        .collect()
+}
+
+fn parse_group<T: Coefficient>(
+    chars: &[char],
+    idx: &mut usize,
+    formula: &str,
+    closing: Option<char>,
+) -> Result<Vec<(String, T)>, String> {
     let mut out = Vec::new();
-    while idx < chars.len() {
-        let c = chars[idx];
+    while *idx < chars.len() {
+        let c = chars[*idx];
+        if Some(c) == closing {
+            return Ok(out);
+        }
+        if closing.is_none() && HYDRATE_SEPARATORS.contains(&c) {
+            return Ok(out);
+        }
+        if c == '(' || c == '[' {
+            let close = if c == '(' { ')' } else { ']' };
+            *idx += 1;
+            let inner = parse_group(chars, idx, formula, Some(close))?;
+            if *idx >= chars.len() {
+                return Err(format!("Unclosed bracket in {}", formula));
+            }
+            *idx += 1;
+            if inner.is_empty() {
+                return Err(format!("Empty group in {}", formula));
+            }
+            let multiplier = read_number(chars, idx, formula)?.unwrap_or(T::ONE);
+            out.extend(scale(inner, multiplier, formula)?);
+            continue;
+        }
         if !c.is_ascii_uppercase() {
             return Err(format!(
                 "Invalid formula at position {} in {}",
-                idx + 1,
+                *idx + 1,
                 formula
             ));
         }
         let mut symbol = String::new();
         symbol.push(c);
-        idx += 1;
-        if idx < chars.len() && chars[idx].is_ascii_lowercase() {
-            symbol.push(chars[idx]);
-            idx += 1;
+        *idx += 1;
+        if *idx < chars.len() && chars[*idx].is_ascii_lowercase() {
+            symbol.push(chars[*idx]);
+            *idx += 1;
         }
-        let mut num = String::new();
-        while idx < chars.len() && (chars[idx].is_ascii_digit() || chars[idx] == '.') {
-            num.push(chars[idx]);
-            idx += 1;
-        }
-        let coefficient = if num.is_empty() {
-            1.0
+        let coefficient = read_number(chars, idx, formula)?.unwrap_or(T::ONE);
+        out.push((symbol, coefficient));
+    }
+    if closing.is_some() {
+        return Err(format!("Unclosed bracket in {}", formula));
+    }
+    Ok(out)
+}
+
+/// Parses a formula into element/coefficient pairs in order of appearance.
+/// Supports nested `()`/`[]` groups, hydrates joined with `·` or `*`
+/// (e.g. `Co(NO3)2·6H2O`) and fractional coefficients (`Li1/3Mn2/3O2`);
+/// repeated elements are not merged.
+pub fn parse_formula(formula: &str) -> Result<Vec<(String, f64)>, String> {
+    parse_coefficients(formula)
+}
+
+/// `parse_formula` with coefficients kept as exact fractions.
+pub fn parse_formula_exact(formula: &str) -> Result<Vec<(String, Rational)>, String> {
+    parse_coefficients(formula)
+}
+
+fn parse_coefficients<T: Coefficient>(formula: &str) -> Result<Vec<(String, T)>, String> {
+    let chars: Vec<char> = formula.chars().collect();
+    if chars.is_empty() {
+        return Err("Formula is empty".to_string());
+    }
+    let mut idx = 0usize;
+    let mut out = Vec::new();
+    let mut first = true;
+    loop {
+        let multiplier = if first {
+            T::ONE
         } else {
-            num.parse::<f64>()
-                .map_err(|_| format!("Invalid number in {}", formula))?
+            read_number(&chars, &mut idx, formula)?.unwrap_or(T::ONE)
         };
-        out.push((symbol, coefficient));
+        let start = idx;
+        let segment = parse_group(&chars, &mut idx, formula, None)?;
+        if segment.is_empty() {
+            return Err(format!(
+                "Invalid formula at position {} in {}",
+                start + 1,
+                formula
+            ));
+        }
+        out.extend(scale(segment, multiplier, formula)?);
+        if idx >= chars.len() {
+            break;
+        }
+        // parse_group only stops early on a hydrate separator
+        idx += 1;
+        first = false;
     }
     Ok(out)
 }
@@ -49,3 +194,89 @@ pub fn ordered_unique_elements(parsed: &[(String, f64)]) -> Vec<String> {
     }
     out
 }
+
+pub fn format_coefficient(value: f64) -> String {
+    let text = format!("{:.4}", value);
+    text.trim_end_matches('0').trim_end_matches('.').to_string()
+}
+
+pub fn format_formula(parsed: &[(String, f64)]) -> String {
+    parsed
+        .iter()
+        .map(|(el, coeff)| {
+            if (coeff - 1.0).abs() < 1e-9 {
+                el.clone()
+            } else {
+                format!("{}{}", el, format_coefficient(*coeff))
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parsed(formula: &str) -> Vec<(String, f64)> {
+        parse_formula(formula).unwrap()
+    }
+
+    #[test]
+    fn parses_groups_and_hydrates_in_order() {
+        assert_eq!(
+            parsed("Co(NO3)2·6H2O"),
+            vec![
+                ("Co".to_string(), 1.0),
+                ("N".to_string(), 2.0),
+                ("O".to_string(), 6.0),
+                ("H".to_string(), 12.0),
+                ("O".to_string(), 6.0),
+            ]
+        );
+        assert_eq!(parsed("Ca3[PO4]2"), parsed("Ca3(PO4)2"));
+        assert_eq!(parsed("CuSO4*5H2O"), parsed("CuSO4·5H2O"));
+    }
+
+    #[test]
+    fn keeps_fractional_coefficients_exact() {
+        let exact = parse_formula_exact("Li1/3Mn2/3O2").unwrap();
+        assert_eq!(exact[0].1, Rational::new(1, 3).unwrap());
+        assert_eq!(exact[1].1, Rational::new(2, 3).unwrap());
+        assert_eq!(exact[2].1, Rational::from_integer(2));
+        let exact = parse_formula_exact("La0.7Sr0.3MnO3").unwrap();
+        assert_eq!(exact[0].1, Rational::new(7, 10).unwrap());
+    }
+
+    #[test]
+    fn long_decimals_parse_as_floats() {
+        // More digits than an i128 numerator holds; only the exact path
+        // needs them as a fraction.
+        let formula = format!("Li0.{}Mn2O4", "3".repeat(45));
+        let parsed = parse_formula(&formula).unwrap();
+        assert!((parsed[0].1 - 1.0 / 3.0).abs() < 1e-15);
+        assert!(parse_formula_exact(&formula).is_err());
+    }
+
+    #[test]
+    fn rejects_malformed_formulas() {
+        assert_eq!(parse_formula(""), Err("Formula is empty".to_string()));
+        assert_eq!(
+            parse_formula("Ba(CO3"),
+            Err("Unclosed bracket in Ba(CO3".to_string())
+        );
+        assert_eq!(
+            parse_formula("Ba()2"),
+            Err("Empty group in Ba()2".to_string())
+        );
+        assert_eq!(
+            parse_formula("bao"),
+            Err("Invalid formula at position 1 in bao".to_string())
+        );
+    }
+
+    #[test]
+    fn formats_trimmed_coefficients() {
+        assert_eq!(format_formula(&parsed("Ba1Ti1.50O3")), "BaTi1.5O3");
+        assert_eq!(format_coefficient(1.0 / 3.0), "0.3333");
+    }
+}
@@ -0,0 +1,183 @@
+use std::collections::HashMap;
+use std::fmt;
+
+/// Exact fraction in lowest terms with a positive denominator. Arithmetic is
+/// checked; `None` means the result no longer fits in an i128.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Rational {
+    num: i128,
+    den: i128,
+}
+
+fn gcd(a: i128, b: i128) -> i128 {
+    let (mut a, mut b) = (a.abs(), b.abs());
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+    a
+}
+
+impl Rational {
+    pub const ZERO: Rational = Rational { num: 0, den: 1 };
+    pub const ONE: Rational = Rational { num: 1, den: 1 };
+
+    pub fn new(num: i128, den: i128) -> Option<Self> {
+        if den == 0 {
+            return None;
+        }
+        let divisor = gcd(num, den).max(1);
+        let sign = if den < 0 { -1 } else { 1 };
+        Some(Rational {
+            num: sign * num / divisor,
+            den: sign * den / divisor,
+        })
+    }
+
+    pub fn from_integer(n: i128) -> Self {
+        Rational { num: n, den: 1 }
+    }
+
+    /// Parses `12`, `0.25` or `1/3` exactly.
+    pub fn parse(text: &str) -> Option<Self> {
+        if let Some((num, den)) = text.split_once('/') {
+            return Rational::parse(num)?.checked_div(Rational::parse(den)?);
+        }
+        let (int_part, frac_part) = text.split_once('.').unwrap_or((text, ""));
+        if int_part.is_empty() && frac_part.is_empty() {
+            return None;
+        }
+        let digits = format!("{}{}", int_part, frac_part);
+        if !digits.chars().all(|c| c.is_ascii_digit()) {
+            return None;
+        }
+        let num: i128 = digits.parse().ok()?;
+        let den = 10_i128.checked_pow(frac_part.len() as u32)?;
+        Rational::new(num, den)
+    }
+
+    pub fn to_f64(self) -> f64 {
+        self.num as f64 / self.den as f64
+    }
+
+    pub fn is_zero(self) -> bool {
+        self.num == 0
+    }
+
+    pub fn is_negative(self) -> bool {
+        self.num < 0
+    }
+
+    pub fn checked_add(self, other: Self) -> Option<Self> {
+        let num = self
+            .num
+            .checked_mul(other.den)?
+            .checked_add(other.num.checked_mul(self.den)?)?;
+        Rational::new(num, self.den.checked_mul(other.den)?)
+    }
+
+    pub fn checked_sub(self, other: Self) -> Option<Self> {
+        self.checked_add(Rational {
+            num: -other.num,
+            den: other.den,
+        })
+    }
+
+    pub fn checked_mul(self, other: Self) -> Option<Self> {
+        // Cross-reduce first to keep intermediates small.
+        let g1 = gcd(self.num, other.den).max(1);
+        let g2 = gcd(other.num, self.den).max(1);
+        Rational::new(
+            (self.num / g1).checked_mul(other.num / g2)?,
+            (self.den / g2).checked_mul(other.den / g1)?,
+        )
+    }
+
+    pub fn checked_div(self, other: Self) -> Option<Self> {
+        if other.is_zero() {
+            return None;
+        }
+        self.checked_mul(Rational::new(other.den, other.num)?)
+    }
+}
+
+impl fmt::Display for Rational {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.den == 1 {
+            write!(f, "{}", self.num)
+        } else {
+            write!(f, "{}/{}", self.num, self.den)
+        }
+    }
+}
+
+pub fn collapse_exact(formula: &[(String, Rational)]) -> Result<HashMap<String, Rational>, String> {
+    let mut map: HashMap<String, Rational> = HashMap::new();
+    for (el, coeff) in formula {
+        let total = map.entry(el.clone()).or_insert(Rational::ZERO);
+        *total = total
+            .checked_add(*coeff)
+            .ok_or("Coefficient too large for exact arithmetic")?;
+    }
+    Ok(map)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ratio(num: i128, den: i128) -> Rational {
+        Rational::new(num, den).unwrap()
+    }
+
+    #[test]
+    fn reduces_to_lowest_terms_with_positive_denominator() {
+        assert_eq!(ratio(4, -6), ratio(-2, 3));
+        assert_eq!(ratio(0, 5), Rational::ZERO);
+        assert_eq!(Rational::new(1, 0), None);
+    }
+
+    #[test]
+    fn parses_integers_decimals_and_fractions() {
+        assert_eq!(Rational::parse("12"), Some(Rational::from_integer(12)));
+        assert_eq!(Rational::parse("0.25"), Some(ratio(1, 4)));
+        assert_eq!(Rational::parse(".5"), Some(ratio(1, 2)));
+        assert_eq!(Rational::parse("2/6"), Some(ratio(1, 3)));
+        assert_eq!(Rational::parse("1/0"), None);
+        assert_eq!(Rational::parse("."), None);
+        assert_eq!(Rational::parse("1e3"), None);
+    }
+
+    #[test]
+    fn arithmetic_is_exact_and_checked() {
+        let third = ratio(1, 3);
+        assert_eq!(
+            third.checked_add(third).unwrap().checked_add(third),
+            Some(Rational::ONE)
+        );
+        assert_eq!(Rational::ONE.checked_sub(third), Some(ratio(2, 3)));
+        assert_eq!(ratio(2, 3).checked_mul(ratio(3, 4)), Some(ratio(1, 2)));
+        assert_eq!(third.checked_div(Rational::ZERO), None);
+        assert_eq!(
+            Rational::from_integer(i128::MAX).checked_add(Rational::ONE),
+            None
+        );
+    }
+
+    #[test]
+    fn displays_integers_without_denominator() {
+        assert_eq!(Rational::from_integer(3).to_string(), "3");
+        assert_eq!(ratio(-1, 3).to_string(), "-1/3");
+    }
+
+    #[test]
+    fn collapses_repeated_elements() {
+        let formula = vec![
+            ("O".to_string(), Rational::from_integer(6)),
+            ("H".to_string(), Rational::from_integer(12)),
+            ("O".to_string(), ratio(1, 2)),
+        ];
+        let collapsed = collapse_exact(&formula).unwrap();
+        assert_eq!(collapsed["O"], ratio(13, 2));
+        assert_eq!(collapsed["H"], Rational::from_integer(12));
+    }
+}
@@ -0,0 +1,222 @@
+use serde::Deserialize;
+use std::collections::HashMap;
+
+use crate::chem::balance::Reagent;
+use crate::chem::mass::{collapse_formula, molar_mass};
+use crate::chem::parse::parse_formula;
+use crate::commands::calc_helpers::format_value;
+use crate::commands::empirical_formula::ElementAmount;
+
+/// Non-metals and the metalloids B, Si, Ge, As, Sb and Te; these are not
+/// counted as metal cations by the `metal_ratio` rule.
+const NON_METALS: [&str; 24] = [
+    "H", "He", "B", "C", "N", "O", "F", "Ne", "Si", "P", "S", "Cl", "Ar", "Ge", "As", "Se", "Br",
+    "Kr", "Sb", "Te", "I", "Xe", "At", "Rn",
+];
+
+/// Propellant-chemistry valences used by the Jain method: C +4, H +1, O -2,
+/// N 0 and metals in their usual cationic state.
+const DEFAULT_VALENCES: [(&str, f64); 49] = [
+    ("H", 1.0),
+    ("C", 4.0),
+    ("N", 0.0),
+    ("O", -2.0),
+    ("Li", 1.0),
+    ("Na", 1.0),
+    ("K", 1.0),
+    ("Rb", 1.0),
+    ("Cs", 1.0),
+    ("Ag", 1.0),
+    ("Be", 2.0),
+    ("Mg", 2.0),
+    ("Ca", 2.0),
+    ("Sr", 2.0),
+    ("Ba", 2.0),
+    ("Mn", 2.0),
+    ("Co", 2.0),
+    ("Ni", 2.0),
+    ("Cu", 2.0),
+    ("Zn", 2.0),
+    ("Cd", 2.0),
+    ("Pb", 2.0),
+    ("Al", 3.0),
+    ("Ga", 3.0),
+    ("In", 3.0),
+    ("Sc", 3.0),
+    ("Y", 3.0),
+    ("La", 3.0),
+    ("Ce", 3.0),
+    ("Pr", 3.0),
+    ("Nd", 3.0),
+    ("Sm", 3.0),
+    ("Eu", 3.0),
+    ("Gd", 3.0),
+    ("Tb", 3.0),
+    ("Dy", 3.0),
+    ("Ho", 3.0),
+    ("Er", 3.0),
+    ("Tm", 3.0),
+    ("Yb", 3.0),
+    ("Lu", 3.0),
+    ("Fe", 3.0),
+    ("Cr", 3.0),
+    ("Bi", 3.0),
+    ("Ti", 4.0),
+    ("Zr", 4.0),
+    ("Hf", 4.0),
+    ("Sn", 4.0),
+    ("Si", 4.0),
+];
+
+#[derive(Deserialize, Clone)]
+pub struct AdditiveRule {
+    pub reagent: String,
+    #[serde(default = "default_mode")]
+    pub mode: String,
+    #[serde(default = "default_ratio")]
+    pub ratio: f64,
+    #[serde(default)]
+    pub valences: Vec<ElementAmount>,
+}
+
+pub struct AdditiveAmount {
+    pub name: String,
+    pub moles: f64,
+    pub molar_mass: f64,
+}
+
+fn default_mode() -> String {
+    "metal_ratio".to_string()
+}
+
+fn default_ratio() -> f64 {
+    1.0
+}
+
+pub fn is_metal(element: &str) -> bool {
+    !NON_METALS.contains(&element)
+}
+
+fn formula_valence(
+    composition: &HashMap<String, f64>,
+    valences: &HashMap<String, f64>,
+    name: &str,
+) -> Result<f64, String> {
+    let mut total = 0.0;
+    for (el, coeff) in composition {
+        let valence = valences
+            .get(el)
+            .ok_or_else(|| format!("No valence known for {} in {}; provide one", el, name))?;
+        total += coeff * valence;
+    }
+    Ok(total)
+}
+
+/// Evaluates additive rules once the metal balance is solved. `amounts` are
+/// the solved moles of `reagents`.
+pub fn evaluate_additives(
+    rules: &[AdditiveRule],
+    reagents: &[Reagent],
+    amounts: &[f64],
+    masses: &HashMap<String, f64>,
+    explanation: &mut Vec<String>,
+) -> Result<Vec<AdditiveAmount>, String> {
+    let mut out = Vec::new();
+    if rules.is_empty() {
+        return Ok(out);
+    }
+
+    let total_metal: f64 = reagents
+        .iter()
+        .zip(amounts.iter())
+        .map(|(r, n)| {
+            n * r
+                .composition
+                .iter()
+                .filter(|(el, _)| is_metal(el))
+                .map(|(_, coeff)| coeff)
+                .sum::<f64>()
+        })
+        .sum();
+
+    for rule in rules {
+        let name = rule.reagent.trim();
+        if name.is_empty() {
+            continue;
+        }
+        if rule.ratio <= 0.0 {
+            return Err(format!("Ratio for {} must be positive", name));
+        }
+        let composition = collapse_formula(&parse_formula(name)?);
+        let additive_molar_mass = molar_mass(&composition, masses)?;
+        let moles = match rule.mode.as_str() {
+            "metal_ratio" => {
+                if total_metal <= 0.0 {
+                    return Err("No metal cations to relate additive ratio to".to_string());
+                }
+                let moles = total_metal * rule.ratio;
+                explanation.push(format!(
+                    "Additive {}: {} mol per mol metal cations -> n = {} x {} mol = {} mol",
+                    name,
+                    rule.ratio,
+                    rule.ratio,
+                    format_value(total_metal),
+                    format_value(moles)
+                ));
+                moles
+            }
+            "jain" => {
+                let mut valences: HashMap<String, f64> = DEFAULT_VALENCES
+                    .iter()
+                    .map(|(el, v)| (el.to_string(), *v))
+                    .collect();
+                for item in &rule.valences {
+                    valences.insert(item.element.trim().to_string(), item.value);
+                }
+                let mut oxidizer_total = 0.0;
+                let mut terms = Vec::new();
+                for (reagent, n) in reagents.iter().zip(amounts.iter()) {
+                    if n.abs() < 1e-12 {
+                        continue;
+                    }
+                    let valence = formula_valence(&reagent.composition, &valences, &reagent.name)?;
+                    oxidizer_total += n * valence;
+                    terms.push(format!(
+                        "{} ({}) x {} mol",
+                        reagent.name,
+                        valence,
+                        format_value(*n)
+                    ));
+                }
+                let fuel_valence = formula_valence(&composition, &valences, name)?;
+                if fuel_valence <= 0.0 {
+                    return Err(format!("{} has no reducing valence", name));
+                }
+                if oxidizer_total >= 0.0 {
+                    return Err(format!(
+                        "Starting materials are not oxidizing; cannot balance {}",
+                        name
+                    ));
+                }
+                let moles = -oxidizer_total / fuel_valence * rule.ratio;
+                explanation.push(format!(
+                    "Additive {} (Jain): oxidizer valence {} = {}; fuel valence {}; phi = {} -> n = {} mol",
+                    name,
+                    terms.join(" + "),
+                    format_value(oxidizer_total),
+                    fuel_valence,
+                    rule.ratio,
+                    format_value(moles)
+                ));
+                moles
+            }
+            other => return Err(format!("Unknown additive mode: {}", other)),
+        };
+        out.push(AdditiveAmount {
+            name: name.to_string(),
+            moles,
+            molar_mass: additive_molar_mass,
+        });
+    }
+    Ok(out)
+}
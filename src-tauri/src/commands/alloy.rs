@@ -0,0 +1,412 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::chem::parse::parse_formula;
+use crate::commands::empirical_formula::ElementAmount;
+use crate::commands::fetch_elements::get_atomic_masses;
+use crate::commands::settings::read_settings;
+
+#[derive(Deserialize)]
+pub struct AlloyInput {
+    #[serde(default)]
+    pub spec: Option<String>,
+    #[serde(default)]
+    pub composition: Vec<ElementAmount>,
+    #[serde(default)]
+    pub basis: Option<String>,
+    pub charge_mass: f64,
+    #[serde(default)]
+    pub evaporation_loss: Vec<ElementAmount>,
+    #[serde(default)]
+    pub actual_masses: Vec<ElementAmount>,
+    #[serde(default)]
+    pub button_mass: Option<f64>,
+}
+
+#[derive(Serialize)]
+pub struct AlloyElement {
+    pub element: String,
+    pub atomic_percent: f64,
+    pub weight_percent: f64,
+    pub nominal_mass: f64,
+    pub loss_percent: f64,
+    pub mass: f64,
+}
+
+#[derive(Serialize)]
+pub struct AlloyActual {
+    pub element: String,
+    pub mass: f64,
+    pub weight_percent: f64,
+    pub atomic_percent: f64,
+    pub atomic_percent_delta: f64,
+}
+
+#[derive(Serialize)]
+pub struct AlloyOutput {
+    pub basis: String,
+    pub charge_mass: f64,
+    pub total_mass: f64,
+    pub elements: Vec<AlloyElement>,
+    pub actual: Option<Vec<AlloyActual>>,
+    pub melting_loss: Option<f64>,
+    pub explanation: Vec<String>,
+}
+
+/// Parses `Ti-6Al-4V` style specifications: the first element is the
+/// balance and every further part is `<amount><element>`.
+fn parse_hyphenated_spec(spec: &str) -> Result<Vec<(String, f64)>, String> {
+    let mut parts = spec.split('-').map(str::trim);
+    let base = parts.next().unwrap_or_default();
+    if base.is_empty() {
+        return Err(format!("Missing balance element in {}", spec));
+    }
+    let mut out = Vec::new();
+    for part in parts {
+        let split = part
+            .find(|c: char| c.is_ascii_uppercase())
+            .ok_or_else(|| format!("Invalid alloy component {} in {}", part, spec))?;
+        let (amount, element) = part.split_at(split);
+        let amount = amount
+            .parse::<f64>()
+            .map_err(|_| format!("Invalid amount in {} of {}", part, spec))?;
+        let parsed = parse_formula(element)?;
+        if parsed.len() != 1 || parsed[0].1 != 1.0 {
+            return Err(format!("Invalid alloy component {} in {}", part, spec));
+        }
+        out.push((parsed[0].0.clone(), amount));
+    }
+    let listed: f64 = out.iter().map(|(_, v)| v).sum();
+    if listed >= 100.0 {
+        return Err(format!("Alloying additions in {} exceed 100%", spec));
+    }
+    let parsed_base = parse_formula(base)?;
+    if parsed_base.len() != 1 || parsed_base[0].1 != 1.0 {
+        return Err(format!("Invalid balance element {} in {}", base, spec));
+    }
+    out.insert(0, (parsed_base[0].0.clone(), 100.0 - listed));
+    Ok(out)
+}
+
+/// Atomic and weight fractions of `amounts` given in at% or wt%.
+fn fractions(
+    elements: &[String],
+    amounts: &[f64],
+    by_weight: bool,
+    masses: &HashMap<String, f64>,
+) -> (Vec<f64>, Vec<f64>) {
+    let total: f64 = amounts.iter().sum();
+    if by_weight {
+        let weight: Vec<f64> = amounts.iter().map(|v| v / total).collect();
+        let moles: Vec<f64> = elements
+            .iter()
+            .zip(weight.iter())
+            .map(|(el, w)| w / masses[el])
+            .collect();
+        let mole_total: f64 = moles.iter().sum();
+        (moles.iter().map(|n| n / mole_total).collect(), weight)
+    } else {
+        let atomic: Vec<f64> = amounts.iter().map(|v| v / total).collect();
+        let grams: Vec<f64> = elements
+            .iter()
+            .zip(atomic.iter())
+            .map(|(el, x)| x * masses[el])
+            .collect();
+        let gram_total: f64 = grams.iter().sum();
+        (atomic, grams.iter().map(|g| g / gram_total).collect())
+    }
+}
+
+fn amounts_to_map(amounts: &[ElementAmount]) -> HashMap<String, f64> {
+    amounts
+        .iter()
+        .map(|a| (a.element.trim().to_string(), a.value))
+        .collect()
+}
+
+#[tauri::command]
+pub async fn alloy_charge(input: AlloyInput) -> Result<AlloyOutput, String> {
+    if input.charge_mass <= 0.0 {
+        return Err("Charge mass must be positive".to_string());
+    }
+    let spec = input.spec.as_deref().map(str::trim).unwrap_or_default();
+    let hyphenated = spec.contains('-');
+    let parsed: Vec<(String, f64)> = if !spec.is_empty() {
+        if hyphenated {
+            parse_hyphenated_spec(spec)?
+        } else {
+            parse_formula(spec)?
+        }
+    } else {
+        input
+            .composition
+            .iter()
+            .filter(|a| !a.element.trim().is_empty())
+            .map(|a| (a.element.trim().to_string(), a.value))
+            .collect()
+    };
+    if parsed.is_empty() {
+        return Err("No alloy composition provided".to_string());
+    }
+    let basis = match input.basis.as_deref() {
+        Some(b) => b.to_string(),
+        None if hyphenated => "wt".to_string(),
+        None => "at".to_string(),
+    };
+    let by_weight = match basis.as_str() {
+        "wt" => true,
+        "at" => false,
+        other => return Err(format!("Unknown composition basis: {}", other)),
+    };
+
+    let masses = get_atomic_masses().await?;
+    let precision = read_settings()?.precision();
+    let mut elements: Vec<String> = Vec::new();
+    let mut amounts: Vec<f64> = Vec::new();
+    for (el, value) in parsed {
+        if value <= 0.0 {
+            return Err(format!("Amount for {} must be positive", el));
+        }
+        if !masses.contains_key(&el) {
+            return Err(format!("Missing atomic mass for {}", el));
+        }
+        if let Some(idx) = elements.iter().position(|e| *e == el) {
+            amounts[idx] += value;
+        } else {
+            elements.push(el);
+            amounts.push(value);
+        }
+    }
+
+    let (atomic, weight) = fractions(&elements, &amounts, by_weight, &masses);
+
+    let losses = amounts_to_map(&input.evaporation_loss);
+    for (el, loss) in losses.iter() {
+        if !elements.contains(el) {
+            return Err(format!(
+                "Evaporation loss given for {} which is not in the alloy",
+                el
+            ));
+        }
+        if !(0.0..100.0).contains(loss) {
+            return Err(format!(
+                "Evaporation loss for {} must be between 0 and 100%",
+                el
+            ));
+        }
+    }
+
+    let mut rows = Vec::new();
+    for (idx, el) in elements.iter().enumerate() {
+        let nominal_mass = weight[idx] * input.charge_mass;
+        let loss_percent = losses.get(el).copied().unwrap_or(0.0);
+        rows.push(AlloyElement {
+            element: el.clone(),
+            atomic_percent: atomic[idx] * 100.0,
+            weight_percent: weight[idx] * 100.0,
+            nominal_mass,
+            loss_percent,
+            mass: nominal_mass / (1.0 - loss_percent / 100.0),
+        });
+    }
+    let total_mass: f64 = rows.iter().map(|r| r.mass).sum();
+
+    let mut explanation = Vec::new();
+    explanation.push(format!(
+        "Composition ({}% input): {}",
+        basis,
+        rows.iter()
+            .map(|r| format!(
+                "{} {:.4} at% / {:.4} wt%",
+                r.element, r.atomic_percent, r.weight_percent
+            ))
+            .collect::<Vec<_>>()
+            .join(", ")
+    ));
+    explanation.push(format!(
+        "Nominal masses ({}) for {} charge: {}",
+        precision.mass_unit.symbol(),
+        precision.display_mass(input.charge_mass),
+        rows.iter()
+            .map(|r| format!("{}={}", r.element, precision.format_mass(r.nominal_mass)))
+            .collect::<Vec<_>>()
+            .join(", ")
+    ));
+    if !losses.is_empty() {
+        explanation.push(format!(
+            "Evaporation compensation m / (1 - loss): {}",
+            rows.iter()
+                .filter(|r| r.loss_percent > 0.0)
+                .map(|r| format!(
+                    "{} {}% -> {}",
+                    r.element,
+                    r.loss_percent,
+                    precision.display_mass(r.mass)
+                ))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ));
+    }
+
+    let actual = if input.actual_masses.is_empty() {
+        None
+    } else {
+        if let Some(bad) = input
+            .actual_masses
+            .iter()
+            .find(|a| !a.value.is_finite() || a.value < 0.0)
+        {
+            return Err(format!(
+                "Weighed mass for {} must be zero or positive",
+                bad.element.trim()
+            ));
+        }
+        let weighed = amounts_to_map(&input.actual_masses);
+        for el in weighed.keys() {
+            if !elements.contains(el) {
+                return Err(format!(
+                    "Weighed mass given for {} which is not in the alloy",
+                    el
+                ));
+            }
+        }
+        let retained: Vec<f64> = elements
+            .iter()
+            .map(|el| {
+                let loss = losses.get(el).copied().unwrap_or(0.0);
+                weighed.get(el).copied().unwrap_or(0.0) * (1.0 - loss / 100.0)
+            })
+            .collect();
+        let mass_total: f64 = retained.iter().sum();
+        let mole_total: f64 = elements
+            .iter()
+            .zip(retained.iter())
+            .map(|(el, m)| m / masses[el])
+            .sum();
+        if mass_total <= 0.0 {
+            return Err("Weighed masses must be positive".to_string());
+        }
+        let rows: Vec<AlloyActual> = elements
+            .iter()
+            .zip(retained.iter())
+            .enumerate()
+            .map(|(idx, (el, m))| {
+                let atomic_percent = m / masses[el] / mole_total * 100.0;
+                AlloyActual {
+                    element: el.clone(),
+                    mass: weighed.get(el).copied().unwrap_or(0.0),
+                    weight_percent: m / mass_total * 100.0,
+                    atomic_percent,
+                    atomic_percent_delta: atomic_percent - atomic[idx] * 100.0,
+                }
+            })
+            .collect();
+        explanation.push(format!(
+            "Expected button composition from weighed pieces: {}",
+            rows.iter()
+                .map(|r| format!(
+                    "{} {:.4} at% ({:+.4})",
+                    r.element, r.atomic_percent, r.atomic_percent_delta
+                ))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ));
+        Some(rows)
+    };
+
+    let melting_loss = match (input.button_mass, &actual) {
+        (Some(button), Some(rows)) => {
+            let charged: f64 = rows.iter().map(|r| r.mass).sum();
+            Some(charged - button)
+        }
+        (Some(button), None) => Some(total_mass - button),
+        _ => None,
+    };
+    if let Some(loss) = melting_loss {
+        explanation.push(format!(
+            "Melting loss: {} (button {})",
+            precision.display_mass(loss),
+            precision.display_mass(input.button_mass.unwrap_or_default())
+        ));
+    }
+
+    Ok(AlloyOutput {
+        basis,
+        charge_mass: input.charge_mass,
+        total_mass,
+        elements: rows,
+        actual,
+        melting_loss,
+        explanation,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn masses() -> HashMap<String, f64> {
+        [
+            ("Fe", 55.845),
+            ("Co", 58.933),
+            ("Ti", 47.867),
+            ("Al", 26.982),
+            ("V", 50.942),
+        ]
+        .iter()
+        .map(|(el, m)| (el.to_string(), *m))
+        .collect()
+    }
+
+    fn split(parsed: Vec<(String, f64)>) -> (Vec<String>, Vec<f64>) {
+        parsed.into_iter().unzip()
+    }
+
+    fn assert_close(actual: &[f64], expected: &[f64]) {
+        for (a, e) in actual.iter().zip(expected.iter()) {
+            assert!((a - e).abs() < 1e-5, "{:?} != {:?}", actual, expected);
+        }
+    }
+
+    #[test]
+    fn hyphenated_spec_puts_the_balance_first() {
+        assert_eq!(
+            parse_hyphenated_spec("Ti-6Al-4V").unwrap(),
+            vec![
+                ("Ti".to_string(), 90.0),
+                ("Al".to_string(), 6.0),
+                ("V".to_string(), 4.0)
+            ]
+        );
+        assert_eq!(
+            parse_hyphenated_spec("Ti2-6Al-4V").err(),
+            Some("Invalid balance element Ti2 in Ti2-6Al-4V".to_string())
+        );
+        assert_eq!(
+            parse_hyphenated_spec("Ti-60Al-40V").err(),
+            Some("Alloying additions in Ti-60Al-40V exceed 100%".to_string())
+        );
+    }
+
+    #[test]
+    fn atomic_percent_converts_to_weight_percent() {
+        let (elements, amounts) = split(parse_formula("Fe80Co20").unwrap());
+        let (atomic, weight) = fractions(&elements, &amounts, false, &masses());
+        assert_close(&atomic, &[0.8, 0.2]);
+        assert_close(&weight, &[0.791249, 0.208751]);
+
+        let (back, _) = fractions(&elements, &weight, true, &masses());
+        assert_close(&back, &atomic);
+    }
+
+    #[test]
+    fn weight_percent_converts_to_atomic_percent() {
+        let (elements, amounts) = split(parse_hyphenated_spec("Ti-6Al-4V").unwrap());
+        let (atomic, weight) = fractions(&elements, &amounts, true, &masses());
+        assert_close(&weight, &[0.9, 0.06, 0.04]);
+        assert_close(&atomic, &[0.862046, 0.101953, 0.036000]);
+
+        let (_, back) = fractions(&elements, &atomic, false, &masses());
+        assert_close(&back, &weight);
+    }
+}
@@ -0,0 +1,182 @@
+use serde::Serialize;
+
+use crate::commands::calc_helpers::{format_value, Precision};
+use crate::commands::calculate::ReagentResult;
+use crate::commands::inventory::weighed_mass;
+
+/// Host-to-dopant mass ratios offered for a master batch.
+const DILUTIONS: [f64; 9] = [1.0, 2.0, 5.0, 10.0, 20.0, 50.0, 100.0, 200.0, 500.0];
+
+/// A pre-mix of a small reagent with a host precursor, weighed once in a
+/// quantity the balance handles and then aliquoted.
+#[derive(Serialize)]
+pub struct MasterBatch {
+    pub dopant: String,
+    pub host: String,
+    /// Grams of host per gram of dopant in the pre-mix.
+    pub dilution: f64,
+    pub batch_dopant_mass: f64,
+    pub batch_host_mass: f64,
+    /// Pre-mix to weigh into this synthesis.
+    pub aliquot_mass: f64,
+    /// Host still to weigh on its own.
+    pub host_mass: f64,
+}
+
+#[derive(Serialize)]
+pub struct BatchAdvice {
+    pub min_weighable: f64,
+    pub small_reagents: Vec<String>,
+    pub minimum_target_mass: f64,
+    pub master_batches: Vec<MasterBatch>,
+}
+
+/// Smallest mass weighed within `relative_error` percent.
+pub fn min_weighable_mass(precision: &Precision, relative_error: Option<f64>) -> f64 {
+    match relative_error {
+        Some(e) => precision.mass(precision.readability / (e / 100.0)),
+        None => precision.min_weighable(),
+    }
+}
+
+fn round_up(value: f64, precision: &Precision) -> f64 {
+    precision.mass((value / precision.readability).ceil() * precision.readability)
+}
+
+/// Suggests how to handle reagents below `min_weighable`: the target mass
+/// that lifts every one of them over the limit, and for each a master batch
+/// with the largest weighable solid as host.
+pub fn batch_advice(
+    reagents: &[ReagentResult],
+    target_mass: f64,
+    min_weighable: f64,
+    precision: &Precision,
+    explanation: &mut Vec<String>,
+) -> Option<BatchAdvice> {
+    let small: Vec<(&ReagentResult, f64)> = reagents
+        .iter()
+        .filter(|r| r.moles > 0.0)
+        .filter_map(|r| weighed_mass(r.mass, r.solution_mass, r.volume_ml).map(|m| (r, m)))
+        .filter(|(_, m)| *m < min_weighable)
+        .collect();
+    if small.is_empty() {
+        return None;
+    }
+
+    let scale = small
+        .iter()
+        .map(|(_, m)| min_weighable / m)
+        .fold(1.0, f64::max);
+    let minimum_target_mass = round_up(target_mass * scale, precision);
+    explanation.push(format!(
+        "Minimum batch: {} of target puts every reagent at or above {} (x{})",
+        precision.display_mass(minimum_target_mass),
+        precision.display_mass(min_weighable),
+        format_value(scale)
+    ));
+
+    let host = reagents
+        .iter()
+        .filter(|r| r.moles > 0.0 && r.volume_ml.is_none())
+        .filter(|r| !small.iter().any(|(s, _)| s.reagent == r.reagent))
+        .max_by(|a, b| a.mass.total_cmp(&b.mass));
+    let mut master_batches = Vec::new();
+    let mut host_left = host.map(|h| h.mass).unwrap_or(0.0);
+    for (dopant, mass) in &small {
+        let Some(host) = host.filter(|_| dopant.volume_ml.is_none()) else {
+            explanation.push(format!(
+                "Master batch for {}: no weighable solid host; scale up or use a stock solution",
+                dopant.reagent
+            ));
+            continue;
+        };
+        let Some(dilution) = DILUTIONS
+            .iter()
+            .copied()
+            .find(|k| mass * (1.0 + k) >= min_weighable)
+        else {
+            explanation.push(format!(
+                "Master batch for {}: more than 1:{} dilution needed; scale up instead",
+                dopant.reagent,
+                DILUTIONS[DILUTIONS.len() - 1]
+            ));
+            continue;
+        };
+        let host_in_aliquot = mass * dilution;
+        if host_in_aliquot > host_left {
+            explanation.push(format!(
+                "Master batch for {}: a 1:{} pre-mix would carry more {} than the recipe needs; scale up instead",
+                dopant.reagent, dilution, host.reagent
+            ));
+            continue;
+        }
+        host_left -= host_in_aliquot;
+        let batch_dopant_mass = round_up(min_weighable.max(*mass), precision);
+        let batch = MasterBatch {
+            dopant: dopant.reagent.clone(),
+            host: host.reagent.clone(),
+            dilution,
+            batch_dopant_mass,
+            batch_host_mass: precision.mass(batch_dopant_mass * dilution),
+            aliquot_mass: precision.mass(mass * (1.0 + dilution)),
+            host_mass: 0.0,
+        };
+        master_batches.push(batch);
+    }
+    for batch in master_batches.iter_mut() {
+        batch.host_mass = precision.mass(host_left);
+    }
+    for batch in &master_batches {
+        explanation.push(format!(
+            "Master batch 1:{} {}:{}: mix {} {} with {} {}, then weigh {} of the pre-mix and {} {}",
+            batch.dilution,
+            batch.dopant,
+            batch.host,
+            precision.display_mass(batch.batch_dopant_mass),
+            batch.dopant,
+            precision.display_mass(batch.batch_host_mass),
+            batch.host,
+            precision.display_mass(batch.aliquot_mass),
+            precision.display_mass(batch.host_mass),
+            batch.host
+        ));
+    }
+
+    Some(BatchAdvice {
+        min_weighable,
+        small_reagents: small.iter().map(|(r, _)| r.reagent.clone()).collect(),
+        minimum_target_mass,
+        master_batches,
+    })
+}
+
+/// Combines the advice of sub-calculations weighed separately (one per
+/// phase). The minimum batch is the total target scaled by the largest
+/// factor any part needs.
+pub fn merge_batch_advice(
+    parts: Vec<(BatchAdvice, f64)>,
+    target_mass: f64,
+    precision: &Precision,
+) -> Option<BatchAdvice> {
+    let scale = parts
+        .iter()
+        .map(|(advice, part_mass)| advice.minimum_target_mass / part_mass)
+        .fold(None, |acc: Option<f64>, s| {
+            Some(acc.map_or(s, |a| a.max(s)))
+        })?;
+    let mut merged = BatchAdvice {
+        min_weighable: parts[0].0.min_weighable,
+        small_reagents: Vec::new(),
+        minimum_target_mass: round_up(target_mass * scale, precision),
+        master_batches: Vec::new(),
+    };
+    for (advice, _) in parts {
+        for reagent in advice.small_reagents {
+            if !merged.small_reagents.contains(&reagent) {
+                merged.small_reagents.push(reagent);
+            }
+        }
+        merged.master_batches.extend(advice.master_batches);
+    }
+    Some(merged)
+}
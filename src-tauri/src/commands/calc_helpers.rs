@@ -0,0 +1,239 @@
+/// A target quantity: a mass in g or an amount of substance in mol.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Quantity {
+    Mass(f64),
+    Amount(f64),
+}
+
+impl Quantity {
+    /// Mass in g of a substance with the given molar mass.
+    pub fn grams(&self, molar_mass: f64) -> f64 {
+        match self {
+            Quantity::Mass(g) => *g,
+            Quantity::Amount(mol) => mol * molar_mass,
+        }
+    }
+}
+
+const QUANTITY_UNITS: [(&str, f64, bool); 9] = [
+    ("ug", 1e-6, false),
+    ("µg", 1e-6, false),
+    ("mg", 1e-3, false),
+    ("g", 1.0, false),
+    ("kg", 1e3, false),
+    ("umol", 1e-6, true),
+    ("µmol", 1e-6, true),
+    ("mmol", 1e-3, true),
+    ("mol", 1.0, true),
+];
+
+/// Parses a positive quantity such as `250 mg`, `5 g`, `0.01 mol` or
+/// `2 mmol`, including scientific notation (`1e-3 g`). Bare numbers are
+/// grams.
+pub fn parse_quantity(value: &serde_json::Value) -> Result<Quantity, String> {
+    let (number, unit) = if let Some(n) = value.as_f64() {
+        (n, "g".to_string())
+    } else if let Some(s) = value.as_str() {
+        let s = s.trim();
+        let parse = |text: &str| text.trim().replace(',', ".").parse::<f64>().ok();
+        // The longest prefix that reads as a number; the rest is the unit.
+        let (split, number) = (1..=s.len())
+            .rev()
+            .filter(|idx| s.is_char_boundary(*idx))
+            .find_map(|idx| parse(&s[..idx]).map(|n| (idx, n)))
+            .ok_or_else(|| format!("Invalid quantity: '{}'", s))?;
+        let unit = s[split..].trim();
+        (number, if unit.is_empty() { "g" } else { unit }.to_string())
+    } else {
+        return Err("Quantity must be a number or a string such as '250 mg'".to_string());
+    };
+    let (_, factor, amount) = QUANTITY_UNITS
+        .iter()
+        .find(|(symbol, _, _)| *symbol == unit)
+        .ok_or_else(|| format!("Unknown unit: '{}' (use mg, g, kg, mmol or mol)", unit))?;
+    if !number.is_finite() || number <= 0.0 {
+        return Err("Quantity must be positive".to_string());
+    }
+    Ok(if *amount {
+        Quantity::Amount(number * factor)
+    } else {
+        Quantity::Mass(number * factor)
+    })
+}
+
+/// Unit masses are shown in; values are always stored in g.
+#[derive(Clone, Copy, Debug)]
+pub enum MassUnit {
+    Milligram,
+    Gram,
+    Kilogram,
+}
+
+impl MassUnit {
+    pub fn parse(symbol: &str) -> Result<Self, String> {
+        match symbol.trim() {
+            "mg" => Ok(MassUnit::Milligram),
+            "g" => Ok(MassUnit::Gram),
+            "kg" => Ok(MassUnit::Kilogram),
+            other => Err(format!("Unknown mass unit: '{}'", other)),
+        }
+    }
+
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            MassUnit::Milligram => "mg",
+            MassUnit::Gram => "g",
+            MassUnit::Kilogram => "kg",
+        }
+    }
+
+    /// Power of ten from g to this unit.
+    fn exponent(&self) -> i32 {
+        match self {
+            MassUnit::Milligram => 3,
+            MassUnit::Gram => 0,
+            MassUnit::Kilogram => -3,
+        }
+    }
+}
+
+pub fn round_decimals(value: f64, decimals: u32) -> f64 {
+    let factor = 10_f64.powi(decimals as i32);
+    (value * factor).round() / factor
+}
+
+pub fn format_value(value: f64) -> String {
+    format!("{:.8}", value)
+}
+
+/// Rounding rules taken from the balance and report settings.
+#[derive(Clone, Copy, Debug)]
+pub struct Precision {
+    pub readability: f64,
+    pub significant_figures: u32,
+    pub mass_unit: MassUnit,
+}
+
+/// Masses below this many readability steps are weighed with more than 1%
+/// relative error.
+const MIN_WEIGHABLE_STEPS: f64 = 100.0;
+
+fn significant_decimals(value: f64, figures: u32) -> i32 {
+    if value == 0.0 || !value.is_finite() {
+        return 0;
+    }
+    figures as i32 - 1 - value.abs().log10().floor() as i32
+}
+
+impl Precision {
+    /// Decimals shown for masses: those of the balance readability.
+    pub fn mass_decimals(&self) -> u32 {
+        (-self.readability.log10() - 1e-9).ceil().max(0.0) as u32
+    }
+
+    /// Rounds a mass to the nearest step the balance can display.
+    pub fn mass(&self, value: f64) -> f64 {
+        round_decimals(
+            (value / self.readability).round() * self.readability,
+            self.mass_decimals(),
+        )
+    }
+
+    pub fn significant(&self, value: f64) -> f64 {
+        let decimals = significant_decimals(value, self.significant_figures);
+        if decimals >= 0 {
+            round_decimals(value, decimals as u32)
+        } else {
+            let factor = 10_f64.powi(-decimals);
+            (value / factor).round() * factor
+        }
+    }
+
+    pub fn min_weighable(&self) -> f64 {
+        self.mass(self.readability * MIN_WEIGHABLE_STEPS)
+    }
+
+    /// Mass in g converted to the display unit, unrounded.
+    pub fn to_unit(&self, value: f64) -> f64 {
+        value * 10_f64.powi(self.mass_unit.exponent())
+    }
+
+    fn unit_decimals(&self) -> u32 {
+        (self.mass_decimals() as i32 - self.mass_unit.exponent()).max(0) as u32
+    }
+
+    /// Mass in g, shown in the display unit with the decimals the balance
+    /// resolves.
+    pub fn format_mass(&self, value: f64) -> String {
+        format!("{:.*}", self.unit_decimals() as usize, self.to_unit(value))
+    }
+
+    /// `format_mass` as a number, for exports that write numeric cells.
+    pub fn unit_mass(&self, value: f64) -> f64 {
+        round_decimals(self.to_unit(value), self.unit_decimals())
+    }
+
+    /// `format_mass` followed by the unit symbol.
+    pub fn display_mass(&self, value: f64) -> String {
+        format!("{} {}", self.format_mass(value), self.mass_unit.symbol())
+    }
+
+    pub fn format_significant(&self, value: f64) -> String {
+        let decimals = significant_decimals(value, self.significant_figures).max(0) as usize;
+        format!("{:.*}", decimals, self.significant(value))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn quantity(value: serde_json::Value) -> Result<Quantity, String> {
+        parse_quantity(&value)
+    }
+
+    fn grams(value: serde_json::Value) -> f64 {
+        match quantity(value).unwrap() {
+            Quantity::Mass(g) => g,
+            Quantity::Amount(_) => panic!("expected a mass"),
+        }
+    }
+
+    #[test]
+    fn parses_numbers_with_units() {
+        assert_eq!(grams(json!(5)), 5.0);
+        assert_eq!(grams(json!("5")), 5.0);
+        assert_eq!(grams(json!("250 mg")), 0.25);
+        assert_eq!(grams(json!("0,5kg")), 500.0);
+        assert_eq!(quantity(json!("2 mmol")), Ok(Quantity::Amount(0.002)));
+    }
+
+    #[test]
+    fn parses_scientific_notation() {
+        assert_eq!(grams(json!("1e-3 g")), 0.001);
+        assert_eq!(grams(json!("2.5E2 mg")), 0.25);
+        assert_eq!(grams(json!("1e2")), 100.0);
+        assert_eq!(quantity(json!("5e-3mol")), Ok(Quantity::Amount(0.005)));
+    }
+
+    #[test]
+    fn rejects_bad_quantities() {
+        assert_eq!(
+            quantity(json!("g")).err(),
+            Some("Invalid quantity: 'g'".to_string())
+        );
+        assert_eq!(
+            quantity(json!("5 lb")).err(),
+            Some("Unknown unit: 'lb' (use mg, g, kg, mmol or mol)".to_string())
+        );
+        assert_eq!(
+            quantity(json!("-1e-3 g")).err(),
+            Some("Quantity must be positive".to_string())
+        );
+        assert_eq!(
+            quantity(json!("inf g")).err(),
+            Some("Quantity must be positive".to_string())
+        );
+    }
+}
@@ -1,14 +1,67 @@
 use serde::{Deserialize, Serialize};
-use std::collections::{HashMap, HashSet};
-
-use crate::chem::parse::{ordered_unique_elements, parse_formula};
-use crate::commands::fetch_elements::get_elements;
-
-#[derive(Deserialize)]
+use std::collections::HashMap;
+
+use crate::chem::balance::{
+    linear_dependencies, solve_element_balance, solve_element_balance_exact, solve_min_cost,
+    Reagent,
+};
+use crate::chem::linalg::conditioning;
+use crate::chem::mass::{collapse_formula, molar_mass};
+use crate::chem::parse::{ordered_unique_elements, parse_formula, parse_formula_exact};
+use crate::chem::rational::collapse_exact;
+use crate::commands::additives::{evaluate_additives, AdditiveRule};
+use crate::commands::batch::{batch_advice, min_weighable_mass, BatchAdvice};
+use crate::commands::calc_helpers::{format_value, parse_quantity, round_decimals, Quantity};
+use crate::commands::composition::{element_composition, ElementComposition};
+use crate::commands::equation::calculation_equation;
+use crate::commands::fetch_elements::get_atomic_masses;
+use crate::commands::hazards::{hazard_warnings, read_hazards};
+use crate::commands::history::record_calculation;
+use crate::commands::hydration::{find_override, read_overrides};
+use crate::commands::inventory::weighed_mass;
+use crate::commands::library::{compound_price, library_price, read_library, resolve_compound};
+use crate::commands::phases::{calculate_phases, PhaseFraction, PhaseResult};
+use crate::commands::settings::read_settings;
+use crate::commands::solution::{format_volume, liquid_amount, SolutionSpec};
+use crate::commands::uncertainty::{
+    format_uncertain, propagate, weighing_uncertainty, CompositionUncertainty, UncertaintyModel,
+    WeighedRow,
+};
+
+/// Condition number above which the explanation warns about sensitivity.
+const ILL_CONDITIONED: f64 = 1e8;
+
+#[derive(Deserialize, Clone)]
 pub struct CalculationInput {
     pub target_formula: String,
+    /// Element amounts per formula unit of a computed target (a flux or a
+    /// phase mixture); `target_formula` is then only its label.
+    #[serde(skip)]
+    pub target_composition: Option<Vec<(String, f64)>>,
     pub target_mass: serde_json::Value,
     pub starting_materials: Vec<String>,
+    #[serde(default)]
+    pub solutions: Vec<SolutionSpec>,
+    #[serde(default)]
+    pub additives: Vec<AdditiveRule>,
+    #[serde(default)]
+    pub volatile_elements: Vec<String>,
+    #[serde(default)]
+    pub phases: Vec<PhaseFraction>,
+    #[serde(default = "default_phase_basis")]
+    pub phase_basis: String,
+    #[serde(default)]
+    pub per_phase: bool,
+    #[serde(default)]
+    pub objective: Option<String>,
+    #[serde(default)]
+    pub uncertainty: Option<UncertaintyModel>,
+    /// Largest acceptable weighing error in %, defaults to 1%.
+    #[serde(default)]
+    pub max_relative_error: Option<f64>,
+    /// Solve the element balance in exact fractions instead of floats.
+    #[serde(default)]
+    pub exact: bool,
 }
 
 #[derive(Serialize)]
@@ -23,6 +76,12 @@ pub struct ReagentResult {
     pub moles: f64,
     pub molar_mass: f64,
     pub mass: f64,
+    pub solution_mass: Option<f64>,
+    pub volume_ml: Option<f64>,
+    pub purity: Option<f64>,
+    pub cost: Option<f64>,
+    pub warnings: Vec<String>,
+    pub mass_uncertainty: Option<f64>,
 }
 
 #[derive(Serialize)]
@@ -32,121 +91,172 @@ pub struct MassCheck {
     pub delta: f64,
 }
 
+/// Rank and conditioning of the element x reagent matrix of the balance.
+#[derive(Serialize)]
+pub struct MatrixDiagnostics {
+    pub rows: usize,
+    pub columns: usize,
+    pub rank: usize,
+    /// `None` when the matrix is rank deficient.
+    pub condition_number: Option<f64>,
+}
+
 #[derive(Serialize)]
 pub struct CalculationOutput {
     pub target_formula: String,
     pub parsed_formula: Vec<ElementCoeff>,
     pub molar_mass: f64,
     pub target_moles: f64,
+    pub composition: Vec<ElementComposition>,
     pub reagents: Vec<ReagentResult>,
     pub mass_check: MassCheck,
+    pub phases: Vec<PhaseResult>,
+    pub total_cost: Option<f64>,
+    pub currency: Option<String>,
+    pub composition_uncertainty: Vec<CompositionUncertainty>,
+    pub batch_advice: Option<BatchAdvice>,
+    pub equation: Option<String>,
+    pub matrix: Option<MatrixDiagnostics>,
     pub explanation: Vec<String>,
 }
 
-#[derive(Clone)]
-struct Reagent {
-    name: String,
-    composition: HashMap<String, f64>,
-    molar_mass: f64,
+fn default_phase_basis() -> String {
+    "mol".to_string()
 }
 
-fn parse_f64(value: &serde_json::Value) -> f64 {
-    if let Some(n) = value.as_f64() {
-        n
-    } else if let Some(s) = value.as_str() {
-        s.parse().unwrap_or(0.0)
-    } else {
-        0.0
+impl Default for CalculationInput {
+    fn default() -> Self {
+        CalculationInput {
+            target_formula: String::new(),
+            target_composition: None,
+            target_mass: serde_json::Value::Null,
+            starting_materials: Vec::new(),
+            solutions: Vec::new(),
+            additives: Vec::new(),
+            volatile_elements: Vec::new(),
+            phases: Vec::new(),
+            phase_basis: default_phase_basis(),
+            per_phase: false,
+            objective: None,
+            uncertainty: None,
+            max_relative_error: None,
+            exact: false,
+        }
     }
 }
 
-fn round_decimals(value: f64, decimals: u32) -> f64 {
-    let factor = 10_f64.powi(decimals as i32);
-    (value * factor).round() / factor
-}
-
-fn format_value(value: f64) -> String {
-    format!("{:.8}", value)
+/// Result row for `moles` of a reagent: the mass to weigh at its purity and,
+/// when it is dispensed as a solution, the liquid amount.
+pub fn reagent_result(
+    name: &str,
+    moles: f64,
+    molar_mass: f64,
+    purity: Option<f64>,
+    solutions: &[SolutionSpec],
+) -> Result<ReagentResult, String> {
+    let liquid = match solutions.iter().find(|s| s.reagent.trim() == name) {
+        Some(spec) => Some(liquid_amount(spec, moles, molar_mass)?),
+        None => None,
+    };
+    Ok(ReagentResult {
+        reagent: name.to_string(),
+        moles,
+        molar_mass,
+        mass: moles * molar_mass / (purity.unwrap_or(100.0) / 100.0),
+        solution_mass: liquid.as_ref().and_then(|l| l.solution_mass),
+        volume_ml: liquid.as_ref().map(|l| l.volume_ml),
+        purity,
+        cost: None,
+        warnings: Vec::new(),
+        mass_uncertainty: None,
+    })
 }
 
-fn collapse_formula(formula: &[(String, f64)]) -> HashMap<String, f64> {
-    let mut map = HashMap::new();
-    for (el, coeff) in formula {
-        *map.entry(el.clone()).or_insert(0.0) += *coeff;
+/// Sums reagent rows from several sub-calculations, keeping the order in
+/// which reagents first appear. Liquid amounts survive only when every part
+/// dispenses the reagent as a liquid.
+pub fn merge_reagent_results(parts: &[&[ReagentResult]]) -> Vec<ReagentResult> {
+    let mut merged: Vec<ReagentResult> = Vec::new();
+    for item in parts.iter().flat_map(|p| p.iter()) {
+        if let Some(existing) = merged.iter_mut().find(|r| r.reagent == item.reagent) {
+            existing.moles += item.moles;
+            existing.mass += item.mass;
+            existing.solution_mass = match (existing.solution_mass, item.solution_mass) {
+                (Some(a), Some(b)) => Some(a + b),
+                _ => None,
+            };
+            existing.volume_ml = match (existing.volume_ml, item.volume_ml) {
+                (Some(a), Some(b)) => Some(a + b),
+                _ => None,
+            };
+            existing.cost = match (existing.cost, item.cost) {
+                (Some(a), Some(b)) => Some(a + b),
+                _ => None,
+            };
+            existing.mass_uncertainty = match (existing.mass_uncertainty, item.mass_uncertainty) {
+                (Some(a), Some(b)) => Some((a * a + b * b).sqrt()),
+                _ => None,
+            };
+            for warning in &item.warnings {
+                if !existing.warnings.contains(warning) {
+                    existing.warnings.push(warning.clone());
+                }
+            }
+        } else {
+            merged.push(ReagentResult {
+                reagent: item.reagent.clone(),
+                moles: item.moles,
+                molar_mass: item.molar_mass,
+                mass: item.mass,
+                solution_mass: item.solution_mass,
+                volume_ml: item.volume_ml,
+                purity: item.purity,
+                cost: item.cost,
+                warnings: item.warnings.clone(),
+                mass_uncertainty: item.mass_uncertainty,
+            });
+        }
     }
-    map
+    merged
 }
 
-fn molar_mass(composition: &HashMap<String, f64>, masses: &HashMap<String, f64>) -> Result<f64, String> {
-    let mut total = 0.0;
-    for (el, coeff) in composition {
-        let mass = masses
-            .get(el)
-            .ok_or_else(|| format!("Missing atomic mass for {}", el))?;
-        total += coeff * mass;
+pub async fn run_calculation(input: CalculationInput) -> Result<CalculationOutput, String> {
+    if input.phases.is_empty() {
+        calculate_single(input).await
+    } else {
+        calculate_phases(input).await
     }
-    Ok(total)
 }
 
-fn solve_square_system(a: Vec<Vec<f64>>, b: Vec<f64>) -> Result<Vec<f64>, String> {
-    let n = a.len();
-    let mut aug = vec![vec![0.0; n + 1]; n];
-    for i in 0..n {
-        for j in 0..n {
-            aug[i][j] = a[i][j];
-        }
-        aug[i][n] = b[i];
-    }
-
-    for i in 0..n {
-        let mut pivot_row = i;
-        let mut pivot_val = aug[i][i].abs();
-        for r in (i + 1)..n {
-            if aug[r][i].abs() > pivot_val {
-                pivot_val = aug[r][i].abs();
-                pivot_row = r;
-            }
-        }
-        if pivot_val < 1e-12 {
-            return Err("Singular system; cannot solve uniquely".to_string());
-        }
-        if pivot_row != i {
-            aug.swap(i, pivot_row);
-        }
-        let pivot = aug[i][i];
-        for c in i..=n {
-            aug[i][c] /= pivot;
-        }
-        for r in 0..n {
-            if r == i {
-                continue;
-            }
-            let factor = aug[r][i];
-            if factor.abs() > 1e-12 {
-                for c in i..=n {
-                    aug[r][c] -= factor * aug[i][c];
-                }
-            }
-        }
+/// Runs a calculation and records it, with the input as submitted, in the
+/// local history.
+#[tauri::command]
+pub async fn calculate(input: serde_json::Value) -> Result<CalculationOutput, String> {
+    let parsed: CalculationInput =
+        serde_json::from_value(input.clone()).map_err(|e| e.to_string())?;
+    let mut output = run_calculation(parsed).await?;
+    if let Err(e) = record_calculation(input, &output.target_formula, &output).await {
+        output
+            .explanation
+            .push(format!("Calculation not saved to history: {}", e));
     }
-
-    Ok(aug.iter().map(|row| row[n]).collect())
+    Ok(output)
 }
 
-#[tauri::command]
-pub async fn calculate(input: CalculationInput) -> Result<CalculationOutput, String> {
-    let target_mass = parse_f64(&input.target_mass);
-    if target_mass <= 0.0 {
-        return Err("Target mass must be positive".to_string());
+pub async fn calculate_single(input: CalculationInput) -> Result<CalculationOutput, String> {
+    let target_quantity =
+        parse_quantity(&input.target_mass).map_err(|e| format!("Target quantity: {}", e))?;
+    if input.exact && input.target_composition.is_some() {
+        return Err("Exact mode needs the target as a formula".to_string());
     }
 
-    let elements = get_elements().await?;
-    let masses: HashMap<String, f64> = elements
-        .into_iter()
-        .map(|el| (el.symbol, el.atomic_mass))
-        .collect();
+    let masses = get_atomic_masses().await?;
+    let precision = read_settings()?.precision();
 
-    let parsed_target = parse_formula(input.target_formula.trim())?;
+    let parsed_target = match &input.target_composition {
+        Some(composition) => composition.clone(),
+        None => parse_formula(input.target_formula.trim())?,
+    };
     let target_composition = collapse_formula(&parsed_target);
     let target_order = ordered_unique_elements(&parsed_target);
     let target_molar_mass = molar_mass(&target_composition, &masses)?;
@@ -155,20 +265,82 @@ pub async fn calculate(input: CalculationInput) -> Result<CalculationOutput, Str
         return Err("Target molar mass is zero".to_string());
     }
 
+    let target_mass = target_quantity.grams(target_molar_mass);
     let target_moles = target_mass / target_molar_mass;
     let mut required: HashMap<String, f64> = target_composition
         .iter()
         .map(|(el, coeff)| (el.clone(), coeff * target_moles))
         .collect();
 
+    let overrides = read_overrides()?;
+    let library = read_library()?;
+    let mut applied_overrides = Vec::new();
+    let mut override_volatile = Vec::new();
+    let mut library_lines = Vec::new();
+    let mut purities = Vec::new();
+    let mut prices = Vec::new();
+    let mut cas_numbers = Vec::new();
+    let mut solutions = input.solutions.clone();
     let mut reagents = Vec::new();
+    let mut formulas = Vec::new();
     for name in input.starting_materials.iter() {
-        let trimmed = name.trim();
+        let mut trimmed = name.trim();
         if trimmed.is_empty() {
             continue;
         }
-        let parsed = parse_formula(trimmed)?;
-        let composition = collapse_formula(&parsed);
+        let compound = resolve_compound(&library, trimmed)?;
+        if let Some(compound) = compound {
+            for spec in solutions.iter_mut() {
+                if spec.reagent.trim() == trimmed {
+                    spec.reagent = compound.formula.clone();
+                }
+            }
+            library_lines.push(format!(
+                "{} {} = {}{}{}{}",
+                trimmed,
+                compound.name,
+                compound.formula,
+                compound
+                    .cas
+                    .as_deref()
+                    .map(|cas| format!(", CAS {}", cas))
+                    .unwrap_or_default(),
+                compound
+                    .lot
+                    .as_deref()
+                    .map(|lot| format!(", lot {}", lot))
+                    .unwrap_or_default(),
+                compound
+                    .purity
+                    .map(|p| format!(", {}% pure", p))
+                    .unwrap_or_default()
+            ));
+            trimmed = compound.formula.as_str();
+        }
+        purities.push(compound.and_then(|c| c.purity));
+        cas_numbers.push(compound.and_then(|c| c.cas.clone()));
+        prices.push(
+            compound
+                .and_then(compound_price)
+                .or_else(|| library_price(&library, trimmed)),
+        );
+        let formula = match find_override(&overrides, trimmed) {
+            Some(item) => {
+                applied_overrides.push(format!(
+                    "{} -> {} (measured {})",
+                    trimmed, item.formula, item.measured_at
+                ));
+                for el in collapse_formula(&parse_formula(&item.volatile)?).into_keys() {
+                    if !target_composition.contains_key(&el) && !override_volatile.contains(&el) {
+                        override_volatile.push(el);
+                    }
+                }
+                item.formula.as_str()
+            }
+            None => trimmed,
+        };
+        let composition = collapse_formula(&parse_formula(formula)?);
+        formulas.push(formula.to_string());
         let reagent_molar_mass = molar_mass(&composition, &masses)?;
         reagents.push(Reagent {
             name: trimmed.to_string(),
@@ -180,6 +352,22 @@ pub async fn calculate(input: CalculationInput) -> Result<CalculationOutput, Str
     if reagents.is_empty() {
         return Err("No starting materials provided".to_string());
     }
+    for spec in solutions.iter_mut().filter(|s| s.density.is_none()) {
+        spec.density = library
+            .iter()
+            .find(|c| c.formula == spec.reagent.trim())
+            .and_then(|c| c.density);
+    }
+    for spec in &solutions {
+        let name = spec.reagent.trim();
+        let is_additive = input.additives.iter().any(|a| a.reagent.trim() == name);
+        if !is_additive && !reagents.iter().any(|r| r.name == name) {
+            return Err(format!(
+                "Solution given for unknown starting material: {}",
+                spec.reagent
+            ));
+        }
+    }
 
     let mut explanation = Vec::new();
     explanation.push(format!(
@@ -206,6 +394,13 @@ pub async fn calculate(input: CalculationInput) -> Result<CalculationOutput, Str
         "Target moles: {} mol",
         format_value(target_moles)
     ));
+    if let Quantity::Amount(amount) = target_quantity {
+        explanation.push(format!(
+            "Target given as {} mol -> {}",
+            format_value(amount),
+            precision.display_mass(target_mass)
+        ));
+    }
 
     explanation.push(format!(
         "Starting materials: {}",
@@ -234,233 +429,184 @@ pub async fn calculate(input: CalculationInput) -> Result<CalculationOutput, Str
             .join(", ")
     ));
 
-    let tol = 1e-10;
-    let mut fixed: Vec<Option<f64>> = vec![None; reagents.len()];
-
-    loop {
-        let remaining_indices: Vec<usize> = fixed
-            .iter()
-            .enumerate()
-            .filter_map(|(i, v)| if v.is_none() { Some(i) } else { None })
-            .collect();
-        let mut changed = false;
-        let remaining_elements: Vec<String> = target_order
-            .iter()
-            .filter_map(|el| {
-                if required.get(el).copied().unwrap_or(0.0).abs() > tol {
-                    Some(el.clone())
-                } else {
-                    None
-                }
-            })
-            .collect();
-
-        for element in remaining_elements {
-            let providers: Vec<usize> = remaining_indices
-                .iter()
-                .cloned()
-                .filter(|idx| {
-                    reagents[*idx]
-                        .composition
-                        .get(&element)
-                        .map(|v| *v > 0.0)
-                        .unwrap_or(false)
-                })
-                .collect();
-            if providers.len() == 1 {
-                let idx = providers[0];
-                let coeff = reagents[idx]
-                    .composition
-                    .get(&element)
-                    .copied()
-                    .unwrap_or(0.0);
-                if coeff.abs() < tol {
-                    return Err(format!("Invalid coefficient for {}", element));
-                }
-                let amount = required.get(&element).copied().unwrap_or(0.0) / coeff;
-                if let Some(existing) = fixed[idx] {
-                    if (existing - amount).abs() > 1e-8 {
-                        return Err(format!(
-                            "Inconsistent requirement for {} in {}",
-                            element, reagents[idx].name
-                        ));
-                    }
-                } else {
-                    fixed[idx] = Some(amount);
-                    changed = true;
-                    explanation.push(format!(
-                        "Unique supplier: {} only in {} -> n({}) = {} mol",
-                        element,
-                        reagents[idx].name,
-                        reagents[idx].name,
-                        format_value(amount)
-                    ));
-                    for (el, coeff) in reagents[idx].composition.iter() {
-                        let entry = required.entry(el.clone()).or_insert(0.0);
-                        *entry -= amount * coeff;
-                    }
-                }
-            }
-        }
-        if !changed {
-            break;
-        }
+    if !library_lines.is_empty() {
+        explanation.push(format!("Library compounds: {}", library_lines.join("; ")));
     }
-
-    for (el, remaining) in required.iter() {
-        if remaining.abs() > 1e-6 {
-            let providers = reagents
-                .iter()
-                .enumerate()
-                .filter(|(idx, _)| fixed[*idx].is_none())
-                .filter(|(_, reagent)| reagent.composition.contains_key(el))
-                .count();
-            if providers == 0 {
-                return Err(format!("No remaining reagent provides {}", el));
-            }
+    if !applied_overrides.is_empty() {
+        explanation.push(format!(
+            "Reagent overrides: {}",
+            applied_overrides.join(", ")
+        ));
+    }
+    let mut volatile: Vec<String> = input
+        .volatile_elements
+        .iter()
+        .map(|el| el.trim().to_string())
+        .filter(|el| !el.is_empty())
+        .collect();
+    for el in override_volatile {
+        if !volatile.contains(&el) {
+            volatile.push(el);
         }
     }
-
-    let remaining_indices: Vec<usize> = fixed
+    let balanced_order: Vec<String> = target_order
         .iter()
-        .enumerate()
-        .filter_map(|(i, v)| if v.is_none() { Some(i) } else { None })
+        .filter(|el| !volatile.contains(el))
+        .cloned()
         .collect();
+    if !volatile.is_empty() {
+        explanation.push(format!(
+            "Volatile elements (not balanced): {}",
+            volatile.join(", ")
+        ));
+    }
 
-    let remaining_elements: Vec<String> = target_order
+    let element_matrix: Vec<Vec<f64>> = balanced_order
         .iter()
-        .filter_map(|el| {
-            if required.get(el).copied().unwrap_or(0.0).abs() > 1e-8 {
-                Some(el.clone())
-            } else {
-                None
-            }
+        .map(|el| {
+            reagents
+                .iter()
+                .map(|r| r.composition.get(el).copied().unwrap_or(0.0))
+                .collect()
         })
         .collect();
-
-    if !remaining_elements.is_empty() && !remaining_indices.is_empty() {
-        if remaining_indices.len() < remaining_elements.len() {
-            return Err("Not enough independent reagents to solve".to_string());
+    let reagent_names: Vec<String> = reagents.iter().map(|r| r.name.clone()).collect();
+    let matrix_conditioning = conditioning(&element_matrix, reagents.len());
+    let matrix = MatrixDiagnostics {
+        rows: balanced_order.len(),
+        columns: reagents.len(),
+        rank: matrix_conditioning.rank,
+        condition_number: matrix_conditioning.condition,
+    };
+    explanation.push(format!(
+        "Element x reagent matrix: {} x {}, rank {}, condition number {}",
+        matrix.rows,
+        matrix.columns,
+        matrix.rank,
+        matrix
+            .condition_number
+            .map(|c| precision.format_significant(c))
+            .unwrap_or_else(|| "infinite".to_string())
+    ));
+    if matrix.condition_number.is_some_and(|c| c > ILL_CONDITIONED) {
+        explanation.push(
+            "Matrix is ill-conditioned; amounts are sensitive to small changes in the formulas"
+                .to_string(),
+        );
+    }
+    if matrix.rank < matrix.rows.min(matrix.columns) {
+        for line in linear_dependencies(&element_matrix, &balanced_order, &reagent_names) {
+            explanation.push(format!("Rank deficient: {}", line));
         }
+    }
 
-        let mut priority: Vec<usize> = remaining_indices.clone();
-        priority.sort_by(|a, b| {
-            let a_elemental = reagents[*a].composition.len() == 1;
-            let b_elemental = reagents[*b].composition.len() == 1;
-            match (a_elemental, b_elemental) {
-                (false, true) => std::cmp::Ordering::Less,
-                (true, false) => std::cmp::Ordering::Greater,
-                _ => a.cmp(b),
-            }
-        });
-
-        let mut selected = Vec::new();
-        let mut covered: HashSet<String> = HashSet::new();
-        for idx in &priority {
-            let contributes = reagents[*idx]
-                .composition
-                .keys()
-                .any(|el| remaining_elements.contains(el));
-            if contributes {
-                selected.push(*idx);
-                for el in reagents[*idx].composition.keys() {
-                    covered.insert(el.clone());
-                }
-                if remaining_elements.iter().all(|el| covered.contains(el)) {
-                    break;
+    let tol = 1e-10;
+    let amounts = match input.objective.as_deref().map(str::trim) {
+        None | Some("") if input.exact => {
+            let compositions = formulas
+                .iter()
+                .map(|f| parse_formula_exact(f).and_then(|parsed| collapse_exact(&parsed)))
+                .collect::<Result<Vec<_>, String>>()?;
+            let target_exact = collapse_exact(&parse_formula_exact(input.target_formula.trim())?)?;
+            let mut elements = balanced_order.clone();
+            for composition in &compositions {
+                for el in composition.keys() {
+                    if !volatile.contains(el) && !elements.contains(el) {
+                        elements.push(el.clone());
+                    }
                 }
             }
+            let per_unit = solve_element_balance_exact(
+                &compositions,
+                &reagent_names,
+                &elements,
+                &target_exact,
+            )?;
+            explanation.push(format!(
+                "Exact rational solution per formula unit: {}",
+                reagent_names
+                    .iter()
+                    .zip(per_unit.iter())
+                    .map(|(name, n)| format!("n({}) = {}", name, n))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ));
+            per_unit.iter().map(|n| n.to_f64() * target_moles).collect()
         }
-
-        if !remaining_elements.iter().all(|el| covered.contains(el)) {
-            return Err("Could not cover all remaining elements".to_string());
-        }
-
-        while selected.len() < remaining_elements.len() {
-            if let Some(next) = priority.iter().find(|idx| !selected.contains(idx)) {
-                selected.push(*next);
-            } else {
-                break;
-            }
-        }
-
-        if selected.len() != remaining_elements.len() {
-            return Err("Ambiguous system; provide additional constraints".to_string());
-        }
-
-        let mut matrix = Vec::new();
-        let mut rhs = Vec::new();
-        for element in &remaining_elements {
-            let mut row = Vec::new();
-            for idx in &selected {
-                row.push(
-                    reagents[*idx]
-                        .composition
-                        .get(element)
-                        .copied()
-                        .unwrap_or(0.0),
-                );
-            }
-            matrix.push(row);
-            rhs.push(required.get(element).copied().unwrap_or(0.0));
+        None | Some("") => {
+            solve_element_balance(&reagents, &balanced_order, &mut required, &mut explanation)?
         }
-
-        let solved = solve_square_system(matrix, rhs)?;
-        for (idx, amount) in selected.iter().zip(solved.iter()) {
-            fixed[*idx] = Some(*amount);
+        Some("min_cost") if input.exact => {
+            return Err("Exact mode supports only the element-balance objective".to_string())
         }
-
-        for idx in &remaining_indices {
-            if fixed[*idx].is_none() {
-                fixed[*idx] = Some(0.0);
-            }
-        }
-    } else {
-        for idx in &remaining_indices {
-            fixed[*idx] = Some(0.0);
+        Some("min_cost") => {
+            let costs = reagents
+                .iter()
+                .zip(prices.iter().zip(purities.iter()))
+                .map(|(r, (price, purity))| {
+                    price
+                        .as_ref()
+                        .map(|(p, _)| p * r.molar_mass / (purity.unwrap_or(100.0) / 100.0))
+                        .ok_or_else(|| format!("No price for {}; cannot minimize cost", r.name))
+                })
+                .collect::<Result<Vec<f64>, String>>()?;
+            explanation.push(
+                "Objective: cheapest non-negative solution of the element balance".to_string(),
+            );
+            solve_min_cost(&reagents, &balanced_order, &required, &costs)?
         }
-    }
+        Some(other) => return Err(format!("Unknown objective: {}", other)),
+    };
 
     let mut totals: HashMap<String, f64> = HashMap::new();
     for (idx, reagent) in reagents.iter().enumerate() {
-        let amount = fixed[idx].unwrap_or(0.0);
+        let amount = amounts[idx];
         for (el, coeff) in reagent.composition.iter() {
             *totals.entry(el.clone()).or_insert(0.0) += amount * coeff;
         }
     }
 
-    for (el, actual) in totals.iter() {
-        if !target_composition.contains_key(el) && actual.abs() > 1e-8 {
-            return Err(format!(
-                "Reagent introduces element not in target: {}",
-                el
-            ));
+    // The exact path has already balanced every element without rounding.
+    if !input.exact {
+        for (el, actual) in totals.iter() {
+            if volatile.contains(el) {
+                continue;
+            }
+            if !target_composition.contains_key(el) && actual.abs() > 1e-8 {
+                return Err(format!(
+                    "Reagent introduces element not in target: {}",
+                    el
+                ));
+            }
         }
-    }
 
-    for (el, required_mol) in target_composition.iter().map(|(el, coeff)| {
-        let required_mol = coeff * target_moles;
-        (el.clone(), required_mol)
-    }) {
-        let actual = totals.get(&el).copied().unwrap_or(0.0);
-        if (actual - required_mol).abs() > 1e-6 {
-            return Err(format!(
-                "Element balance mismatch for {} (required {}, got {})",
-                el,
-                format_value(required_mol),
-                format_value(actual)
-            ));
+        for (el, required_mol) in target_composition.iter().map(|(el, coeff)| {
+            let required_mol = coeff * target_moles;
+            (el.clone(), required_mol)
+        }) {
+            if volatile.contains(&el) {
+                continue;
+            }
+            let actual = totals.get(&el).copied().unwrap_or(0.0);
+            if (actual - required_mol).abs() > 1e-6 {
+                return Err(format!(
+                    "Element balance mismatch for {} (required {}, got {})",
+                    el,
+                    format_value(required_mol),
+                    format_value(actual)
+                ));
+            }
         }
     }
 
     let mut equation_lines = Vec::new();
-    for el in &target_order {
+    for el in &balanced_order {
         let coeff = target_composition.get(el).copied().unwrap_or(0.0);
         let required_mol = coeff * target_moles;
         let mut terms = Vec::new();
         for (idx, reagent) in reagents.iter().enumerate() {
             if let Some(r_coeff) = reagent.composition.get(el) {
-                let amount = fixed[idx].unwrap_or(0.0);
+                let amount = amounts[idx];
                 if amount.abs() > tol {
                     terms.push(format!(
                         "{}*n({})",
@@ -488,7 +634,7 @@ pub async fn calculate(input: CalculationInput) -> Result<CalculationOutput, Str
     let mut reagent_results = Vec::new();
     let mut total_reagent_mass = 0.0;
     for (idx, reagent) in reagents.iter().enumerate() {
-        let mut moles = fixed[idx].unwrap_or(0.0);
+        let mut moles = amounts[idx];
         if moles.abs() < tol {
             moles = 0.0;
         }
@@ -497,31 +643,305 @@ pub async fn calculate(input: CalculationInput) -> Result<CalculationOutput, Str
         }
         let mass = moles * reagent.molar_mass;
         total_reagent_mass += mass;
-        reagent_results.push(ReagentResult {
-            reagent: reagent.name.clone(),
+        reagent_results.push(reagent_result(
+            &reagent.name,
             moles,
-            molar_mass: reagent.molar_mass,
-            mass: round_decimals(mass, 6),
-        });
+            reagent.molar_mass,
+            purities[idx],
+            &solutions,
+        )?);
+    }
+
+    let used: Vec<(&str, &HashMap<String, f64>, f64)> = reagents
+        .iter()
+        .zip(amounts.iter())
+        .filter(|(_, n)| **n > tol)
+        .map(|(r, n)| (r.name.as_str(), &r.composition, *n))
+        .collect();
+    let equation = calculation_equation(
+        &used,
+        input.target_formula.trim(),
+        &target_composition,
+        target_moles,
+    );
+    let equation = match equation {
+        Ok(equation) => {
+            explanation.push(format!("Balanced equation: {}", equation));
+            Some(equation)
+        }
+        Err(reason) => {
+            explanation.push(format!("Balanced equation: not shown, {}", reason));
+            None
+        }
+    };
+
+    let additives = evaluate_additives(
+        &input.additives,
+        &reagents,
+        &amounts,
+        &masses,
+        &mut explanation,
+    )?;
+    for additive in &additives {
+        reagent_results.push(reagent_result(
+            &additive.name,
+            additive.moles,
+            additive.molar_mass,
+            None,
+            &solutions,
+        )?);
+    }
+
+    if reagent_results.iter().any(|r| r.volume_ml.is_some()) {
+        explanation.push(format!(
+            "Liquid reagents: {}",
+            reagent_results
+                .iter()
+                .filter_map(|r| r.volume_ml.map(|v| match r.solution_mass {
+                    Some(m) => format!(
+                        "{}={} ({})",
+                        r.reagent,
+                        format_volume(v),
+                        precision.display_mass(m)
+                    ),
+                    None => format!("{}={}", r.reagent, format_volume(v)),
+                }))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ));
+    }
+
+    let mut result_prices = prices;
+    for additive in &additives {
+        result_prices.push(library_price(&library, &additive.name));
+    }
+    for (result, price) in reagent_results.iter_mut().zip(result_prices.iter()) {
+        result.cost = price
+            .as_ref()
+            .map(|(p, _)| round_decimals(result.mass * p, 4));
+    }
+    let mut currencies: Vec<&str> = result_prices
+        .iter()
+        .flatten()
+        .map(|(_, currency)| currency.as_str())
+        .collect();
+    currencies.sort();
+    currencies.dedup();
+    let (total_cost, currency) = match currencies.as_slice() {
+        [] => (None, None),
+        [currency] => {
+            let total: f64 = reagent_results.iter().filter_map(|r| r.cost).sum();
+            let unpriced: Vec<&str> = reagent_results
+                .iter()
+                .filter(|r| r.cost.is_none() && r.moles > 0.0)
+                .map(|r| r.reagent.as_str())
+                .collect();
+            explanation.push(format!(
+                "Cost: {} = {:.2} {}{}",
+                reagent_results
+                    .iter()
+                    .filter(|r| r.moles > 0.0)
+                    .filter_map(|r| r.cost.map(|c| format!("{} {:.2}", r.reagent, c)))
+                    .collect::<Vec<_>>()
+                    .join(" + "),
+                total,
+                currency,
+                if unpriced.is_empty() {
+                    String::new()
+                } else {
+                    format!(" (no price for {})", unpriced.join(", "))
+                }
+            ));
+            (Some(round_decimals(total, 4)), Some(currency.to_string()))
+        }
+        _ => {
+            explanation.push(format!(
+                "Cost: reagents are priced in different currencies ({}); no total",
+                currencies.join(", ")
+            ));
+            (None, None)
+        }
+    };
+
+    // Starting materials carry the CAS of the library entry they resolved
+    // to; additives are looked up by formula.
+    let hazards = read_hazards()?;
+    for (idx, result) in reagent_results.iter_mut().enumerate() {
+        let cas = match cas_numbers.get(idx) {
+            Some(cas) => cas.as_deref(),
+            None => library
+                .iter()
+                .find(|c| c.formula == result.reagent)
+                .and_then(|c| c.cas.as_deref()),
+        };
+        result.warnings = hazard_warnings(&hazards, &result.reagent, cas);
+    }
+    let hazardous: Vec<&str> = reagent_results
+        .iter()
+        .filter(|r| !r.warnings.is_empty())
+        .map(|r| r.reagent.as_str())
+        .collect();
+    if !hazardous.is_empty() {
+        explanation.push(format!(
+            "Safety: hazard information for {}",
+            hazardous.join(", ")
+        ));
+    }
+
+    if input.max_relative_error.is_some_and(|e| e <= 0.0) {
+        return Err("Maximum relative error must be positive".to_string());
+    }
+    let min_weighable = min_weighable_mass(&precision, input.max_relative_error);
+    let mut too_small = Vec::new();
+    for result in reagent_results.iter_mut() {
+        let Some(weighed) = weighed_mass(result.mass, result.solution_mass, result.volume_ml)
+        else {
+            continue;
+        };
+        if result.moles > 0.0 && weighed < min_weighable {
+            result.warnings.push(format!(
+                "{} is below the minimum weighable mass of {} for a {} balance",
+                precision.display_mass(weighed),
+                precision.display_mass(min_weighable),
+                precision.display_mass(precision.readability)
+            ));
+            too_small.push(result.reagent.clone());
+        }
+    }
+    if !too_small.is_empty() {
+        explanation.push(format!(
+            "Below the minimum weighable mass ({}): {}; scale up the batch or use a stock solution",
+            precision.display_mass(min_weighable),
+            too_small.join(", ")
+        ));
+    }
+    let batch_advice = batch_advice(
+        &reagent_results,
+        target_mass,
+        min_weighable,
+        &precision,
+        &mut explanation,
+    );
+
+    if reagent_results.iter().any(|r| r.purity.is_some()) {
+        explanation.push(format!(
+            "Purity correction (weighed mass = pure mass / purity): {}",
+            reagent_results
+                .iter()
+                .filter_map(|r| r.purity.map(|p| format!(
+                    "{} {}% -> {}",
+                    r.reagent,
+                    p,
+                    precision.display_mass(r.mass)
+                )))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ));
     }
 
     explanation.push(format!(
         "Reagent moles: {}",
         reagent_results
             .iter()
-            .map(|r| format!("{}={}", r.reagent, format_value(r.moles)))
+            .map(|r| format!("{}={}", r.reagent, precision.format_significant(r.moles)))
             .collect::<Vec<_>>()
             .join(", ")
     ));
     explanation.push(format!(
-        "Reagent masses (g, 6 decimals): {}",
+        "Reagent masses ({}, balance readability {}): {}",
+        precision.mass_unit.symbol(),
+        precision.display_mass(precision.readability),
         reagent_results
             .iter()
-            .map(|r| format!("{}={:.6}", r.reagent, r.mass))
+            .map(|r| format!("{}={}", r.reagent, precision.format_mass(r.mass)))
             .collect::<Vec<_>>()
             .join(", ")
     ));
 
+    let mut composition_uncertainty = Vec::new();
+    if let Some(model) = &input.uncertainty {
+        if model.purity < 0.0 || model.atomic_weights.iter().any(|a| a.value < 0.0) {
+            return Err("Uncertainties cannot be negative".to_string());
+        }
+        let additive_compositions = additives
+            .iter()
+            .map(|a| parse_formula(&a.name).map(|parsed| collapse_formula(&parsed)))
+            .collect::<Result<Vec<_>, String>>()?;
+        let rows: Vec<WeighedRow> = reagents
+            .iter()
+            .map(|r| &r.composition)
+            .chain(additive_compositions.iter())
+            .zip(reagent_results.iter())
+            .map(|(composition, r)| WeighedRow {
+                composition,
+                moles: r.moles,
+                purity: r.purity,
+            })
+            .collect();
+        let propagation = propagate(
+            model,
+            precision.readability,
+            &rows,
+            &target_composition,
+            target_mass,
+            &balanced_order,
+            &masses,
+        );
+        for (result, u) in reagent_results
+            .iter_mut()
+            .zip(propagation.mass_uncertainties.iter())
+        {
+            result.mass_uncertainty = Some(*u);
+        }
+        explanation.push(format!(
+            "Uncertainty model: readability {} g (u = {} g per weighing), purity ± {} %, atomic weights {}",
+            precision.readability,
+            format_value(weighing_uncertainty(precision.readability)),
+            model.purity,
+            if model.atomic_weights.is_empty() {
+                "exact".to_string()
+            } else {
+                model
+                    .atomic_weights
+                    .iter()
+                    .map(|a| format!("{} ± {}", a.element.trim(), a.value))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            }
+        ));
+        explanation.push(format!(
+            "Reagent masses with standard uncertainty ({}): {}",
+            precision.mass_unit.symbol(),
+            reagent_results
+                .iter()
+                .filter(|r| r.moles > 0.0)
+                .map(|r| format!(
+                    "{} = {}",
+                    r.reagent,
+                    format_uncertain(
+                        precision.to_unit(r.mass),
+                        precision.to_unit(r.mass_uncertainty.unwrap_or(0.0))
+                    )
+                ))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ));
+        explanation.push(format!(
+            "Product composition with standard uncertainty: {}",
+            propagation
+                .composition
+                .iter()
+                .map(|c| format!(
+                    "{} = {}",
+                    c.element,
+                    format_uncertain(c.coefficient, c.uncertainty)
+                ))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ));
+        composition_uncertainty = propagation.composition;
+    }
+
     let mass_check = MassCheck {
         target_mass,
         total_reagent_mass,
@@ -529,12 +949,19 @@ pub async fn calculate(input: CalculationInput) -> Result<CalculationOutput, Str
     };
 
     explanation.push(format!(
-        "Mass check: total reagents {} g vs target {} g (delta {})",
-        format_value(total_reagent_mass),
-        format_value(target_mass),
-        format_value(mass_check.delta)
+        "Mass check: total reagents{} {} vs target {} (delta {})",
+        if additives.is_empty() {
+            ""
+        } else {
+            " excluding additives"
+        },
+        precision.display_mass(mass_check.total_reagent_mass),
+        precision.display_mass(target_mass),
+        precision.display_mass(mass_check.delta)
     ));
 
+    let composition = element_composition(&parsed_target, &masses)?;
+
     let parsed_formula = parsed_target
         .iter()
         .map(|(el, coeff)| ElementCoeff {
@@ -548,8 +975,16 @@ pub async fn calculate(input: CalculationInput) -> Result<CalculationOutput, Str
         parsed_formula,
         molar_mass: target_molar_mass,
         target_moles,
+        composition,
         reagents: reagent_results,
         mass_check,
+        phases: Vec::new(),
+        total_cost,
+        currency,
+        composition_uncertainty,
+        batch_advice,
+        equation,
+        matrix: Some(matrix),
         explanation,
     })
 }
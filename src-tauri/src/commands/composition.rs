@@ -0,0 +1,188 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::chem::mass::{collapse_formula, molar_mass};
+use crate::chem::parse::{ordered_unique_elements, parse_formula};
+use crate::commands::calc_helpers::format_value;
+use crate::commands::fetch_elements::get_atomic_masses;
+
+#[derive(Deserialize)]
+pub struct CompositionInput {
+    pub formula: String,
+    #[serde(default)]
+    pub oxides: Vec<String>,
+}
+
+#[derive(Serialize)]
+pub struct ElementComposition {
+    pub element: String,
+    pub coefficient: f64,
+    pub atomic_mass: f64,
+    pub mass_contribution: f64,
+    pub mass_percent: f64,
+    pub atomic_percent: f64,
+}
+
+#[derive(Serialize)]
+pub struct OxideComposition {
+    pub oxide: String,
+    pub cation: String,
+    pub moles_per_formula: f64,
+    pub mass_percent: f64,
+    pub normalized_percent: f64,
+}
+
+#[derive(Serialize)]
+pub struct CompositionOutput {
+    pub target_formula: String,
+    pub molar_mass: f64,
+    pub composition: Vec<ElementComposition>,
+    pub oxide_composition: Vec<OxideComposition>,
+    pub explanation: Vec<String>,
+}
+
+pub fn element_composition(
+    parsed: &[(String, f64)],
+    masses: &HashMap<String, f64>,
+) -> Result<Vec<ElementComposition>, String> {
+    let composition = collapse_formula(parsed);
+    let total_mass = molar_mass(&composition, masses)?;
+    let total_atoms: f64 = composition.values().sum();
+    if total_mass <= 0.0 || total_atoms <= 0.0 {
+        return Err("Formula molar mass is zero".to_string());
+    }
+    let mut out = Vec::new();
+    for el in ordered_unique_elements(parsed) {
+        let coefficient = composition.get(&el).copied().unwrap_or(0.0);
+        let atomic_mass = masses
+            .get(&el)
+            .copied()
+            .ok_or_else(|| format!("Missing atomic mass for {}", el))?;
+        let mass_contribution = coefficient * atomic_mass;
+        out.push(ElementComposition {
+            element: el,
+            coefficient,
+            atomic_mass,
+            mass_contribution,
+            mass_percent: mass_contribution / total_mass * 100.0,
+            atomic_percent: coefficient / total_atoms * 100.0,
+        });
+    }
+    Ok(out)
+}
+
+pub fn oxide_cation(oxide: &str, composition: &HashMap<String, f64>) -> Result<String, String> {
+    if !composition.contains_key("O") {
+        return Err(format!("{} is not an oxide", oxide));
+    }
+    let cations: Vec<&String> = composition.keys().filter(|el| *el != "O").collect();
+    if cations.len() != 1 {
+        return Err(format!("Oxide {} must contain exactly one cation", oxide));
+    }
+    Ok(cations[0].clone())
+}
+
+pub fn oxide_composition(
+    parsed: &[(String, f64)],
+    oxides: &[String],
+    masses: &HashMap<String, f64>,
+) -> Result<Vec<OxideComposition>, String> {
+    let composition = collapse_formula(parsed);
+    let total_mass = molar_mass(&composition, masses)?;
+    let mut out = Vec::new();
+    for oxide in oxides {
+        let trimmed = oxide.trim();
+        if trimmed.is_empty() {
+            continue;
+        }
+        let oxide_composition = collapse_formula(&parse_formula(trimmed)?);
+        let cation = oxide_cation(trimmed, &oxide_composition)?;
+        let target_coeff = composition
+            .get(&cation)
+            .copied()
+            .ok_or_else(|| format!("{} is not present in the formula", cation))?;
+        let oxide_mass = molar_mass(&oxide_composition, masses)?;
+        let moles_per_formula = target_coeff / oxide_composition[&cation];
+        out.push(OxideComposition {
+            oxide: trimmed.to_string(),
+            cation,
+            moles_per_formula,
+            mass_percent: moles_per_formula * oxide_mass / total_mass * 100.0,
+            normalized_percent: 0.0,
+        });
+    }
+    let oxide_total: f64 = out.iter().map(|o| o.mass_percent).sum();
+    if oxide_total > 0.0 {
+        for item in out.iter_mut() {
+            item.normalized_percent = item.mass_percent / oxide_total * 100.0;
+        }
+    }
+    Ok(out)
+}
+
+#[tauri::command]
+pub async fn composition(input: CompositionInput) -> Result<CompositionOutput, String> {
+    let formula = input.formula.trim();
+    let masses = get_atomic_masses().await?;
+    let parsed = parse_formula(formula)?;
+    let total_mass = molar_mass(&collapse_formula(&parsed), &masses)?;
+    let elements = element_composition(&parsed, &masses)?;
+    let oxides = oxide_composition(&parsed, &input.oxides, &masses)?;
+
+    let mut explanation = Vec::new();
+    explanation.push(format!("Molar mass: {} g/mol", format_value(total_mass)));
+    explanation.push(format!(
+        "Mass contributions (g/mol): {}",
+        elements
+            .iter()
+            .map(|e| format!(
+                "{}={}*{}={}",
+                e.element,
+                e.coefficient,
+                e.atomic_mass,
+                format_value(e.mass_contribution)
+            ))
+            .collect::<Vec<_>>()
+            .join(", ")
+    ));
+    explanation.push(format!(
+        "Mass percent: {}",
+        elements
+            .iter()
+            .map(|e| format!("{}={:.4}%", e.element, e.mass_percent))
+            .collect::<Vec<_>>()
+            .join(", ")
+    ));
+    explanation.push(format!(
+        "Atomic percent: {}",
+        elements
+            .iter()
+            .map(|e| format!("{}={:.4}%", e.element, e.atomic_percent))
+            .collect::<Vec<_>>()
+            .join(", ")
+    ));
+    if !oxides.is_empty() {
+        explanation.push(format!(
+            "Oxide basis: {} (sum {:.4}%)",
+            oxides
+                .iter()
+                .map(|o| format!(
+                    "{}={} mol per formula unit ({:.4}%)",
+                    o.oxide,
+                    format_value(o.moles_per_formula),
+                    o.mass_percent
+                ))
+                .collect::<Vec<_>>()
+                .join(", "),
+            oxides.iter().map(|o| o.mass_percent).sum::<f64>()
+        ));
+    }
+
+    Ok(CompositionOutput {
+        target_formula: formula.to_string(),
+        molar_mass: total_mass,
+        composition: elements,
+        oxide_composition: oxides,
+        explanation,
+    })
+}
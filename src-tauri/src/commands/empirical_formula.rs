@@ -0,0 +1,513 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::chem::mass::{collapse_formula, molar_mass};
+use crate::chem::parse::{format_formula, parse_formula};
+use crate::commands::calc_helpers::{format_value, round_decimals};
+use crate::commands::fetch_elements::get_atomic_masses;
+
+const MAX_MULTIPLIER: u32 = 12;
+
+#[derive(Deserialize, Clone)]
+pub struct ElementAmount {
+    pub element: String,
+    pub value: f64,
+}
+
+#[derive(Deserialize)]
+pub struct EmpiricalFormulaInput {
+    pub composition: Vec<ElementAmount>,
+    #[serde(default = "default_basis")]
+    pub basis: String,
+    #[serde(default)]
+    pub normalize_element: Option<String>,
+    #[serde(default)]
+    pub normalize_value: Option<f64>,
+    #[serde(default)]
+    pub denominator: Option<u32>,
+    #[serde(default = "default_tolerance")]
+    pub tolerance: f64,
+    #[serde(default)]
+    pub nominal_formula: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct ElementRatio {
+    pub element: String,
+    pub input_value: f64,
+    pub atomic_percent: f64,
+    pub weight_percent: f64,
+    pub ratio: f64,
+    pub coefficient: f64,
+    pub residual: f64,
+    /// Too dilute to round to a whole atom; kept at its measured ratio.
+    pub trace: bool,
+}
+
+#[derive(Serialize)]
+pub struct NominalComparison {
+    pub element: String,
+    pub measured_ratio: Option<f64>,
+    pub nominal_ratio: f64,
+    pub measured_weight_percent: Option<f64>,
+    pub nominal_weight_percent: f64,
+    pub weight_percent_delta: Option<f64>,
+}
+
+#[derive(Serialize)]
+pub struct EmpiricalFormulaOutput {
+    pub formula: String,
+    pub molar_mass: f64,
+    pub multiplier: u32,
+    pub reference_element: String,
+    pub elements: Vec<ElementRatio>,
+    pub max_residual: f64,
+    pub nominal: Option<Vec<NominalComparison>>,
+    pub explanation: Vec<String>,
+}
+
+fn default_basis() -> String {
+    "wt".to_string()
+}
+
+fn default_tolerance() -> f64 {
+    0.1
+}
+
+fn max_residual(ratios: &[f64], multiplier: f64) -> f64 {
+    ratios
+        .iter()
+        .map(|r| (r * multiplier - (r * multiplier).round()).abs())
+        .fold(0.0, f64::max)
+}
+
+#[tauri::command]
+pub async fn empirical_formula(
+    input: EmpiricalFormulaInput,
+) -> Result<EmpiricalFormulaOutput, String> {
+    let masses = get_atomic_masses().await?;
+    solve_empirical_formula(&input, &masses)
+}
+
+fn solve_empirical_formula(
+    input: &EmpiricalFormulaInput,
+    masses: &HashMap<String, f64>,
+) -> Result<EmpiricalFormulaOutput, String> {
+    let by_weight = match input.basis.as_str() {
+        "wt" => true,
+        "at" => false,
+        other => return Err(format!("Unknown composition basis: {}", other)),
+    };
+    if input.composition.is_empty() {
+        return Err("No element amounts provided".to_string());
+    }
+
+    let mut elements: Vec<String> = Vec::new();
+    let mut values: Vec<f64> = Vec::new();
+    let mut moles: Vec<f64> = Vec::new();
+    for item in &input.composition {
+        let element = item.element.trim().to_string();
+        if element.is_empty() {
+            continue;
+        }
+        if elements.contains(&element) {
+            return Err(format!("Duplicate element: {}", element));
+        }
+        if item.value <= 0.0 {
+            return Err(format!("Amount for {} must be positive", element));
+        }
+        let atomic_mass = *masses
+            .get(&element)
+            .ok_or_else(|| format!("Missing atomic mass for {}", element))?;
+        moles.push(if by_weight {
+            item.value / atomic_mass
+        } else {
+            item.value
+        });
+        elements.push(element);
+        values.push(item.value);
+    }
+    if elements.is_empty() {
+        return Err("No element amounts provided".to_string());
+    }
+
+    let total_moles: f64 = moles.iter().sum();
+    let total_mass: f64 = elements
+        .iter()
+        .zip(moles.iter())
+        .map(|(el, n)| n * masses[el])
+        .sum();
+
+    let (reference_index, reference_value) = match &input.normalize_element {
+        Some(el) => {
+            let idx = elements
+                .iter()
+                .position(|e| e == el.trim())
+                .ok_or_else(|| format!("Normalization element {} not in composition", el))?;
+            (idx, input.normalize_value.unwrap_or(1.0))
+        }
+        None => {
+            let idx = moles
+                .iter()
+                .enumerate()
+                .min_by(|a, b| a.1.total_cmp(b.1))
+                .map(|(i, _)| i)
+                .unwrap_or(0);
+            (idx, 1.0)
+        }
+    };
+    if reference_value <= 0.0 {
+        return Err("Normalization value must be positive".to_string());
+    }
+    let reference_element = elements[reference_index].clone();
+    let ratios: Vec<f64> = moles
+        .iter()
+        .map(|n| n / moles[reference_index] * reference_value)
+        .collect();
+
+    let mut explanation = Vec::new();
+    explanation.push(format!(
+        "Input ({}%): {}",
+        input.basis,
+        elements
+            .iter()
+            .zip(values.iter())
+            .map(|(el, v)| format!("{}={}", el, v))
+            .collect::<Vec<_>>()
+            .join(", ")
+    ));
+    if by_weight {
+        explanation.push(format!(
+            "Relative moles (wt% / atomic mass): {}",
+            elements
+                .iter()
+                .zip(moles.iter())
+                .map(|(el, n)| format!("{}={}", el, format_value(*n)))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ));
+    }
+    explanation.push(format!(
+        "Mole ratios normalized to {}={}: {}",
+        reference_element,
+        reference_value,
+        elements
+            .iter()
+            .zip(ratios.iter())
+            .map(|(el, r)| format!("{}={}", el, format_value(*r)))
+            .collect::<Vec<_>>()
+            .join(", ")
+    ));
+
+    let multiplier = match input.denominator {
+        Some(0) => return Err("Denominator must be positive".to_string()),
+        Some(denominator) => {
+            explanation.push(format!(
+                "Fixed denominator {}: coefficients rounded to multiples of 1/{}",
+                denominator, denominator
+            ));
+            denominator
+        }
+        None => {
+            let mut best = 1;
+            let mut best_residual = f64::MAX;
+            for k in 1..=MAX_MULTIPLIER {
+                let residual = max_residual(&ratios, k as f64);
+                if residual < best_residual - 1e-12 {
+                    best = k;
+                    best_residual = residual;
+                }
+                if residual <= input.tolerance {
+                    best = k;
+                    break;
+                }
+            }
+            explanation.push(format!(
+                "Smallest integer multiplier within tolerance {}: x{} (max deviation {})",
+                input.tolerance,
+                best,
+                format_value(max_residual(&ratios, best as f64))
+            ));
+            best
+        }
+    };
+
+    let fixed_denominator = input.denominator.is_some();
+    let scale = multiplier as f64;
+    let mut parsed = Vec::new();
+    let mut element_ratios = Vec::new();
+    let mut traces = Vec::new();
+    for (idx, el) in elements.iter().enumerate() {
+        let scaled = ratios[idx] * scale;
+        let trace = scaled.round() == 0.0;
+        let (coefficient, residual) = if trace {
+            // Rounding would drop the element; keep the measured ratio.
+            traces.push(el.clone());
+            let exact = if fixed_denominator {
+                ratios[idx]
+            } else {
+                scaled
+            };
+            let coefficient = round_decimals(exact, 4);
+            (coefficient, coefficient - exact)
+        } else if fixed_denominator {
+            let coefficient = scaled.round() / scale;
+            (coefficient, coefficient - ratios[idx])
+        } else {
+            let coefficient = scaled.round();
+            (coefficient, coefficient - scaled)
+        };
+        parsed.push((el.clone(), coefficient));
+        element_ratios.push(ElementRatio {
+            element: el.clone(),
+            input_value: values[idx],
+            atomic_percent: moles[idx] / total_moles * 100.0,
+            weight_percent: moles[idx] * masses[el] / total_mass * 100.0,
+            ratio: ratios[idx],
+            coefficient,
+            residual,
+            trace,
+        });
+    }
+    let max_abs_residual = element_ratios
+        .iter()
+        .map(|r| r.residual.abs())
+        .fold(0.0, f64::max);
+
+    if !traces.is_empty() {
+        explanation.push(format!(
+            "Trace elements kept at their measured ratio instead of a whole number: {}",
+            traces.join(", ")
+        ));
+    }
+    let formula = format_formula(&parsed);
+    let formula_mass = molar_mass(&collapse_formula(&parsed), masses)?;
+    explanation.push(format!(
+        "Empirical formula: {} ({} g/mol), residuals: {}",
+        formula,
+        format_value(formula_mass),
+        element_ratios
+            .iter()
+            .map(|r| format!("{}={}", r.element, format_value(r.residual)))
+            .collect::<Vec<_>>()
+            .join(", ")
+    ));
+
+    let nominal = match input.nominal_formula.as_deref().map(str::trim) {
+        Some(text) if !text.is_empty() => {
+            let parsed_nominal = parse_formula(text)?;
+            let composition = collapse_formula(&parsed_nominal);
+            let nominal_mass = molar_mass(&composition, masses)?;
+            let nominal_reference =
+                composition
+                    .get(&reference_element)
+                    .copied()
+                    .ok_or_else(|| {
+                        format!(
+                            "Nominal formula {} does not contain {}",
+                            text, reference_element
+                        )
+                    })?;
+            let measured: HashMap<&String, &ElementRatio> =
+                element_ratios.iter().map(|r| (&r.element, r)).collect();
+            let mut order: Vec<String> = Vec::new();
+            for (el, _) in parsed_nominal.iter() {
+                if !order.contains(el) {
+                    order.push(el.clone());
+                }
+            }
+            for el in &elements {
+                if !order.contains(el) {
+                    order.push(el.clone());
+                }
+            }
+            let mut rows = Vec::new();
+            for el in order {
+                let coeff = composition.get(&el).copied().unwrap_or(0.0);
+                let atomic_mass = *masses
+                    .get(&el)
+                    .ok_or_else(|| format!("Missing atomic mass for {}", el))?;
+                let nominal_weight_percent = coeff * atomic_mass / nominal_mass * 100.0;
+                let found = measured.get(&el);
+                let measured_weight_percent = found.map(|r| {
+                    if by_weight {
+                        r.input_value
+                    } else {
+                        r.weight_percent
+                    }
+                });
+                rows.push(NominalComparison {
+                    measured_ratio: found.map(|r| r.ratio),
+                    nominal_ratio: coeff / nominal_reference * reference_value,
+                    measured_weight_percent,
+                    nominal_weight_percent,
+                    weight_percent_delta: measured_weight_percent
+                        .map(|m| m - nominal_weight_percent),
+                    element: el,
+                });
+            }
+            explanation.push(format!(
+                "Nominal {} (wt% found/calc): {}",
+                text,
+                rows.iter()
+                    .map(|r| match r.measured_weight_percent {
+                        Some(m) =>
+                            format!("{}={:.2}/{:.2}", r.element, m, r.nominal_weight_percent),
+                        None => format!("{}=-/{:.2}", r.element, r.nominal_weight_percent),
+                    })
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ));
+            Some(rows)
+        }
+        _ => None,
+    };
+
+    Ok(EmpiricalFormulaOutput {
+        formula,
+        molar_mass: formula_mass,
+        multiplier,
+        reference_element,
+        elements: element_ratios,
+        max_residual: max_abs_residual,
+        nominal,
+        explanation,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn masses() -> HashMap<String, f64> {
+        [
+            ("Fe", 55.845),
+            ("O", 15.999),
+            ("La", 138.905),
+            ("Sr", 87.62),
+            ("Mn", 54.938),
+            ("Ti", 47.867),
+            ("Nb", 92.906),
+        ]
+        .iter()
+        .map(|(el, m)| (el.to_string(), *m))
+        .collect()
+    }
+
+    fn input(basis: &str, pairs: &[(&str, f64)]) -> EmpiricalFormulaInput {
+        EmpiricalFormulaInput {
+            composition: pairs
+                .iter()
+                .map(|(el, v)| ElementAmount {
+                    element: el.to_string(),
+                    value: *v,
+                })
+                .collect(),
+            basis: basis.to_string(),
+            normalize_element: None,
+            normalize_value: None,
+            denominator: None,
+            tolerance: default_tolerance(),
+            nominal_formula: None,
+        }
+    }
+
+    #[test]
+    fn weight_percent_of_hematite_gives_fe2o3() {
+        let output =
+            solve_empirical_formula(&input("wt", &[("Fe", 69.94), ("O", 30.06)]), &masses())
+                .unwrap();
+        assert_eq!(output.formula, "Fe2O3");
+        assert_eq!(output.multiplier, 2);
+        assert_eq!(output.reference_element, "Fe");
+        assert!((output.elements[1].ratio - 1.5).abs() < 1e-3);
+        assert!(output.elements.iter().all(|r| !r.trace));
+        assert!(output.max_residual < 1e-3);
+        assert!((output.molar_mass - 159.687).abs() < 1e-9);
+    }
+
+    #[test]
+    fn multiplier_search_stops_at_the_first_multiple_within_tolerance() {
+        let mut magnetite = input("wt", &[("Fe", 72.36), ("O", 27.64)]);
+        let output = solve_empirical_formula(&magnetite, &masses()).unwrap();
+        assert_eq!(output.formula, "Fe3O4");
+        assert_eq!(output.multiplier, 3);
+
+        // A loose tolerance accepts the first multiplier.
+        magnetite.tolerance = 0.4;
+        let output = solve_empirical_formula(&magnetite, &masses()).unwrap();
+        assert_eq!(output.formula, "FeO");
+        assert_eq!(output.multiplier, 1);
+        assert!((output.elements[1].residual + 1.0 / 3.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn fixed_denominator_keeps_fractional_coefficients() {
+        let mut lsmo = input(
+            "at",
+            &[("La", 14.0), ("Sr", 6.0), ("Mn", 20.0), ("O", 60.0)],
+        );
+        lsmo.normalize_element = Some("Mn".to_string());
+        lsmo.denominator = Some(10);
+        let output = solve_empirical_formula(&lsmo, &masses()).unwrap();
+        assert_eq!(output.formula, "La0.7Sr0.3MnO3");
+        assert_eq!(output.multiplier, 10);
+        assert!(output.max_residual < 1e-12);
+
+        lsmo.denominator = Some(0);
+        assert_eq!(
+            solve_empirical_formula(&lsmo, &masses()).err(),
+            Some("Denominator must be positive".to_string())
+        );
+    }
+
+    #[test]
+    fn atomic_percent_input_reports_weight_percent() {
+        let output =
+            solve_empirical_formula(&input("at", &[("Fe", 40.0), ("O", 60.0)]), &masses()).unwrap();
+        assert_eq!(output.formula, "Fe2O3");
+        assert!((output.elements[0].atomic_percent - 40.0).abs() < 1e-9);
+        assert!((output.elements[0].weight_percent - 69.94).abs() < 0.01);
+        assert!((output.elements[1].weight_percent - 30.06).abs() < 0.01);
+        assert_eq!(
+            solve_empirical_formula(&input("mol", &[("Fe", 1.0)]), &masses()).err(),
+            Some("Unknown composition basis: mol".to_string())
+        );
+    }
+
+    #[test]
+    fn trace_elements_keep_their_measured_ratio() {
+        let mut doped = input("at", &[("Ti", 1.0), ("O", 2.0), ("Nb", 0.012)]);
+        doped.normalize_element = Some("Ti".to_string());
+        let output = solve_empirical_formula(&doped, &masses()).unwrap();
+        assert_eq!(output.formula, "TiO2Nb0.012");
+        let niobium = &output.elements[2];
+        assert!(niobium.trace);
+        assert!((niobium.coefficient - 0.012).abs() < 1e-12);
+        assert!(niobium.residual.abs() < 1e-12);
+        assert!(output
+            .explanation
+            .iter()
+            .any(|line| line.ends_with("instead of a whole number: Nb")));
+    }
+
+    #[test]
+    fn nominal_comparison_lists_found_and_calculated_weight_percent() {
+        let mut hematite = input("wt", &[("Fe", 69.5), ("O", 30.5)]);
+        hematite.nominal_formula = Some("Fe2O3".to_string());
+        let output = solve_empirical_formula(&hematite, &masses()).unwrap();
+        let rows = output.nominal.unwrap();
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0].element, "Fe");
+        assert_eq!(rows[0].measured_weight_percent, Some(69.5));
+        assert!((rows[0].nominal_weight_percent - 69.943).abs() < 1e-3);
+        assert!((rows[0].weight_percent_delta.unwrap() + 0.443).abs() < 1e-3);
+        assert!((rows[1].nominal_ratio - 1.5).abs() < 1e-12);
+
+        hematite.nominal_formula = Some("TiO2".to_string());
+        assert_eq!(
+            solve_empirical_formula(&hematite, &masses()).err(),
+            Some("Nominal formula TiO2 does not contain Fe".to_string())
+        );
+    }
+}
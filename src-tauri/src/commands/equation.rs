@@ -0,0 +1,286 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::chem::equation::{element_matrix, format_equation, integer_coefficients, null_space};
+use crate::chem::mass::collapse_formula;
+use crate::chem::parse::parse_formula;
+
+/// Gases released for leftover volatile elements, in the order they take up
+/// oxygen; whatever oxygen remains leaves as O2.
+const GASES: [(&str, &str, f64, f64); 4] = [
+    ("H", "H2O", 2.0, 1.0),
+    ("C", "CO2", 1.0, 2.0),
+    ("N", "NO2", 1.0, 2.0),
+    ("S", "SO2", 1.0, 2.0),
+];
+
+#[derive(Deserialize)]
+pub struct EquationInput {
+    pub reactants: Vec<String>,
+    pub products: Vec<String>,
+}
+
+#[derive(Serialize)]
+pub struct EquationTerm {
+    pub species: String,
+    pub coefficient: i64,
+}
+
+#[derive(Serialize)]
+pub struct BalancedReaction {
+    pub equation: String,
+    /// Reactants then products; a negative coefficient means the species
+    /// appears on the other side.
+    pub terms: Vec<EquationTerm>,
+}
+
+#[derive(Serialize)]
+pub struct EquationOutput {
+    pub reactions: Vec<BalancedReaction>,
+    pub explanation: Vec<String>,
+}
+
+fn compositions(species: &[String]) -> Result<Vec<HashMap<String, f64>>, String> {
+    species
+        .iter()
+        .map(|s| parse_formula(s).map(|parsed| collapse_formula(&parsed)))
+        .collect()
+}
+
+/// Balanced equation for a finished calculation: reagents at their computed
+/// amounts give one formula unit of target plus gases for the leftover
+/// volatile elements. The error says why none can be written: a leftover
+/// element with no gas to leave as, or amounts without small whole-number
+/// ratios.
+pub fn calculation_equation(
+    reagents: &[(&str, &HashMap<String, f64>, f64)],
+    target: &str,
+    target_composition: &HashMap<String, f64>,
+    target_moles: f64,
+) -> Result<String, String> {
+    let mut leftover: HashMap<String, f64> = HashMap::new();
+    for (_, composition, moles) in reagents {
+        for (el, c) in composition.iter() {
+            *leftover.entry(el.clone()).or_insert(0.0) += c * moles / target_moles;
+        }
+    }
+    for (el, c) in target_composition {
+        *leftover.entry(el.clone()).or_insert(0.0) -= c;
+    }
+
+    let mut names: Vec<String> = reagents
+        .iter()
+        .map(|(name, _, _)| name.to_string())
+        .collect();
+    let mut vector: Vec<f64> = reagents.iter().map(|(_, _, n)| n / target_moles).collect();
+    names.push(target.to_string());
+    vector.push(1.0);
+    for (el, gas, per_gas, oxygen) in GASES {
+        let amount = leftover.remove(el).unwrap_or(0.0) / per_gas;
+        if amount.abs() > 1e-9 {
+            *leftover.entry("O".to_string()).or_insert(0.0) -= amount * oxygen;
+            names.push(gas.to_string());
+            vector.push(amount);
+        }
+    }
+    let oxygen = leftover.remove("O").unwrap_or(0.0) / 2.0;
+    if oxygen.abs() > 1e-9 {
+        names.push("O2".to_string());
+        vector.push(oxygen);
+    }
+    let mut stranded: Vec<&String> = leftover
+        .iter()
+        .filter(|(_, v)| v.abs() > 1e-9)
+        .map(|(el, _)| el)
+        .collect();
+    if !stranded.is_empty() {
+        stranded.sort();
+        return Err(format!(
+            "leftover {} does not form a known gas",
+            stranded
+                .iter()
+                .map(|el| el.as_str())
+                .collect::<Vec<_>>()
+                .join(", ")
+        ));
+    }
+    let coefficients =
+        integer_coefficients(&vector).ok_or("the amounts have no small whole-number ratio")?;
+    Ok(format_equation(&names, reagents.len(), &coefficients))
+}
+
+/// Balances reactants -> products with the smallest integer coefficients.
+/// When the element matrix leaves more than one degree of freedom, each
+/// basis vector of its null space is reported as an independent reaction.
+#[tauri::command]
+pub fn balance_equation(input: EquationInput) -> Result<EquationOutput, String> {
+    let reactants: Vec<String> = input
+        .reactants
+        .iter()
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+        .collect();
+    let products: Vec<String> = input
+        .products
+        .iter()
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+        .collect();
+    if reactants.is_empty() || products.is_empty() {
+        return Err("Both reactants and products are required".to_string());
+    }
+    let (elements, matrix) = element_matrix(&compositions(&reactants)?, &compositions(&products)?);
+    let names: Vec<String> = reactants.iter().chain(products.iter()).cloned().collect();
+    let basis = null_space(&matrix, names.len());
+    if basis.is_empty() {
+        return Err("No balanced equation exists for these species".to_string());
+    }
+
+    let mut explanation = vec![
+        format!("Elements: {}", elements.join(", ")),
+        format!(
+            "Element matrix: {} x {} (rank {}), {} independent reaction(s)",
+            elements.len(),
+            names.len(),
+            names.len() - basis.len(),
+            basis.len()
+        ),
+    ];
+    let mut reactions = Vec::new();
+    for vector in basis {
+        let mut coefficients = integer_coefficients(&vector)
+            .ok_or("Coefficients are not rational with a small denominator")?;
+        // Prefer the orientation that keeps most species on their given side.
+        let on_side = coefficients.iter().filter(|c| **c > 0).count();
+        let off_side = coefficients.iter().filter(|c| **c < 0).count();
+        if off_side > on_side {
+            coefficients.iter_mut().for_each(|c| *c = -*c);
+        }
+        let equation = format_equation(&names, reactants.len(), &coefficients);
+        let unused: Vec<&str> = names
+            .iter()
+            .zip(coefficients.iter())
+            .filter(|(_, c)| **c == 0)
+            .map(|(n, _)| n.as_str())
+            .collect();
+        explanation.push(format!(
+            "{}{}",
+            equation,
+            if unused.is_empty() {
+                String::new()
+            } else {
+                format!(" (not involved: {})", unused.join(", "))
+            }
+        ));
+        reactions.push(BalancedReaction {
+            equation,
+            terms: names
+                .iter()
+                .zip(coefficients.iter())
+                .map(|(species, c)| EquationTerm {
+                    species: species.clone(),
+                    coefficient: *c,
+                })
+                .collect(),
+        });
+    }
+    if reactions.len() > 1 {
+        explanation
+            .push("Any linear combination of these reactions is also balanced".to_string());
+    }
+
+    Ok(EquationOutput {
+        reactions,
+        explanation,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn balance(reactants: &[&str], products: &[&str]) -> Result<EquationOutput, String> {
+        balance_equation(EquationInput {
+            reactants: reactants.iter().map(|s| s.to_string()).collect(),
+            products: products.iter().map(|s| s.to_string()).collect(),
+        })
+    }
+
+    fn equations(output: &EquationOutput) -> Vec<&str> {
+        output
+            .reactions
+            .iter()
+            .map(|r| r.equation.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn balances_oxygen_release() {
+        let output = balance(&["La2O3", "MnO2"], &["LaMnO3", "O2"]).unwrap();
+        assert_eq!(
+            equations(&output),
+            vec!["2 La2O3 + 4 MnO2 -> 4 LaMnO3 + O2"]
+        );
+    }
+
+    #[test]
+    fn balances_carbonate_decomposition() {
+        let output = balance(&["BaCO3", "TiO2"], &["BaTiO3", "CO2"]).unwrap();
+        assert_eq!(equations(&output), vec!["BaCO3 + TiO2 -> BaTiO3 + CO2"]);
+        assert_eq!(output.reactions[0].terms[3].coefficient, 1);
+    }
+
+    #[test]
+    fn reports_each_independent_reaction() {
+        let output = balance(&["Fe", "O2"], &["FeO", "Fe2O3", "Fe3O4"]).unwrap();
+        assert_eq!(
+            equations(&output),
+            vec![
+                "2 Fe + O2 -> 2 FeO",
+                "4 Fe + 3 O2 -> 2 Fe2O3",
+                "3 Fe + 2 O2 -> Fe3O4"
+            ]
+        );
+        assert!(output
+            .explanation
+            .contains(&"Any linear combination of these reactions is also balanced".to_string()));
+    }
+
+    #[test]
+    fn rejects_species_that_cannot_balance() {
+        assert_eq!(
+            balance(&["NaCl"], &["KBr"]).err(),
+            Some("No balanced equation exists for these species".to_string())
+        );
+        assert_eq!(
+            balance(&["H2"], &[" "]).err(),
+            Some("Both reactants and products are required".to_string())
+        );
+    }
+
+    #[test]
+    fn calculation_equation_releases_leftovers_as_gases() {
+        let baco3 = collapse_formula(&parse_formula("BaCO3").unwrap());
+        let tio2 = collapse_formula(&parse_formula("TiO2").unwrap());
+        let target = collapse_formula(&parse_formula("BaTiO3").unwrap());
+        let equation = calculation_equation(
+            &[("BaCO3", &baco3, 0.002), ("TiO2", &tio2, 0.002)],
+            "BaTiO3",
+            &target,
+            0.002,
+        );
+        assert_eq!(equation, Ok("BaCO3 + TiO2 -> BaTiO3 + CO2".to_string()));
+
+        let bacl2 = collapse_formula(&parse_formula("BaCl2").unwrap());
+        let equation = calculation_equation(
+            &[("BaCl2", &bacl2, 0.002), ("TiO2", &tio2, 0.002)],
+            "BaTiO3",
+            &target,
+            0.002,
+        );
+        assert_eq!(
+            equation,
+            Err("leftover Cl does not form a known gas".to_string())
+        );
+    }
+}
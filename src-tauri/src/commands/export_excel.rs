@@ -2,9 +2,15 @@ use rust_xlsxwriter::Workbook;
 
 use crate::commands::export_helpers::pick_save_path;
 use crate::commands::export_types::CalculationOutput;
+use crate::commands::settings::read_settings;
 
+/// Writes the calculation as a workbook. Masses are rounded to the balance
+/// and written in the configured mass unit; moles and molar masses to the
+/// configured significant figures.
 #[tauri::command]
 pub fn export_to_excel(output: CalculationOutput) -> Result<(), String> {
+    let precision = read_settings()?.precision();
+    let unit = precision.mass_unit.symbol();
     let path = pick_save_path(
         "Save Excel Report",
         "Excel Workbook",
@@ -22,21 +28,38 @@ pub fn export_to_excel(output: CalculationOutput) -> Result<(), String> {
         .write_string(0, 1, output.target_formula.trim())
         .map_err(|e| e.to_string())?;
     worksheet
-        .write_string(0, 2, "Target mass")
+        .write_string(0, 2, format!("Target mass ({})", unit))
         .map_err(|e| e.to_string())?;
     worksheet
-        .write_number(0, 3, output.mass_check.target_mass)
+        .write_number(0, 3, precision.unit_mass(output.mass_check.target_mass))
         .map_err(|e| e.to_string())?;
+    if let Some(equation) = &output.equation {
+        worksheet
+            .write_string(0, 4, "Equation")
+            .map_err(|e| e.to_string())?;
+        worksheet
+            .write_string(0, 5, equation)
+            .map_err(|e| e.to_string())?;
+    }
 
     worksheet
         .write_string(1, 0, "compound")
         .map_err(|e| e.to_string())?;
     worksheet
-        .write_string(1, 1, "calculated mass")
+        .write_string(1, 1, format!("calculated mass ({})", unit))
         .map_err(|e| e.to_string())?;
     worksheet
-        .write_string(1, 2, "weighed mass")
+        .write_string(1, 2, format!("weighed mass ({})", unit))
         .map_err(|e| e.to_string())?;
+    let has_volume = output.reagents.iter().any(|r| r.volume_ml.is_some());
+    if has_volume {
+        worksheet
+            .write_string(1, 3, format!("solution mass ({})", unit))
+            .map_err(|e| e.to_string())?;
+        worksheet
+            .write_string(1, 4, "volume (mL)")
+            .map_err(|e| e.to_string())?;
+    }
 
     for (index, item) in output.reagents.iter().enumerate() {
         let row = (index + 2) as u32;
@@ -44,11 +67,224 @@ pub fn export_to_excel(output: CalculationOutput) -> Result<(), String> {
             .write_string(row, 0, &item.reagent)
             .map_err(|e| e.to_string())?;
         worksheet
-            .write_number(row, 1, item.mass)
+            .write_number(row, 1, precision.unit_mass(item.mass))
             .map_err(|e| e.to_string())?;
         worksheet
             .write_string(row, 2, "")
             .map_err(|e| e.to_string())?;
+        if let Some(solution_mass) = item.solution_mass {
+            worksheet
+                .write_number(row, 3, precision.unit_mass(solution_mass))
+                .map_err(|e| e.to_string())?;
+        }
+        if let Some(volume) = item.volume_ml {
+            worksheet
+                .write_number(row, 4, volume)
+                .map_err(|e| e.to_string())?;
+        }
+    }
+
+    if !output.composition.is_empty() {
+        let sheet = workbook.add_worksheet();
+        sheet.set_name("Composition").map_err(|e| e.to_string())?;
+        let headers = ["element", "coefficient", "g/mol contribution", "wt%", "at%"];
+        for (col, header) in headers.iter().enumerate() {
+            sheet
+                .write_string(0, col as u16, *header)
+                .map_err(|e| e.to_string())?;
+        }
+        for (index, item) in output.composition.iter().enumerate() {
+            let row = (index + 1) as u32;
+            sheet
+                .write_string(row, 0, &item.element)
+                .map_err(|e| e.to_string())?;
+            sheet
+                .write_number(row, 1, item.coefficient)
+                .map_err(|e| e.to_string())?;
+            sheet
+                .write_number(row, 2, item.mass_contribution)
+                .map_err(|e| e.to_string())?;
+            sheet
+                .write_number(row, 3, item.mass_percent)
+                .map_err(|e| e.to_string())?;
+            sheet
+                .write_number(row, 4, item.atomic_percent)
+                .map_err(|e| e.to_string())?;
+        }
+
+        let offset = (output.composition.len() + 2) as u32;
+        if !output.oxide_composition.is_empty() {
+            let headers = ["oxide", "wt%", "normalized wt%"];
+            for (col, header) in headers.iter().enumerate() {
+                sheet
+                    .write_string(offset, col as u16, *header)
+                    .map_err(|e| e.to_string())?;
+            }
+            for (index, item) in output.oxide_composition.iter().enumerate() {
+                let row = offset + 1 + index as u32;
+                sheet
+                    .write_string(row, 0, &item.oxide)
+                    .map_err(|e| e.to_string())?;
+                sheet
+                    .write_number(row, 1, item.mass_percent)
+                    .map_err(|e| e.to_string())?;
+                sheet
+                    .write_number(row, 2, item.normalized_percent)
+                    .map_err(|e| e.to_string())?;
+            }
+        }
+    }
+
+    if !output.phases.is_empty() {
+        let sheet = workbook.add_worksheet();
+        sheet.set_name("Phases").map_err(|e| e.to_string())?;
+        let mass_header = format!("mass ({})", unit);
+        let headers = [
+            "phase",
+            "mole fraction",
+            "weight fraction",
+            "molar mass (g/mol)",
+            "moles",
+            mass_header.as_str(),
+        ];
+        for (col, header) in headers.iter().enumerate() {
+            sheet
+                .write_string(0, col as u16, *header)
+                .map_err(|e| e.to_string())?;
+        }
+        for (index, item) in output.phases.iter().enumerate() {
+            let row = (index + 1) as u32;
+            sheet
+                .write_string(row, 0, &item.formula)
+                .map_err(|e| e.to_string())?;
+            let values = [
+                item.mole_fraction,
+                item.weight_fraction,
+                precision.significant(item.molar_mass),
+                precision.significant(item.moles),
+                precision.unit_mass(item.mass),
+            ];
+            for (col, value) in values.iter().enumerate() {
+                sheet
+                    .write_number(row, (col + 1) as u16, *value)
+                    .map_err(|e| e.to_string())?;
+            }
+        }
+    }
+
+    if output.reagents.iter().any(|r| r.cost.is_some()) {
+        let sheet = workbook.add_worksheet();
+        sheet.set_name("Cost").map_err(|e| e.to_string())?;
+        let currency = output.currency.clone().unwrap_or_default();
+        let mass_header = format!("mass ({})", unit);
+        let headers = ["reagent", mass_header.as_str(), "cost", "currency"];
+        for (col, header) in headers.iter().enumerate() {
+            sheet
+                .write_string(0, col as u16, *header)
+                .map_err(|e| e.to_string())?;
+        }
+        for (index, item) in output.reagents.iter().enumerate() {
+            let row = (index + 1) as u32;
+            sheet
+                .write_string(row, 0, &item.reagent)
+                .map_err(|e| e.to_string())?;
+            sheet
+                .write_number(row, 1, precision.unit_mass(item.mass))
+                .map_err(|e| e.to_string())?;
+            if let Some(cost) = item.cost {
+                sheet
+                    .write_number(row, 2, cost)
+                    .map_err(|e| e.to_string())?;
+                sheet
+                    .write_string(row, 3, &currency)
+                    .map_err(|e| e.to_string())?;
+            }
+        }
+        if let Some(total) = output.total_cost {
+            let row = (output.reagents.len() + 1) as u32;
+            sheet
+                .write_string(row, 0, "total")
+                .map_err(|e| e.to_string())?;
+            sheet
+                .write_number(row, 2, total)
+                .map_err(|e| e.to_string())?;
+            sheet
+                .write_string(row, 3, &currency)
+                .map_err(|e| e.to_string())?;
+        }
+    }
+
+    if output.reagents.iter().any(|r| r.mass_uncertainty.is_some()) {
+        let sheet = workbook.add_worksheet();
+        sheet.set_name("Uncertainty").map_err(|e| e.to_string())?;
+        let headers = ["item", "value", "standard uncertainty"];
+        for (col, header) in headers.iter().enumerate() {
+            sheet
+                .write_string(0, col as u16, *header)
+                .map_err(|e| e.to_string())?;
+        }
+        let mut row = 1;
+        for item in &output.reagents {
+            let Some(u) = item.mass_uncertainty else {
+                continue;
+            };
+            sheet
+                .write_string(row, 0, format!("{} ({})", item.reagent, unit))
+                .map_err(|e| e.to_string())?;
+            sheet
+                .write_number(row, 1, precision.to_unit(item.mass))
+                .map_err(|e| e.to_string())?;
+            sheet
+                .write_number(row, 2, precision.to_unit(u))
+                .map_err(|e| e.to_string())?;
+            row += 1;
+        }
+        for item in &output.composition_uncertainty {
+            sheet
+                .write_string(row, 0, &item.element)
+                .map_err(|e| e.to_string())?;
+            sheet
+                .write_number(row, 1, item.coefficient)
+                .map_err(|e| e.to_string())?;
+            sheet
+                .write_number(row, 2, item.uncertainty)
+                .map_err(|e| e.to_string())?;
+            row += 1;
+        }
+    }
+
+    if !output.dilution_steps.is_empty() {
+        let sheet = workbook.add_worksheet();
+        sheet.set_name("Dilution").map_err(|e| e.to_string())?;
+        let headers = [
+            "step",
+            "from",
+            "to",
+            "aliquot (mL)",
+            "diluent (mL)",
+            "final volume (mL)",
+        ];
+        for (col, header) in headers.iter().enumerate() {
+            sheet
+                .write_string(0, col as u16, *header)
+                .map_err(|e| e.to_string())?;
+        }
+        for (index, item) in output.dilution_steps.iter().enumerate() {
+            let row = (index + 1) as u32;
+            let values = [
+                item.step as f64,
+                item.source_concentration,
+                item.target_concentration,
+                item.aliquot_ml,
+                item.diluent_ml,
+                item.final_volume_ml,
+            ];
+            for (col, value) in values.iter().enumerate() {
+                sheet
+                    .write_number(row, col as u16, *value)
+                    .map_err(|e| e.to_string())?;
+            }
+        }
     }
 
     workbook.save(path).map_err(|e| e.to_string())?;
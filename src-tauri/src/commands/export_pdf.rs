@@ -1,10 +1,15 @@
 use chrono::Local;
-use printpdf::{BuiltinFont, Line, Mm, PdfDocument, Point};
+use printpdf::{
+    BuiltinFont, IndirectFontRef, Line, Mm, PdfDocument, PdfDocumentReference, PdfLayerReference,
+    Point,
+};
 use std::{fs::File, io::BufWriter};
 
+use crate::commands::calc_helpers::Precision;
 use crate::commands::export_helpers::pick_save_path;
 use crate::commands::export_types::CalculationOutput;
 use crate::commands::settings::read_settings;
+use crate::commands::uncertainty::format_uncertain;
 
 fn wrap_text(line: &str, max_chars: usize) -> Vec<String> {
     let mut out = Vec::new();
@@ -31,11 +36,435 @@ fn wrap_text(line: &str, max_chars: usize) -> Vec<String> {
     out
 }
 
+fn ensure_space(doc: &PdfDocumentReference, layer: &mut PdfLayerReference, y: &mut Mm, min: f64) {
+    if y.0 < min {
+        let (new_page, new_layer) = doc.add_page(Mm(210.0), Mm(297.0), "Layer 1");
+        *layer = doc.get_page(new_page).get_layer(new_layer);
+        *y = Mm(280.0);
+    }
+}
+
+fn draw_rule(layer: &PdfLayerReference, y: Mm) {
+    let line = Line {
+        points: vec![
+            (Point::new(Mm(20.0), y), false),
+            (Point::new(Mm(190.0), y), false),
+        ],
+        is_closed: false,
+        has_fill: false,
+        has_stroke: true,
+        is_clipping_path: false,
+    };
+    layer.add_shape(line);
+}
+
+fn write_composition(
+    doc: &PdfDocumentReference,
+    layer: &mut PdfLayerReference,
+    y: &mut Mm,
+    font: &IndirectFontRef,
+    output: &CalculationOutput,
+) {
+    if output.composition.is_empty() {
+        return;
+    }
+    *y -= Mm(14.0);
+    ensure_space(doc, layer, y, 40.0);
+    layer.use_text("Composition", 14.0, Mm(20.0), *y, font);
+    *y -= Mm(8.0);
+    layer.use_text("Element", 12.0, Mm(20.0), *y, font);
+    layer.use_text("wt%", 12.0, Mm(60.0), *y, font);
+    layer.use_text("at%", 12.0, Mm(100.0), *y, font);
+    layer.use_text("g/mol", 12.0, Mm(140.0), *y, font);
+    *y -= Mm(4.0);
+    draw_rule(layer, *y);
+    *y -= Mm(7.0);
+    for item in &output.composition {
+        ensure_space(doc, layer, y, 20.0);
+        layer.use_text(&item.element, 12.0, Mm(20.0), *y, font);
+        layer.use_text(
+            format!("{:.4}", item.mass_percent),
+            12.0,
+            Mm(60.0),
+            *y,
+            font,
+        );
+        layer.use_text(
+            format!("{:.4}", item.atomic_percent),
+            12.0,
+            Mm(100.0),
+            *y,
+            font,
+        );
+        layer.use_text(
+            format!("{:.6}", item.mass_contribution),
+            12.0,
+            Mm(140.0),
+            *y,
+            font,
+        );
+        *y -= Mm(7.0);
+    }
+    if output.oxide_composition.is_empty() {
+        return;
+    }
+    *y -= Mm(6.0);
+    ensure_space(doc, layer, y, 30.0);
+    layer.use_text("Oxide", 12.0, Mm(20.0), *y, font);
+    layer.use_text("wt%", 12.0, Mm(60.0), *y, font);
+    layer.use_text("normalized wt%", 12.0, Mm(100.0), *y, font);
+    *y -= Mm(4.0);
+    draw_rule(layer, *y);
+    *y -= Mm(7.0);
+    for item in &output.oxide_composition {
+        ensure_space(doc, layer, y, 20.0);
+        layer.use_text(&item.oxide, 12.0, Mm(20.0), *y, font);
+        layer.use_text(
+            format!("{:.4}", item.mass_percent),
+            12.0,
+            Mm(60.0),
+            *y,
+            font,
+        );
+        layer.use_text(
+            format!("{:.4}", item.normalized_percent),
+            12.0,
+            Mm(100.0),
+            *y,
+            font,
+        );
+        *y -= Mm(7.0);
+    }
+}
+
+fn write_equation(
+    doc: &PdfDocumentReference,
+    layer: &mut PdfLayerReference,
+    y: &mut Mm,
+    font: &IndirectFontRef,
+    output: &CalculationOutput,
+) {
+    let Some(equation) = &output.equation else {
+        return;
+    };
+    *y -= Mm(14.0);
+    ensure_space(doc, layer, y, 30.0);
+    layer.use_text("Reaction", 14.0, Mm(20.0), *y, font);
+    *y -= Mm(8.0);
+    for wrapped in wrap_text(equation, 80) {
+        ensure_space(doc, layer, y, 20.0);
+        layer.use_text(wrapped, 12.0, Mm(20.0), *y, font);
+        *y -= Mm(6.0);
+    }
+}
+
+fn write_phases(
+    doc: &PdfDocumentReference,
+    layer: &mut PdfLayerReference,
+    y: &mut Mm,
+    font: &IndirectFontRef,
+    output: &CalculationOutput,
+    precision: &Precision,
+) {
+    if output.phases.is_empty() {
+        return;
+    }
+    *y -= Mm(14.0);
+    ensure_space(doc, layer, y, 40.0);
+    layer.use_text("Phases", 14.0, Mm(20.0), *y, font);
+    *y -= Mm(8.0);
+    layer.use_text("Phase", 12.0, Mm(20.0), *y, font);
+    layer.use_text("mol frac.", 12.0, Mm(70.0), *y, font);
+    layer.use_text("wt frac.", 12.0, Mm(100.0), *y, font);
+    layer.use_text("Moles", 12.0, Mm(130.0), *y, font);
+    layer.use_text(
+        format!("Mass ({})", precision.mass_unit.symbol()),
+        12.0,
+        Mm(165.0),
+        *y,
+        font,
+    );
+    *y -= Mm(4.0);
+    draw_rule(layer, *y);
+    *y -= Mm(7.0);
+    for item in &output.phases {
+        ensure_space(doc, layer, y, 20.0);
+        layer.use_text(&item.formula, 12.0, Mm(20.0), *y, font);
+        layer.use_text(
+            format!("{:.4}", item.mole_fraction),
+            12.0,
+            Mm(70.0),
+            *y,
+            font,
+        );
+        layer.use_text(
+            format!("{:.4}", item.weight_fraction),
+            12.0,
+            Mm(100.0),
+            *y,
+            font,
+        );
+        layer.use_text(
+            precision.format_significant(item.moles),
+            12.0,
+            Mm(130.0),
+            *y,
+            font,
+        );
+        layer.use_text(precision.format_mass(item.mass), 12.0, Mm(165.0), *y, font);
+        *y -= Mm(7.0);
+    }
+}
+
+fn write_cost(
+    doc: &PdfDocumentReference,
+    layer: &mut PdfLayerReference,
+    y: &mut Mm,
+    font: &IndirectFontRef,
+    output: &CalculationOutput,
+    precision: &Precision,
+) {
+    if output.reagents.iter().all(|r| r.cost.is_none()) {
+        return;
+    }
+    let currency = output.currency.clone().unwrap_or_default();
+    *y -= Mm(14.0);
+    ensure_space(doc, layer, y, 40.0);
+    layer.use_text("Cost", 14.0, Mm(20.0), *y, font);
+    *y -= Mm(8.0);
+    layer.use_text("Reagent", 12.0, Mm(20.0), *y, font);
+    layer.use_text(
+        format!("Mass ({})", precision.mass_unit.symbol()),
+        12.0,
+        Mm(90.0),
+        *y,
+        font,
+    );
+    layer.use_text(format!("Cost {}", currency), 12.0, Mm(140.0), *y, font);
+    *y -= Mm(4.0);
+    draw_rule(layer, *y);
+    *y -= Mm(7.0);
+    for item in &output.reagents {
+        ensure_space(doc, layer, y, 20.0);
+        layer.use_text(&item.reagent, 12.0, Mm(20.0), *y, font);
+        layer.use_text(precision.format_mass(item.mass), 12.0, Mm(90.0), *y, font);
+        let cost = item
+            .cost
+            .map(|c| format!("{:.2}", c))
+            .unwrap_or("-".to_string());
+        layer.use_text(cost, 12.0, Mm(140.0), *y, font);
+        *y -= Mm(7.0);
+    }
+    if let Some(total) = output.total_cost {
+        ensure_space(doc, layer, y, 20.0);
+        layer.use_text(
+            format!("Total: {:.2} {}", total, currency),
+            12.0,
+            Mm(140.0),
+            *y,
+            font,
+        );
+        *y -= Mm(7.0);
+    }
+}
+
+fn write_batch_advice(
+    doc: &PdfDocumentReference,
+    layer: &mut PdfLayerReference,
+    y: &mut Mm,
+    font: &IndirectFontRef,
+    output: &CalculationOutput,
+    precision: &Precision,
+) {
+    let Some(advice) = &output.batch_advice else {
+        return;
+    };
+    *y -= Mm(14.0);
+    ensure_space(doc, layer, y, 40.0);
+    layer.use_text("Small masses", 14.0, Mm(20.0), *y, font);
+    *y -= Mm(4.0);
+    draw_rule(layer, *y);
+    *y -= Mm(7.0);
+    let mut lines = vec![
+        format!(
+            "Below {}: {}",
+            precision.display_mass(advice.min_weighable),
+            advice.small_reagents.join(", ")
+        ),
+        format!(
+            "Minimum target mass: {}",
+            precision.display_mass(advice.minimum_target_mass)
+        ),
+    ];
+    for batch in &advice.master_batches {
+        lines.push(format!(
+            "Master batch 1:{} {}:{}: mix {} {} with {} {}; weigh {} pre-mix and {} {}",
+            batch.dilution,
+            batch.dopant,
+            batch.host,
+            precision.display_mass(batch.batch_dopant_mass),
+            batch.dopant,
+            precision.display_mass(batch.batch_host_mass),
+            batch.host,
+            precision.display_mass(batch.aliquot_mass),
+            precision.display_mass(batch.host_mass),
+            batch.host
+        ));
+    }
+    for line in lines {
+        for wrapped in wrap_text(&line, 90) {
+            ensure_space(doc, layer, y, 20.0);
+            layer.use_text(wrapped, 10.0, Mm(20.0), *y, font);
+            *y -= Mm(5.0);
+        }
+    }
+}
+
+fn write_uncertainty(
+    doc: &PdfDocumentReference,
+    layer: &mut PdfLayerReference,
+    y: &mut Mm,
+    font: &IndirectFontRef,
+    output: &CalculationOutput,
+    precision: &Precision,
+) {
+    if output.reagents.iter().all(|r| r.mass_uncertainty.is_none()) {
+        return;
+    }
+    *y -= Mm(14.0);
+    ensure_space(doc, layer, y, 40.0);
+    layer.use_text("Uncertainty", 14.0, Mm(20.0), *y, font);
+    *y -= Mm(8.0);
+    layer.use_text("Reagent", 12.0, Mm(20.0), *y, font);
+    layer.use_text(
+        format!("Mass ({})", precision.mass_unit.symbol()),
+        12.0,
+        Mm(90.0),
+        *y,
+        font,
+    );
+    *y -= Mm(4.0);
+    draw_rule(layer, *y);
+    *y -= Mm(7.0);
+    for item in &output.reagents {
+        let Some(u) = item.mass_uncertainty else {
+            continue;
+        };
+        ensure_space(doc, layer, y, 20.0);
+        layer.use_text(&item.reagent, 12.0, Mm(20.0), *y, font);
+        layer.use_text(
+            format_uncertain(precision.to_unit(item.mass), precision.to_unit(u)),
+            12.0,
+            Mm(90.0),
+            *y,
+            font,
+        );
+        *y -= Mm(7.0);
+    }
+    if !output.composition_uncertainty.is_empty() {
+        *y -= Mm(3.0);
+        ensure_space(doc, layer, y, 20.0);
+        let composition = output
+            .composition_uncertainty
+            .iter()
+            .map(|c| {
+                format!(
+                    "{} = {}",
+                    c.element,
+                    format_uncertain(c.coefficient, c.uncertainty)
+                )
+            })
+            .collect::<Vec<_>>()
+            .join(", ");
+        for wrapped in wrap_text(&format!("Composition: {}", composition), 90) {
+            ensure_space(doc, layer, y, 20.0);
+            layer.use_text(wrapped, 10.0, Mm(20.0), *y, font);
+            *y -= Mm(5.0);
+        }
+    }
+}
+
+fn write_safety(
+    doc: &PdfDocumentReference,
+    layer: &mut PdfLayerReference,
+    y: &mut Mm,
+    font: &IndirectFontRef,
+    output: &CalculationOutput,
+) {
+    if output.reagents.iter().all(|r| r.warnings.is_empty()) {
+        return;
+    }
+    *y -= Mm(14.0);
+    ensure_space(doc, layer, y, 40.0);
+    layer.use_text("Safety", 14.0, Mm(20.0), *y, font);
+    *y -= Mm(4.0);
+    draw_rule(layer, *y);
+    *y -= Mm(7.0);
+    for item in output.reagents.iter().filter(|r| !r.warnings.is_empty()) {
+        ensure_space(doc, layer, y, 30.0);
+        layer.use_text(&item.reagent, 12.0, Mm(20.0), *y, font);
+        *y -= Mm(6.0);
+        for warning in &item.warnings {
+            for wrapped in wrap_text(warning, 90) {
+                ensure_space(doc, layer, y, 20.0);
+                layer.use_text(wrapped, 10.0, Mm(25.0), *y, font);
+                *y -= Mm(5.0);
+            }
+        }
+        *y -= Mm(3.0);
+    }
+}
+
+fn write_dilution(
+    doc: &PdfDocumentReference,
+    layer: &mut PdfLayerReference,
+    y: &mut Mm,
+    font: &IndirectFontRef,
+    output: &CalculationOutput,
+) {
+    if output.dilution_steps.is_empty() {
+        return;
+    }
+    *y -= Mm(14.0);
+    ensure_space(doc, layer, y, 40.0);
+    layer.use_text("Dilution", 14.0, Mm(20.0), *y, font);
+    *y -= Mm(8.0);
+    layer.use_text("Step", 12.0, Mm(20.0), *y, font);
+    layer.use_text("From", 12.0, Mm(40.0), *y, font);
+    layer.use_text("To", 12.0, Mm(75.0), *y, font);
+    layer.use_text("Aliquot (mL)", 12.0, Mm(110.0), *y, font);
+    layer.use_text("Diluent (mL)", 12.0, Mm(150.0), *y, font);
+    *y -= Mm(4.0);
+    draw_rule(layer, *y);
+    *y -= Mm(7.0);
+    for item in &output.dilution_steps {
+        ensure_space(doc, layer, y, 20.0);
+        layer.use_text(item.step.to_string(), 12.0, Mm(20.0), *y, font);
+        layer.use_text(
+            format!("{}", item.source_concentration),
+            12.0,
+            Mm(40.0),
+            *y,
+            font,
+        );
+        layer.use_text(
+            format!("{}", item.target_concentration),
+            12.0,
+            Mm(75.0),
+            *y,
+            font,
+        );
+        layer.use_text(format!("{:.4}", item.aliquot_ml), 12.0, Mm(110.0), *y, font);
+        layer.use_text(format!("{:.4}", item.diluent_ml), 12.0, Mm(150.0), *y, font);
+        *y -= Mm(7.0);
+    }
+}
+
 #[tauri::command]
 pub fn export_to_pdf(output: CalculationOutput) -> Result<(), String> {
-    let detailed_report = read_settings()
-        .map(|settings| settings.detailed_report)
-        .unwrap_or(false);
+    let settings = read_settings()?;
+    let detailed_report = settings.detailed_report;
+    let precision = settings.precision();
     let path = pick_save_path(
         "Save PDF Report",
         "PDF Document",
@@ -61,51 +490,66 @@ pub fn export_to_pdf(output: CalculationOutput) -> Result<(), String> {
     }
 
     let mut y = Mm(245.0);
-    let x_reagent = Mm(20.0);
-    let x_moles = Mm(90.0);
-    let x_mass = Mm(140.0);
-    let x_right = Mm(190.0);
-    layer.use_text("Reagent", 14.0, x_reagent, y, &font);
-    layer.use_text("Moles", 14.0, x_moles, y, &font);
-    layer.use_text("Mass (g)", 14.0, x_mass, y, &font);
-    y -= Mm(6.0);
-    let line = Line {
-        points: vec![
-            (Point::new(x_reagent, y), false),
-            (Point::new(x_right, y), false),
-        ],
-        is_closed: false,
-        has_fill: false,
-        has_stroke: true,
-        is_clipping_path: false,
-    };
-    layer.add_shape(line);
-    y -= Mm(8.0);
-
-    for item in &output.reagents {
-        if y.0 < 20.0 {
-            let (new_page, new_layer) = doc.add_page(Mm(210.0), Mm(297.0), "Layer 1");
-            layer = doc.get_page(new_page).get_layer(new_layer);
-            y = Mm(280.0);
+    if !output.reagents.is_empty() {
+        let x_reagent = Mm(20.0);
+        let x_moles = Mm(90.0);
+        let x_mass = Mm(140.0);
+        let x_volume = Mm(170.0);
+        let has_volume = output.reagents.iter().any(|r| r.volume_ml.is_some());
+        layer.use_text("Reagent", 14.0, x_reagent, y, &font);
+        layer.use_text("Moles", 14.0, x_moles, y, &font);
+        layer.use_text(
+            format!("Mass ({})", precision.mass_unit.symbol()),
+            14.0,
+            x_mass,
+            y,
+            &font,
+        );
+        if has_volume {
+            layer.use_text("Vol. (mL)", 14.0, x_volume, y, &font);
         }
-        layer.use_text(&item.reagent, 12.0, x_reagent, y, &font);
-        layer.use_text(format!("{:.10}", item.moles), 12.0, x_moles, y, &font);
-        layer.use_text(format!("{:.6}", item.mass), 12.0, x_mass, y, &font);
+        y -= Mm(6.0);
+        draw_rule(&layer, y);
         y -= Mm(8.0);
+
+        for item in &output.reagents {
+            ensure_space(&doc, &mut layer, &mut y, 20.0);
+            layer.use_text(&item.reagent, 12.0, x_reagent, y, &font);
+            layer.use_text(
+                precision.format_significant(item.moles),
+                12.0,
+                x_moles,
+                y,
+                &font,
+            );
+            layer.use_text(precision.format_mass(item.mass), 12.0, x_mass, y, &font);
+            if let Some(volume) = item.volume_ml {
+                layer.use_text(format!("{:.4}", volume), 12.0, x_volume, y, &font);
+            }
+            y -= Mm(8.0);
+        }
+        y -= Mm(10.0);
     }
 
-    y -= Mm(10.0);
-    layer.use_text(
-        format!("Target molar mass: {:.6} g/mol", output.molar_mass),
-        12.0,
-        Mm(20.0),
-        y,
-        &font,
-    );
-    if detailed_report {
+    if output.molar_mass > 0.0 {
+        layer.use_text(
+            format!(
+                "Target molar mass: {} g/mol",
+                precision.format_significant(output.molar_mass)
+            ),
+            12.0,
+            Mm(20.0),
+            y,
+            &font,
+        );
+    }
+    if detailed_report && !output.reagents.is_empty() {
         y -= Mm(8.0);
         layer.use_text(
-            format!("Target moles: {:.6} mol", output.target_moles),
+            format!(
+                "Target moles: {} mol",
+                precision.format_significant(output.target_moles)
+            ),
             12.0,
             Mm(20.0),
             y,
@@ -114,34 +558,35 @@ pub fn export_to_pdf(output: CalculationOutput) -> Result<(), String> {
         y -= Mm(8.0);
         layer.use_text(
             format!(
-                "Mass check: total {:.6} g, target {:.6} g, delta {:.6} g",
-                output.mass_check.total_reagent_mass,
-                output.mass_check.target_mass,
-                output.mass_check.delta
+                "Mass check: total {}, target {}, delta {}",
+                precision.display_mass(output.mass_check.total_reagent_mass),
+                precision.display_mass(output.mass_check.target_mass),
+                precision.display_mass(output.mass_check.delta)
             ),
             12.0,
             Mm(20.0),
             y,
             &font,
         );
+    }
 
+    write_equation(&doc, &mut layer, &mut y, &font, &output);
+    write_phases(&doc, &mut layer, &mut y, &font, &output, &precision);
+    write_cost(&doc, &mut layer, &mut y, &font, &output, &precision);
+    write_batch_advice(&doc, &mut layer, &mut y, &font, &output, &precision);
+    write_uncertainty(&doc, &mut layer, &mut y, &font, &output, &precision);
+    write_safety(&doc, &mut layer, &mut y, &font, &output);
+    write_composition(&doc, &mut layer, &mut y, &font, &output);
+    write_dilution(&doc, &mut layer, &mut y, &font, &output);
+    if detailed_report {
         y -= Mm(14.0);
-        if y.0 < 30.0 {
-            let (new_page, new_layer) = doc.add_page(Mm(210.0), Mm(297.0), "Layer 1");
-            layer = doc.get_page(new_page).get_layer(new_layer);
-            y = Mm(280.0);
-        }
+        ensure_space(&doc, &mut layer, &mut y, 30.0);
         layer.use_text("Explanation:", 12.0, Mm(20.0), y, &font);
         y -= Mm(8.0);
         let max_chars = 90;
         for line in &output.explanation {
             for wrapped in wrap_text(line, max_chars) {
-                if y.0 < 20.0 {
-                    let (new_page, new_layer) =
-                        doc.add_page(Mm(210.0), Mm(297.0), "Layer 1");
-                    layer = doc.get_page(new_page).get_layer(new_layer);
-                    y = Mm(280.0);
-                }
+                ensure_space(&doc, &mut layer, &mut y, 20.0);
                 layer.use_text(wrapped, 10.0, Mm(20.0), y, &font);
                 y -= Mm(6.0);
             }
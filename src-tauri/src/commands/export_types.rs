@@ -6,21 +6,132 @@ pub struct ReagentResult {
     pub moles: f64,
     pub molar_mass: f64,
     pub mass: f64,
+    #[serde(default)]
+    pub solution_mass: Option<f64>,
+    #[serde(default)]
+    pub volume_ml: Option<f64>,
+    #[serde(default)]
+    pub purity: Option<f64>,
+    #[serde(default)]
+    pub cost: Option<f64>,
+    #[serde(default)]
+    pub warnings: Vec<String>,
+    #[serde(default)]
+    pub mass_uncertainty: Option<f64>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Default, Serialize, Deserialize)]
 pub struct MassCheck {
     pub target_mass: f64,
     pub total_reagent_mass: f64,
     pub delta: f64,
 }
 
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ElementComposition {
+    pub element: String,
+    pub coefficient: f64,
+    pub atomic_mass: f64,
+    pub mass_contribution: f64,
+    pub mass_percent: f64,
+    pub atomic_percent: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct OxideComposition {
+    pub oxide: String,
+    pub cation: String,
+    pub moles_per_formula: f64,
+    pub mass_percent: f64,
+    pub normalized_percent: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DilutionStep {
+    pub step: u32,
+    pub source_concentration: f64,
+    pub target_concentration: f64,
+    pub aliquot_ml: f64,
+    pub diluent_ml: f64,
+    pub final_volume_ml: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PhaseResult {
+    pub formula: String,
+    pub mole_fraction: f64,
+    pub weight_fraction: f64,
+    pub molar_mass: f64,
+    pub moles: f64,
+    pub mass: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CompositionUncertainty {
+    pub element: String,
+    pub coefficient: f64,
+    pub uncertainty: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MasterBatch {
+    pub dopant: String,
+    pub host: String,
+    pub dilution: f64,
+    pub batch_dopant_mass: f64,
+    pub batch_host_mass: f64,
+    pub aliquot_mass: f64,
+    pub host_mass: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BatchAdvice {
+    pub min_weighable: f64,
+    pub small_reagents: Vec<String>,
+    pub minimum_target_mass: f64,
+    #[serde(default)]
+    pub master_batches: Vec<MasterBatch>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MatrixDiagnostics {
+    pub rows: usize,
+    pub columns: usize,
+    pub rank: usize,
+    #[serde(default)]
+    pub condition_number: Option<f64>,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct CalculationOutput {
     pub target_formula: String,
     pub molar_mass: f64,
+    #[serde(default)]
     pub target_moles: f64,
+    #[serde(default)]
+    pub composition: Vec<ElementComposition>,
+    #[serde(default)]
+    pub oxide_composition: Vec<OxideComposition>,
+    #[serde(default)]
     pub reagents: Vec<ReagentResult>,
+    #[serde(default)]
     pub mass_check: MassCheck,
+    #[serde(default)]
+    pub dilution_steps: Vec<DilutionStep>,
+    #[serde(default)]
+    pub phases: Vec<PhaseResult>,
+    #[serde(default)]
+    pub total_cost: Option<f64>,
+    #[serde(default)]
+    pub currency: Option<String>,
+    #[serde(default)]
+    pub composition_uncertainty: Vec<CompositionUncertainty>,
+    #[serde(default)]
+    pub batch_advice: Option<BatchAdvice>,
+    #[serde(default)]
+    pub equation: Option<String>,
+    #[serde(default)]
+    pub matrix: Option<MatrixDiagnostics>,
+    #[serde(default)]
     pub explanation: Vec<String>,
 }
@@ -1,5 +1,6 @@
 use directories::ProjectDirs;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::fs;
 use std::path::PathBuf;
 use tauri_plugin_http::reqwest::header::{HeaderMap, HeaderValue};
@@ -88,6 +89,30 @@ pub async fn get_elements() -> Result<Vec<Element>, String> {
     read_elements_from_path(&path)
 }
 
+pub async fn get_atomic_masses() -> Result<HashMap<String, f64>, String> {
+    let elements = get_elements().await?;
+    Ok(elements
+        .into_iter()
+        .map(|el| (el.symbol, el.atomic_mass))
+        .collect())
+}
+
+/// Fingerprint of the element table in use, so stored results can tell
+/// which atomic masses they were computed with.
+pub async fn element_table_version() -> Result<String, String> {
+    let mut masses: Vec<(String, f64)> = get_atomic_masses().await?.into_iter().collect();
+    masses.sort_by(|a, b| a.0.cmp(&b.0));
+    // FNV-1a, stable across builds unlike the std hasher.
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for (symbol, mass) in &masses {
+        for byte in format!("{}={};", symbol, mass).bytes() {
+            hash ^= byte as u64;
+            hash = hash.wrapping_mul(0x100000001b3);
+        }
+    }
+    Ok(format!("{:016x}", hash))
+}
+
 #[tauri::command]
 pub fn save_elements(elements: Vec<Element>) -> Result<Vec<Element>, String> {
     let path = get_json_path();
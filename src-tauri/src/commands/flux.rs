@@ -0,0 +1,206 @@
+use serde::{Deserialize, Serialize};
+
+use crate::chem::mass::{collapse_formula, molar_mass};
+use crate::chem::parse::{format_formula, ordered_unique_elements, parse_formula};
+use crate::commands::calc_helpers::format_value;
+use crate::commands::calculate::{
+    merge_reagent_results, run_calculation, CalculationInput, CalculationOutput, ReagentResult,
+};
+use crate::commands::fetch_elements::get_atomic_masses;
+use crate::commands::settings::read_settings;
+
+#[derive(Deserialize)]
+pub struct FluxComponent {
+    pub formula: String,
+    pub fraction: f64,
+}
+
+#[derive(Deserialize)]
+pub struct FluxInput {
+    pub target_formula: String,
+    pub starting_materials: Vec<String>,
+    pub flux_components: Vec<FluxComponent>,
+    #[serde(default)]
+    pub flux_materials: Vec<String>,
+    #[serde(default = "default_basis")]
+    pub flux_basis: String,
+    #[serde(default = "default_basis")]
+    pub ratio_basis: String,
+    pub solute_ratio: f64,
+    pub flux_ratio: f64,
+    pub total_mass: f64,
+    #[serde(default)]
+    pub volatile_elements: Vec<String>,
+}
+
+#[derive(Serialize)]
+pub struct FluxOutput {
+    pub target_formula: String,
+    pub molar_mass: f64,
+    pub target_moles: f64,
+    pub target_mass: f64,
+    pub flux_formula: String,
+    pub flux_molar_mass: f64,
+    pub flux_moles: f64,
+    pub flux_mass: f64,
+    pub solute: CalculationOutput,
+    pub flux: CalculationOutput,
+    pub reagents: Vec<ReagentResult>,
+    pub explanation: Vec<String>,
+}
+
+fn default_basis() -> String {
+    "mol".to_string()
+}
+
+fn is_molar(basis: &str) -> Result<bool, String> {
+    match basis {
+        "mol" => Ok(true),
+        "wt" => Ok(false),
+        other => Err(format!("Unknown ratio basis: {}", other)),
+    }
+}
+
+#[tauri::command]
+pub async fn flux_charge(input: FluxInput) -> Result<FluxOutput, String> {
+    if input.total_mass <= 0.0 {
+        return Err("Total mass must be positive".to_string());
+    }
+    if input.solute_ratio <= 0.0 || input.flux_ratio <= 0.0 {
+        return Err("Solute and flux ratios must be positive".to_string());
+    }
+    let flux_molar = is_molar(&input.flux_basis)?;
+    let ratio_molar = is_molar(&input.ratio_basis)?;
+
+    let masses = get_atomic_masses().await?;
+    let precision = read_settings()?.precision();
+    let target = input.target_formula.trim();
+    let target_molar_mass = molar_mass(&collapse_formula(&parse_formula(target)?), &masses)?;
+
+    // One flux "formula unit" is the molar mixture of its components.
+    let mut components = Vec::new();
+    for item in &input.flux_components {
+        let formula = item.formula.trim();
+        if formula.is_empty() {
+            continue;
+        }
+        if item.fraction <= 0.0 {
+            return Err(format!("Fraction for {} must be positive", formula));
+        }
+        let parsed = parse_formula(formula)?;
+        let component_mass = molar_mass(&collapse_formula(&parsed), &masses)?;
+        let moles = if flux_molar {
+            item.fraction
+        } else {
+            item.fraction / component_mass
+        };
+        components.push((formula.to_string(), parsed, moles));
+    }
+    if components.is_empty() {
+        return Err("No flux components provided".to_string());
+    }
+    let mole_total: f64 = components.iter().map(|(_, _, n)| n).sum();
+    let mut flux_parsed = Vec::new();
+    for (_, parsed, n) in &components {
+        for (el, coeff) in parsed {
+            flux_parsed.push((el.clone(), coeff * n / mole_total));
+        }
+    }
+    let flux_composition = collapse_formula(&flux_parsed);
+    let flux_unit: Vec<(String, f64)> = ordered_unique_elements(&flux_parsed)
+        .into_iter()
+        .map(|el| {
+            let coeff = flux_composition[&el];
+            (el, coeff)
+        })
+        .collect();
+    // The formula string is rounded for display; compute from the composition.
+    let flux_formula = format_formula(&flux_unit);
+    let flux_molar_mass = molar_mass(&flux_composition, &masses)?;
+
+    let (target_mass, flux_mass) = if ratio_molar {
+        let target_moles = input.total_mass * input.solute_ratio
+            / (input.solute_ratio * target_molar_mass + input.flux_ratio * flux_molar_mass);
+        let target_mass = target_moles * target_molar_mass;
+        (target_mass, input.total_mass - target_mass)
+    } else {
+        let target_mass =
+            input.total_mass * input.solute_ratio / (input.solute_ratio + input.flux_ratio);
+        (target_mass, input.total_mass - target_mass)
+    };
+
+    let mut explanation = Vec::new();
+    explanation.push(format!(
+        "Flux: {} ({} fractions) -> {} per formula unit, {} g/mol",
+        components
+            .iter()
+            .map(|(f, _, n)| format!("{} {}", f, format_value(n / mole_total)))
+            .collect::<Vec<_>>()
+            .join(" + "),
+        input.flux_basis,
+        flux_formula,
+        format_value(flux_molar_mass)
+    ));
+    explanation.push(format!(
+        "Solute:flux = {}:{} ({}) for {} g total -> {} {} g, flux {} g",
+        input.solute_ratio,
+        input.flux_ratio,
+        input.ratio_basis,
+        input.total_mass,
+        target,
+        format_value(target_mass),
+        format_value(flux_mass)
+    ));
+
+    let flux_materials = if input.flux_materials.iter().all(|m| m.trim().is_empty()) {
+        components.iter().map(|(f, _, _)| f.clone()).collect()
+    } else {
+        input.flux_materials.clone()
+    };
+
+    let solute = run_calculation(CalculationInput {
+        target_formula: target.to_string(),
+        target_mass: serde_json::json!(target_mass),
+        starting_materials: input.starting_materials.clone(),
+        volatile_elements: input.volatile_elements.clone(),
+        ..Default::default()
+    })
+    .await
+    .map_err(|e| format!("Solute: {}", e))?;
+    let flux = run_calculation(CalculationInput {
+        target_formula: flux_formula.clone(),
+        target_composition: Some(flux_unit),
+        target_mass: serde_json::json!(flux_mass),
+        starting_materials: flux_materials,
+        volatile_elements: input.volatile_elements.clone(),
+        ..Default::default()
+    })
+    .await
+    .map_err(|e| format!("Flux: {}", e))?;
+
+    let reagents = merge_reagent_results(&[&solute.reagents, &flux.reagents]);
+    explanation.push(format!(
+        "Combined charge ({}): {}",
+        precision.mass_unit.symbol(),
+        reagents
+            .iter()
+            .map(|r| format!("{}={}", r.reagent, precision.format_mass(r.mass)))
+            .collect::<Vec<_>>()
+            .join(", ")
+    ));
+
+    Ok(FluxOutput {
+        target_formula: target.to_string(),
+        molar_mass: target_molar_mass,
+        target_moles: target_mass / target_molar_mass,
+        target_mass,
+        flux_formula,
+        flux_molar_mass,
+        flux_moles: flux_mass / flux_molar_mass,
+        flux_mass,
+        solute,
+        flux,
+        reagents,
+        explanation,
+    })
+}
@@ -0,0 +1,291 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::chem::balance::{solve_element_balance, Reagent};
+use crate::chem::mass::{collapse_formula, molar_mass};
+use crate::chem::parse::parse_formula;
+use crate::commands::calc_helpers::format_value;
+use crate::commands::calculate::MassCheck;
+use crate::commands::composition::oxide_cation;
+use crate::commands::fetch_elements::get_atomic_masses;
+use crate::commands::settings::read_settings;
+
+/// Elements that leave the batch on melting (CO2, H2O, NOx) or are exchanged
+/// with the furnace atmosphere, so they are not balanced against the glass.
+const VOLATILE_ELEMENTS: [&str; 4] = ["C", "H", "N", "O"];
+
+#[derive(Deserialize)]
+pub struct OxideFraction {
+    pub oxide: String,
+    pub fraction: f64,
+}
+
+#[derive(Deserialize)]
+pub struct GlassBatchInput {
+    pub components: Vec<OxideFraction>,
+    #[serde(default = "default_basis")]
+    pub basis: String,
+    pub raw_materials: Vec<String>,
+    #[serde(default = "default_glass_mass")]
+    pub glass_mass: f64,
+}
+
+#[derive(Serialize)]
+pub struct GlassComponent {
+    pub oxide: String,
+    pub mol_percent: f64,
+    pub weight_percent: f64,
+    pub moles: f64,
+    pub mass: f64,
+}
+
+#[derive(Serialize)]
+pub struct BatchReagent {
+    pub reagent: String,
+    pub moles: f64,
+    pub molar_mass: f64,
+    pub mass: f64,
+    pub oxide_yield: f64,
+    pub loss_on_ignition: f64,
+}
+
+#[derive(Serialize)]
+pub struct GlassBatchOutput {
+    pub glass_mass: f64,
+    pub components: Vec<GlassComponent>,
+    pub reagents: Vec<BatchReagent>,
+    pub mass_check: MassCheck,
+    pub explanation: Vec<String>,
+}
+
+struct OxideSpec {
+    name: String,
+    cation: String,
+    cation_coeff: f64,
+    molar_mass: f64,
+    fraction: f64,
+}
+
+fn default_basis() -> String {
+    "wt".to_string()
+}
+
+fn default_glass_mass() -> f64 {
+    100.0
+}
+
+#[tauri::command]
+pub async fn glass_batch(input: GlassBatchInput) -> Result<GlassBatchOutput, String> {
+    let by_weight = match input.basis.as_str() {
+        "wt" => true,
+        "mol" => false,
+        other => return Err(format!("Unknown composition basis: {}", other)),
+    };
+    if input.glass_mass <= 0.0 {
+        return Err("Glass mass must be positive".to_string());
+    }
+
+    let masses = get_atomic_masses().await?;
+    let precision = read_settings()?.precision();
+
+    let mut oxides: Vec<OxideSpec> = Vec::new();
+    for item in &input.components {
+        let name = item.oxide.trim();
+        if name.is_empty() {
+            continue;
+        }
+        if item.fraction <= 0.0 {
+            return Err(format!("Fraction for {} must be positive", name));
+        }
+        let composition = collapse_formula(&parse_formula(name)?);
+        let cation = oxide_cation(name, &composition)?;
+        if oxides.iter().any(|o| o.cation == cation) {
+            return Err(format!("More than one oxide provides {}", cation));
+        }
+        oxides.push(OxideSpec {
+            name: name.to_string(),
+            cation_coeff: composition[&cation],
+            cation,
+            molar_mass: molar_mass(&composition, &masses)?,
+            fraction: item.fraction,
+        });
+    }
+    if oxides.is_empty() {
+        return Err("No glass components provided".to_string());
+    }
+
+    let fraction_total: f64 = oxides.iter().map(|o| o.fraction).sum();
+    let oxide_moles: Vec<f64> = if by_weight {
+        oxides
+            .iter()
+            .map(|o| o.fraction / fraction_total * input.glass_mass / o.molar_mass)
+            .collect()
+    } else {
+        let mean_molar_mass: f64 = oxides
+            .iter()
+            .map(|o| o.fraction / fraction_total * o.molar_mass)
+            .sum();
+        oxides
+            .iter()
+            .map(|o| o.fraction / fraction_total * input.glass_mass / mean_molar_mass)
+            .collect()
+    };
+    let total_oxide_moles: f64 = oxide_moles.iter().sum();
+
+    let components: Vec<GlassComponent> = oxides
+        .iter()
+        .zip(oxide_moles.iter())
+        .map(|(o, n)| GlassComponent {
+            oxide: o.name.clone(),
+            mol_percent: n / total_oxide_moles * 100.0,
+            weight_percent: n * o.molar_mass / input.glass_mass * 100.0,
+            moles: *n,
+            mass: n * o.molar_mass,
+        })
+        .collect();
+
+    let order: Vec<String> = oxides.iter().map(|o| o.cation.clone()).collect();
+    let mut required: HashMap<String, f64> = oxides
+        .iter()
+        .zip(oxide_moles.iter())
+        .map(|(o, n)| (o.cation.clone(), n * o.cation_coeff))
+        .collect();
+    let target_required = required.clone();
+
+    let mut reagents = Vec::new();
+    for name in input.raw_materials.iter() {
+        let trimmed = name.trim();
+        if trimmed.is_empty() {
+            continue;
+        }
+        let composition = collapse_formula(&parse_formula(trimmed)?);
+        for el in composition.keys() {
+            if !order.contains(el) && !VOLATILE_ELEMENTS.contains(&el.as_str()) {
+                return Err(format!(
+                    "Raw material {} introduces element not in glass: {}",
+                    trimmed, el
+                ));
+            }
+        }
+        reagents.push(Reagent {
+            name: trimmed.to_string(),
+            molar_mass: molar_mass(&composition, &masses)?,
+            composition,
+        });
+    }
+    if reagents.is_empty() {
+        return Err("No raw materials provided".to_string());
+    }
+
+    let mut explanation = Vec::new();
+    explanation.push(format!(
+        "Glass composition per {} g: {}",
+        input.glass_mass,
+        components
+            .iter()
+            .map(|c| format!(
+                "{} {:.4} wt% / {:.4} mol% ({} mol)",
+                c.oxide,
+                c.weight_percent,
+                c.mol_percent,
+                format_value(c.moles)
+            ))
+            .collect::<Vec<_>>()
+            .join(", ")
+    ));
+    explanation.push(format!(
+        "Cation requirements (mol): {}",
+        order
+            .iter()
+            .map(|el| format!("{}={}", el, format_value(target_required[el])))
+            .collect::<Vec<_>>()
+            .join(", ")
+    ));
+
+    let amounts = solve_element_balance(&reagents, &order, &mut required, &mut explanation)?;
+
+    for el in &order {
+        let actual: f64 = reagents
+            .iter()
+            .zip(amounts.iter())
+            .map(|(r, n)| n * r.composition.get(el).copied().unwrap_or(0.0))
+            .sum();
+        if (actual - target_required[el]).abs() > 1e-6 {
+            return Err(format!(
+                "Element balance mismatch for {} (required {}, got {})",
+                el,
+                format_value(target_required[el]),
+                format_value(actual)
+            ));
+        }
+    }
+
+    let mut batch = Vec::new();
+    let mut total_mass = 0.0;
+    for (reagent, moles) in reagents.iter().zip(amounts.iter()) {
+        let moles = if moles.abs() < 1e-10 { 0.0 } else { *moles };
+        if moles < 0.0 {
+            return Err(format!("Negative amount for {}", reagent.name));
+        }
+        let mass = moles * reagent.molar_mass;
+        let oxide_yield: f64 = oxides
+            .iter()
+            .filter_map(|o| {
+                reagent
+                    .composition
+                    .get(&o.cation)
+                    .map(|coeff| moles * coeff / o.cation_coeff * o.molar_mass)
+            })
+            .sum();
+        let loss_on_ignition = if mass > 0.0 {
+            (mass - oxide_yield) / mass * 100.0
+        } else {
+            0.0
+        };
+        total_mass += mass;
+        batch.push(BatchReagent {
+            reagent: reagent.name.clone(),
+            moles,
+            molar_mass: reagent.molar_mass,
+            mass,
+            oxide_yield,
+            loss_on_ignition,
+        });
+    }
+
+    explanation.push(format!(
+        "Batch masses ({}): {}",
+        precision.mass_unit.symbol(),
+        batch
+            .iter()
+            .map(|r| format!(
+                "{}={} (oxide yield {}, LOI {:.2}%)",
+                r.reagent,
+                precision.format_mass(r.mass),
+                precision.format_mass(r.oxide_yield),
+                r.loss_on_ignition
+            ))
+            .collect::<Vec<_>>()
+            .join(", ")
+    ));
+
+    let mass_check = MassCheck {
+        target_mass: input.glass_mass,
+        total_reagent_mass: total_mass,
+        delta: total_mass - input.glass_mass,
+    };
+    explanation.push(format!(
+        "Mass check: batch {} yields {} glass (loss on ignition {})",
+        precision.display_mass(total_mass),
+        precision.display_mass(input.glass_mass),
+        precision.display_mass(mass_check.delta)
+    ));
+
+    Ok(GlassBatchOutput {
+        glass_mass: input.glass_mass,
+        components,
+        reagents: batch,
+        mass_check,
+        explanation,
+    })
+}
@@ -0,0 +1,130 @@
+use directories::ProjectDirs;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+
+use crate::chem::mass::collapse_formula;
+use crate::chem::parse::parse_formula;
+
+const BUNDLED_HAZARDS: &str = include_str!("../../hazards.json");
+
+/// One row of the hazard table. Rows keyed by `element` apply to every
+/// compound containing it; rows keyed by `formula` or `cas` to that
+/// compound only.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct HazardEntry {
+    pub label: String,
+    #[serde(default)]
+    pub formula: Option<String>,
+    #[serde(default)]
+    pub cas: Option<String>,
+    #[serde(default)]
+    pub element: Option<String>,
+    #[serde(default)]
+    pub pictograms: Vec<String>,
+    #[serde(default)]
+    pub h_statements: Vec<String>,
+    #[serde(default)]
+    pub p_statements: Vec<String>,
+    #[serde(default)]
+    pub air_sensitive: bool,
+    #[serde(default)]
+    pub moisture_sensitive: bool,
+    #[serde(default)]
+    pub notes: Option<String>,
+}
+
+fn get_hazards_path() -> PathBuf {
+    let proj_dirs =
+        ProjectDirs::from("com", "chooinet", "MassCalc").expect("Cannot get project directories");
+    let data_dir = proj_dirs.data_local_dir();
+    fs::create_dir_all(data_dir).expect("Cannot create data directory");
+    data_dir.join("hazards.json")
+}
+
+fn bundled_hazards() -> Result<Vec<HazardEntry>, String> {
+    serde_json::from_str(BUNDLED_HAZARDS).map_err(|e| e.to_string())
+}
+
+pub fn read_hazards() -> Result<Vec<HazardEntry>, String> {
+    let path = get_hazards_path();
+    if !path.exists() {
+        return bundled_hazards();
+    }
+    let raw = fs::read_to_string(path).map_err(|e| e.to_string())?;
+    serde_json::from_str(&raw).map_err(|e| e.to_string())
+}
+
+fn describe(entry: &HazardEntry) -> String {
+    let mut parts = vec![entry.label.clone()];
+    if !entry.pictograms.is_empty() {
+        parts.push(entry.pictograms.join(", "));
+    }
+    if !entry.h_statements.is_empty() {
+        parts.push(entry.h_statements.join(", "));
+    }
+    if !entry.p_statements.is_empty() {
+        parts.push(entry.p_statements.join(", "));
+    }
+    if entry.air_sensitive {
+        parts.push("air-sensitive".to_string());
+    }
+    if entry.moisture_sensitive {
+        parts.push("moisture-sensitive".to_string());
+    }
+    if let Some(notes) = entry.notes.as_deref().filter(|n| !n.trim().is_empty()) {
+        parts.push(notes.trim().to_string());
+    }
+    parts.join("; ")
+}
+
+/// Warning lines for a reagent: rows matching its formula or CAS number
+/// first, then element rows for each element it contains.
+pub fn hazard_warnings(hazards: &[HazardEntry], formula: &str, cas: Option<&str>) -> Vec<String> {
+    let formula = formula.trim();
+    let mut warnings: Vec<String> = hazards
+        .iter()
+        .filter(|h| {
+            h.formula.as_deref().is_some_and(|f| f.trim() == formula)
+                || cas.is_some_and(|cas| h.cas.as_deref().is_some_and(|c| c.trim() == cas.trim()))
+        })
+        .map(describe)
+        .collect();
+    let Ok(parsed) = parse_formula(formula) else {
+        return warnings;
+    };
+    let composition = collapse_formula(&parsed);
+    for entry in hazards {
+        if entry
+            .element
+            .as_deref()
+            .is_some_and(|el| composition.contains_key(el.trim()))
+        {
+            warnings.push(describe(entry));
+        }
+    }
+    warnings.dedup();
+    warnings
+}
+
+#[tauri::command]
+pub fn get_hazards() -> Result<Vec<HazardEntry>, String> {
+    read_hazards()
+}
+
+#[tauri::command]
+pub fn save_hazards(hazards: Vec<HazardEntry>) -> Result<Vec<HazardEntry>, String> {
+    let data = serde_json::to_string_pretty(&hazards).map_err(|e| e.to_string())?;
+    fs::write(get_hazards_path(), data).map_err(|e| e.to_string())?;
+    Ok(hazards)
+}
+
+/// Replaces the local table with the one shipped with the app.
+#[tauri::command]
+pub fn restore_hazards() -> Result<Vec<HazardEntry>, String> {
+    let path = get_hazards_path();
+    if path.exists() {
+        fs::remove_file(&path).map_err(|e| e.to_string())?;
+    }
+    bundled_hazards()
+}
@@ -0,0 +1,625 @@
+use chrono::{Local, NaiveDate};
+use directories::ProjectDirs;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+
+use crate::commands::calc_helpers::format_value;
+use crate::commands::export_types::CalculationOutput;
+use crate::commands::fetch_elements::element_table_version;
+use crate::commands::settings::read_settings;
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct HistoryEntry {
+    pub id: u32,
+    pub timestamp: String,
+    pub target_formula: String,
+    /// Fingerprint of the element table the result was computed with.
+    pub element_table: String,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// Calculation input exactly as submitted, for re-opening the form.
+    pub input: Value,
+    pub output: Value,
+}
+
+/// One line of the history file. Entries are never rewritten; tag changes
+/// and deletions are appended and applied when the file is read back.
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+enum HistoryRecord {
+    Entry(HistoryEntry),
+    Tags { id: u32, tags: Vec<String> },
+    Deleted { id: u32 },
+}
+
+#[derive(Serialize)]
+pub struct HistorySummary {
+    pub id: u32,
+    pub timestamp: String,
+    pub target_formula: String,
+    pub target_mass: Value,
+    pub element_table: String,
+    pub tags: Vec<String>,
+}
+
+#[derive(Serialize)]
+pub struct HistoryList {
+    pub entries: Vec<HistorySummary>,
+    /// Lines of the history file that could not be parsed and were skipped.
+    pub skipped_lines: usize,
+}
+
+#[derive(Deserialize, Default)]
+pub struct HistoryQuery {
+    /// Case-insensitive substring of the target formula.
+    #[serde(default)]
+    pub formula: Option<String>,
+    /// Inclusive date range, `YYYY-MM-DD`.
+    #[serde(default)]
+    pub from: Option<String>,
+    #[serde(default)]
+    pub to: Option<String>,
+    #[serde(default)]
+    pub tag: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct FieldChange {
+    pub path: String,
+    pub first: Option<Value>,
+    pub second: Option<Value>,
+}
+
+#[derive(Serialize)]
+pub struct ReagentChange {
+    pub reagent: String,
+    pub first_mass: Option<f64>,
+    pub second_mass: Option<f64>,
+    pub delta: Option<f64>,
+}
+
+#[derive(Serialize)]
+pub struct HistoryDiff {
+    pub first: u32,
+    pub second: u32,
+    pub element_table_changed: bool,
+    pub input_changes: Vec<FieldChange>,
+    pub reagent_changes: Vec<ReagentChange>,
+    pub explanation: Vec<String>,
+}
+
+fn get_history_path() -> PathBuf {
+    let proj_dirs =
+        ProjectDirs::from("com", "chooinet", "MassCalc").expect("Cannot get project directories");
+    let data_dir = proj_dirs.data_local_dir();
+    fs::create_dir_all(data_dir).expect("Cannot create data directory");
+    data_dir.join("history.jsonl")
+}
+
+/// Records of the history file and the number of malformed lines skipped,
+/// so one damaged line does not hide the rest of the history.
+fn read_records() -> Result<(Vec<HistoryRecord>, usize), String> {
+    let path = get_history_path();
+    if !path.exists() {
+        return Ok((Vec::new(), 0));
+    }
+    let raw = fs::read_to_string(path).map_err(|e| e.to_string())?;
+    Ok(parse_records(&raw))
+}
+
+fn parse_records(raw: &str) -> (Vec<HistoryRecord>, usize) {
+    let mut records = Vec::new();
+    let mut skipped = 0;
+    for line in raw.lines().filter(|line| !line.trim().is_empty()) {
+        match serde_json::from_str(line) {
+            Ok(record) => records.push(record),
+            Err(_) => skipped += 1,
+        }
+    }
+    (records, skipped)
+}
+
+/// Id for the next entry. An entry's id never exceeds the number of entry
+/// lines up to and including it, so counting malformed lines as entries
+/// keeps the id of a damaged (e.g. truncated) entry from being reused.
+fn next_id(records: &[HistoryRecord], skipped: usize) -> u32 {
+    let ids: Vec<u32> = records
+        .iter()
+        .filter_map(|r| match r {
+            HistoryRecord::Entry(entry) => Some(entry.id),
+            _ => None,
+        })
+        .collect();
+    let max_id = ids.iter().copied().max().unwrap_or(0);
+    max_id.max((ids.len() + skipped) as u32) + 1
+}
+
+fn append_record(record: &HistoryRecord) -> Result<(), String> {
+    let line = serde_json::to_string(record).map_err(|e| e.to_string())?;
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(get_history_path())
+        .map_err(|e| e.to_string())?;
+    writeln!(file, "{}", line).map_err(|e| e.to_string())
+}
+
+/// Live entries, oldest first, with tag changes and deletions applied, and
+/// the number of malformed lines skipped.
+fn read_history() -> Result<(Vec<HistoryEntry>, usize), String> {
+    let (records, skipped) = read_records()?;
+    Ok((replay(records), skipped))
+}
+
+fn replay(records: Vec<HistoryRecord>) -> Vec<HistoryEntry> {
+    let mut entries: Vec<HistoryEntry> = Vec::new();
+    for record in records {
+        match record {
+            HistoryRecord::Entry(entry) => entries.push(entry),
+            HistoryRecord::Tags { id, tags } => {
+                if let Some(entry) = entries.iter_mut().find(|e| e.id == id) {
+                    entry.tags = tags;
+                }
+            }
+            HistoryRecord::Deleted { id } => entries.retain(|e| e.id != id),
+        }
+    }
+    entries
+}
+
+fn find_entry(id: u32) -> Result<HistoryEntry, String> {
+    read_history()?
+        .0
+        .into_iter()
+        .find(|e| e.id == id)
+        .ok_or_else(|| format!("Unknown history entry: {}", id))
+}
+
+fn summary(entry: &HistoryEntry) -> HistorySummary {
+    HistorySummary {
+        id: entry.id,
+        timestamp: entry.timestamp.clone(),
+        target_formula: entry.target_formula.clone(),
+        target_mass: entry
+            .input
+            .get("target_mass")
+            .cloned()
+            .unwrap_or(Value::Null),
+        element_table: entry.element_table.clone(),
+        tags: entry.tags.clone(),
+    }
+}
+
+fn history_list(entries: &[HistoryEntry], skipped_lines: usize) -> HistoryList {
+    HistoryList {
+        entries: entries.iter().rev().map(summary).collect(),
+        skipped_lines,
+    }
+}
+
+/// Appends a finished calculation to the history and returns its id.
+/// `target_formula` is the one the output reports, which for phase mixtures
+/// is composed from the phases rather than typed in.
+pub async fn record_calculation(
+    input: Value,
+    target_formula: &str,
+    output: &impl Serialize,
+) -> Result<u32, String> {
+    let element_table = element_table_version().await?;
+    let (records, skipped) = read_records()?;
+    let id = next_id(&records, skipped);
+    let entry = HistoryEntry {
+        id,
+        timestamp: Local::now().format("%Y-%m-%d %H:%M:%S").to_string(),
+        target_formula: target_formula.trim().to_string(),
+        element_table,
+        tags: Vec::new(),
+        input,
+        output: serde_json::to_value(output).map_err(|e| e.to_string())?,
+    };
+    append_record(&HistoryRecord::Entry(entry))?;
+    Ok(id)
+}
+
+/// All entries, newest first.
+#[tauri::command]
+pub fn get_history() -> Result<HistoryList, String> {
+    let (entries, skipped) = read_history()?;
+    Ok(history_list(&entries, skipped))
+}
+
+fn parse_date(value: &Option<String>, label: &str) -> Result<Option<NaiveDate>, String> {
+    match value.as_deref().map(str::trim) {
+        None | Some("") => Ok(None),
+        Some(text) => NaiveDate::parse_from_str(text, "%Y-%m-%d")
+            .map(Some)
+            .map_err(|_| format!("{} date must be YYYY-MM-DD", label)),
+    }
+}
+
+#[tauri::command]
+pub fn search_history(query: HistoryQuery) -> Result<HistoryList, String> {
+    let (entries, skipped) = read_history()?;
+    Ok(history_list(&filter_entries(entries, &query)?, skipped))
+}
+
+fn filter_entries(
+    entries: Vec<HistoryEntry>,
+    query: &HistoryQuery,
+) -> Result<Vec<HistoryEntry>, String> {
+    let formula = query
+        .formula
+        .as_deref()
+        .map(|f| f.trim().to_lowercase())
+        .filter(|f| !f.is_empty());
+    let tag = query
+        .tag
+        .as_deref()
+        .map(|t| t.trim().to_lowercase())
+        .filter(|t| !t.is_empty());
+    let from = parse_date(&query.from, "From")?;
+    let to = parse_date(&query.to, "To")?;
+    Ok(entries
+        .into_iter()
+        .filter(|e| {
+            formula
+                .as_ref()
+                .is_none_or(|f| e.target_formula.to_lowercase().contains(f))
+        })
+        .filter(|e| {
+            tag.as_ref()
+                .is_none_or(|t| e.tags.iter().any(|tag| tag.to_lowercase() == *t))
+        })
+        .filter(|e| {
+            let date = e
+                .timestamp
+                .get(..10)
+                .and_then(|d| NaiveDate::parse_from_str(d, "%Y-%m-%d").ok());
+            from.is_none_or(|from| date.is_some_and(|d| d >= from))
+                && to.is_none_or(|to| date.is_some_and(|d| d <= to))
+        })
+        .collect())
+}
+
+/// Full entry, to re-open its input and result.
+#[tauri::command]
+pub fn get_history_entry(id: u32) -> Result<HistoryEntry, String> {
+    find_entry(id)
+}
+
+/// Replaces the tags of an entry.
+#[tauri::command]
+pub fn tag_history_entry(id: u32, tags: Vec<String>) -> Result<HistorySummary, String> {
+    let mut entry = find_entry(id)?;
+    let mut cleaned: Vec<String> = Vec::new();
+    for tag in tags.iter().map(|t| t.trim()).filter(|t| !t.is_empty()) {
+        if !cleaned.iter().any(|c| c.eq_ignore_ascii_case(tag)) {
+            cleaned.push(tag.to_string());
+        }
+    }
+    append_record(&HistoryRecord::Tags {
+        id,
+        tags: cleaned.clone(),
+    })?;
+    entry.tags = cleaned;
+    Ok(summary(&entry))
+}
+
+#[tauri::command]
+pub fn delete_history_entry(id: u32) -> Result<HistoryList, String> {
+    find_entry(id)?;
+    append_record(&HistoryRecord::Deleted { id })?;
+    get_history()
+}
+
+/// Leaf values of a JSON document keyed by path (`a.b[0]`).
+fn flatten(value: &Value, path: String, out: &mut Vec<(String, Value)>) {
+    match value {
+        Value::Object(map) => {
+            for (key, item) in map {
+                let child = if path.is_empty() {
+                    key.clone()
+                } else {
+                    format!("{}.{}", path, key)
+                };
+                flatten(item, child, out);
+            }
+        }
+        Value::Array(items) if !items.is_empty() => {
+            for (idx, item) in items.iter().enumerate() {
+                flatten(item, format!("{}[{}]", path, idx), out);
+            }
+        }
+        _ => out.push((path, value.clone())),
+    }
+}
+
+fn field_changes(first: &Value, second: &Value) -> Vec<FieldChange> {
+    let (mut a, mut b) = (Vec::new(), Vec::new());
+    flatten(first, String::new(), &mut a);
+    flatten(second, String::new(), &mut b);
+    let mut paths: Vec<&String> = a.iter().chain(b.iter()).map(|(p, _)| p).collect();
+    paths.sort();
+    paths.dedup();
+    let lookup = |items: &[(String, Value)], path: &str| {
+        items
+            .iter()
+            .find(|(p, _)| p == path)
+            .map(|(_, v)| v.clone())
+    };
+    paths
+        .into_iter()
+        .filter_map(|path| {
+            let (first, second) = (lookup(&a, path), lookup(&b, path));
+            (first != second).then(|| FieldChange {
+                path: path.clone(),
+                first,
+                second,
+            })
+        })
+        .collect()
+}
+
+fn show(value: &Option<Value>) -> String {
+    value
+        .as_ref()
+        .map(|v| v.to_string())
+        .unwrap_or_else(|| "-".to_string())
+}
+
+/// Compares two entries: changed input fields, reagent masses and the
+/// element table they were computed with.
+#[tauri::command]
+pub fn diff_history_entries(first: u32, second: u32) -> Result<HistoryDiff, String> {
+    let precision = read_settings()?.precision();
+    let a = find_entry(first)?;
+    let b = find_entry(second)?;
+    let output = |entry: &HistoryEntry| -> Result<CalculationOutput, String> {
+        serde_json::from_value(entry.output.clone())
+            .map_err(|e| format!("History entry {}: {}", entry.id, e))
+    };
+    let (out_a, out_b) = (output(&a)?, output(&b)?);
+
+    let mut explanation = vec![format!(
+        "Comparing #{} ({}, {}) with #{} ({}, {})",
+        a.id, a.target_formula, a.timestamp, b.id, b.target_formula, b.timestamp
+    )];
+    let element_table_changed = a.element_table != b.element_table;
+    if element_table_changed {
+        explanation.push("Computed with different element tables".to_string());
+    }
+    let input_changes = field_changes(&a.input, &b.input);
+    for change in &input_changes {
+        explanation.push(format!(
+            "Input {}: {} -> {}",
+            change.path,
+            show(&change.first),
+            show(&change.second)
+        ));
+    }
+    if out_a.molar_mass != out_b.molar_mass {
+        explanation.push(format!(
+            "Molar mass: {} -> {} g/mol",
+            format_value(out_a.molar_mass),
+            format_value(out_b.molar_mass)
+        ));
+    }
+
+    let mut names: Vec<&String> = Vec::new();
+    for reagent in out_a.reagents.iter().chain(out_b.reagents.iter()) {
+        if !names.contains(&&reagent.reagent) {
+            names.push(&reagent.reagent);
+        }
+    }
+    let mass = |output: &CalculationOutput, name: &str| {
+        output
+            .reagents
+            .iter()
+            .find(|r| r.reagent == name)
+            .map(|r| r.mass)
+    };
+    let reagent_changes: Vec<ReagentChange> = names
+        .into_iter()
+        .map(|name| {
+            let (first_mass, second_mass) = (mass(&out_a, name), mass(&out_b, name));
+            ReagentChange {
+                reagent: name.clone(),
+                first_mass,
+                second_mass,
+                delta: first_mass.zip(second_mass).map(|(x, y)| y - x),
+            }
+        })
+        .collect();
+    for change in reagent_changes
+        .iter()
+        .filter(|c| c.first_mass != c.second_mass)
+    {
+        let show_mass = |m: Option<f64>| {
+            m.map(|m| precision.display_mass(m))
+                .unwrap_or_else(|| "-".to_string())
+        };
+        explanation.push(format!(
+            "{}: {} -> {}",
+            change.reagent,
+            show_mass(change.first_mass),
+            show_mass(change.second_mass)
+        ));
+    }
+    if explanation.len() == 1 {
+        explanation.push("No differences".to_string());
+    }
+
+    Ok(HistoryDiff {
+        first,
+        second,
+        element_table_changed,
+        input_changes,
+        reagent_changes,
+        explanation,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn entry(id: u32, timestamp: &str, formula: &str) -> String {
+        serde_json::to_string(&HistoryRecord::Entry(HistoryEntry {
+            id,
+            timestamp: timestamp.to_string(),
+            target_formula: formula.to_string(),
+            element_table: "v1".to_string(),
+            tags: Vec::new(),
+            input: json!({ "target_formula": formula }),
+            output: Value::Null,
+        }))
+        .unwrap()
+    }
+
+    fn history(lines: &[String]) -> (Vec<HistoryRecord>, usize) {
+        parse_records(&lines.join("\n"))
+    }
+
+    fn ids(entries: &[HistoryEntry]) -> Vec<u32> {
+        entries.iter().map(|e| e.id).collect()
+    }
+
+    #[test]
+    fn deleted_ids_are_not_reused() {
+        let (records, skipped) = history(&[
+            entry(1, "2026-01-05 10:00:00", "BaTiO3"),
+            entry(2, "2026-01-06 10:00:00", "SrTiO3"),
+            r#"{"kind":"deleted","id":2}"#.to_string(),
+        ]);
+        assert_eq!(next_id(&records, skipped), 3);
+        assert_eq!(ids(&replay(records)), vec![1]);
+    }
+
+    #[test]
+    fn truncated_entry_keeps_its_id() {
+        let full = entry(2, "2026-01-06 10:00:00", "SrTiO3");
+        let (records, skipped) = history(&[
+            entry(1, "2026-01-05 10:00:00", "BaTiO3"),
+            r#"{"kind":"tags","id":1,"tags":["a"]}"#.to_string(),
+            full[..full.len() / 2].to_string(),
+        ]);
+        assert_eq!(skipped, 1);
+        assert_eq!(next_id(&records, skipped), 3);
+        assert_eq!(next_id(&[], 0), 1);
+    }
+
+    #[test]
+    fn tags_and_deletions_replay_in_order() {
+        let (records, _) = history(&[
+            entry(1, "2026-01-05 10:00:00", "BaTiO3"),
+            r#"{"kind":"tags","id":1,"tags":["batch A"]}"#.to_string(),
+            entry(2, "2026-01-06 10:00:00", "SrTiO3"),
+            r#"{"kind":"tags","id":2,"tags":["old"]}"#.to_string(),
+            r#"{"kind":"tags","id":1,"tags":["batch B","sintered"]}"#.to_string(),
+            r#"{"kind":"deleted","id":2}"#.to_string(),
+            r#"{"kind":"tags","id":2,"tags":["late"]}"#.to_string(),
+        ]);
+        let entries = replay(records);
+        assert_eq!(ids(&entries), vec![1]);
+        assert_eq!(entries[0].tags, vec!["batch B", "sintered"]);
+    }
+
+    #[test]
+    fn search_filters_by_date_range_and_tag() {
+        let (records, _) = history(&[
+            entry(1, "2026-01-05 10:00:00", "BaTiO3"),
+            entry(2, "2026-01-06 23:59:59", "SrTiO3"),
+            entry(3, "2026-01-07 00:00:00", "BaZrO3"),
+            r#"{"kind":"tags","id":3,"tags":["Sintered"]}"#.to_string(),
+        ]);
+        let entries = replay(records);
+        let search = |query: HistoryQuery| ids(&filter_entries(entries.clone(), &query).unwrap());
+
+        assert_eq!(
+            search(HistoryQuery {
+                from: Some("2026-01-06".to_string()),
+                ..Default::default()
+            }),
+            vec![2, 3]
+        );
+        assert_eq!(
+            search(HistoryQuery {
+                from: Some("2026-01-05".to_string()),
+                to: Some("2026-01-06".to_string()),
+                ..Default::default()
+            }),
+            vec![1, 2]
+        );
+        assert_eq!(
+            search(HistoryQuery {
+                tag: Some(" sintered ".to_string()),
+                ..Default::default()
+            }),
+            vec![3]
+        );
+        assert_eq!(
+            search(HistoryQuery {
+                formula: Some("ba".to_string()),
+                to: Some("2026-01-06".to_string()),
+                ..Default::default()
+            }),
+            vec![1]
+        );
+        assert_eq!(
+            filter_entries(
+                entries,
+                &HistoryQuery {
+                    from: Some("06/01/2026".to_string()),
+                    ..Default::default()
+                }
+            )
+            .err(),
+            Some("From date must be YYYY-MM-DD".to_string())
+        );
+    }
+
+    #[test]
+    fn diff_flattens_nested_paths() {
+        let mut leaves = Vec::new();
+        flatten(
+            &json!({
+                "target_mass": "5 g",
+                "reagents": [{ "formula": "BaCO3" }, { "formula": "TiO2", "purity": 99.9 }],
+                "volatile_elements": [],
+            }),
+            String::new(),
+            &mut leaves,
+        );
+        let paths: Vec<&str> = leaves.iter().map(|(p, _)| p.as_str()).collect();
+        assert_eq!(
+            paths,
+            vec![
+                "reagents[0].formula",
+                "reagents[1].formula",
+                "reagents[1].purity",
+                "target_mass",
+                "volatile_elements",
+            ]
+        );
+
+        let changes = field_changes(
+            &json!({ "target_mass": "5 g", "reagents": [{ "formula": "BaCO3" }] }),
+            &json!({ "target_mass": "10 g", "reagents": [{ "formula": "BaCO3" }, { "formula": "TiO2" }] }),
+        );
+        let summary: Vec<(&str, Option<&Value>, Option<&Value>)> = changes
+            .iter()
+            .map(|c| (c.path.as_str(), c.first.as_ref(), c.second.as_ref()))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("reagents[1].formula", None, Some(&json!("TiO2"))),
+                ("target_mass", Some(&json!("5 g")), Some(&json!("10 g"))),
+            ]
+        );
+    }
+}
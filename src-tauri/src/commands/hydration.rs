@@ -0,0 +1,241 @@
+use chrono::Local;
+use directories::ProjectDirs;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+
+use crate::chem::mass::{collapse_formula, molar_mass};
+use crate::chem::parse::{format_coefficient, parse_formula};
+use crate::commands::calc_helpers::{format_value, round_decimals};
+use crate::commands::fetch_elements::get_atomic_masses;
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ReagentOverride {
+    pub reagent: String,
+    pub formula: String,
+    pub volatile: String,
+    pub amount: f64,
+    pub measured_at: String,
+}
+
+#[derive(Deserialize)]
+pub struct HydrationInput {
+    pub reagent: String,
+    pub mass_before: f64,
+    pub mass_after: f64,
+    #[serde(default)]
+    pub residue: Option<String>,
+    #[serde(default = "default_volatile")]
+    pub volatile: String,
+    #[serde(default = "default_save")]
+    pub save: bool,
+}
+
+#[derive(Serialize)]
+pub struct HydrationOutput {
+    pub reagent: String,
+    pub corrected_formula: String,
+    pub volatile: String,
+    pub amount: f64,
+    pub nominal_molar_mass: f64,
+    pub effective_molar_mass: f64,
+    pub saved: bool,
+    pub explanation: Vec<String>,
+}
+
+fn default_volatile() -> String {
+    "H2O".to_string()
+}
+
+fn default_save() -> bool {
+    true
+}
+
+fn get_overrides_path() -> PathBuf {
+    let proj_dirs =
+        ProjectDirs::from("com", "chooinet", "MassCalc").expect("Cannot get project directories");
+    let data_dir = proj_dirs.data_local_dir();
+    fs::create_dir_all(data_dir).expect("Cannot create data directory");
+    data_dir.join("reagent_overrides.json")
+}
+
+pub fn read_overrides() -> Result<Vec<ReagentOverride>, String> {
+    let path = get_overrides_path();
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let raw = fs::read_to_string(path).map_err(|e| e.to_string())?;
+    serde_json::from_str(&raw).map_err(|e| e.to_string())
+}
+
+fn write_overrides(overrides: &[ReagentOverride]) -> Result<(), String> {
+    let data = serde_json::to_string_pretty(overrides).map_err(|e| e.to_string())?;
+    fs::write(get_overrides_path(), data).map_err(|e| e.to_string())
+}
+
+pub fn find_override<'a>(
+    overrides: &'a [ReagentOverride],
+    reagent: &str,
+) -> Option<&'a ReagentOverride> {
+    overrides.iter().find(|o| o.reagent == reagent.trim())
+}
+
+/// Formula to weigh out for `reagent`: the measured override when one is
+/// stored, otherwise the nominal formula.
+pub fn effective_formula(overrides: &[ReagentOverride], reagent: &str) -> String {
+    find_override(overrides, reagent)
+        .map(|o| o.formula.clone())
+        .unwrap_or_else(|| reagent.trim().to_string())
+}
+
+/// Anhydrous part of a formula, i.e. everything before a hydrate separator.
+fn anhydrous_part(formula: &str) -> &str {
+    match formula.find(['·', '*']) {
+        Some(idx) => formula[..idx].trim(),
+        None => formula.trim(),
+    }
+}
+
+/// Moles of crystal water per formula unit, i.e. the H2O written after a
+/// hydrate separator (`·` or `*`).
+pub fn crystal_water(formula: &str, composition: &HashMap<String, f64>) -> Result<f64, String> {
+    let Some(idx) = formula.find(['·', '*']) else {
+        return Ok(0.0);
+    };
+    let anhydrous = collapse_formula(&parse_formula(&formula[..idx])?);
+    let hydrogen =
+        composition.get("H").copied().unwrap_or(0.0) - anhydrous.get("H").copied().unwrap_or(0.0);
+    let oxygen =
+        composition.get("O").copied().unwrap_or(0.0) - anhydrous.get("O").copied().unwrap_or(0.0);
+    let only_water = composition.iter().all(|(el, c)| {
+        el == "H" || el == "O" || (c - anhydrous.get(el).unwrap_or(&0.0)).abs() < 1e-9
+    });
+    if only_water && hydrogen > 0.0 && (hydrogen - 2.0 * oxygen).abs() < 1e-9 {
+        Ok(oxygen)
+    } else {
+        Ok(0.0)
+    }
+}
+
+/// Works out the water (or CO2) content per formula unit from a mass pair
+/// measured before and after drying or ignition. Without a `residue` the
+/// dried product is taken to be the anhydrous reagent; for ignition give
+/// the residue per reagent formula unit, e.g. "LaO1.5" for a lanthanum
+/// nitrate.
+#[tauri::command]
+pub async fn determine_hydration(input: HydrationInput) -> Result<HydrationOutput, String> {
+    if input.mass_before <= 0.0 || input.mass_after <= 0.0 {
+        return Err("Masses must be positive".to_string());
+    }
+    if input.mass_after > input.mass_before {
+        return Err("Mass after drying exceeds mass before".to_string());
+    }
+    let reagent = input.reagent.trim();
+    let base = anhydrous_part(reagent);
+    let volatile = input.volatile.trim();
+    let residue = input
+        .residue
+        .as_deref()
+        .map(str::trim)
+        .filter(|r| !r.is_empty())
+        .unwrap_or(base);
+
+    let masses = get_atomic_masses().await?;
+    let mass_of = |formula: &str| -> Result<f64, String> {
+        molar_mass(&collapse_formula(&parse_formula(formula)?), &masses)
+    };
+    let nominal_molar_mass = mass_of(reagent)?;
+    let base_molar_mass = mass_of(base)?;
+    let residue_molar_mass = mass_of(residue)?;
+    let volatile_molar_mass = mass_of(volatile)?;
+    if residue_molar_mass <= 0.0 || volatile_molar_mass <= 0.0 {
+        return Err("Residue and volatile molar masses must be positive".to_string());
+    }
+
+    let moles = input.mass_after / residue_molar_mass;
+    let effective_molar_mass = input.mass_before / moles;
+    let amount = (effective_molar_mass - base_molar_mass) / volatile_molar_mass;
+    if amount < -1e-3 {
+        return Err(format!(
+            "Measured loss is smaller than the anhydrous {} implies ({} {} per formula unit)",
+            base,
+            format_value(amount),
+            volatile
+        ));
+    }
+    let amount = round_decimals(amount.max(0.0), 4);
+    let corrected_formula = if amount > 0.0 {
+        format!("{}·{}{}", base, format_coefficient(amount), volatile)
+    } else {
+        base.to_string()
+    };
+
+    let mut explanation = vec![
+        format!(
+            "{} g -> {} g of {} ({} g/mol) = {} mol formula units",
+            input.mass_before,
+            input.mass_after,
+            residue,
+            format_value(residue_molar_mass),
+            format_value(moles)
+        ),
+        format!(
+            "Effective molar mass: {} g / {} mol = {} g/mol (nominal {} g/mol)",
+            input.mass_before,
+            format_value(moles),
+            format_value(effective_molar_mass),
+            format_value(nominal_molar_mass)
+        ),
+        format!(
+            "{} per formula unit: ({} - {}) / {} = {}",
+            volatile,
+            format_value(effective_molar_mass),
+            format_value(base_molar_mass),
+            format_value(volatile_molar_mass),
+            format_coefficient(amount)
+        ),
+        format!("Corrected formula: {}", corrected_formula),
+    ];
+
+    if input.save {
+        let mut overrides = read_overrides()?;
+        overrides.retain(|o| o.reagent != reagent);
+        overrides.push(ReagentOverride {
+            reagent: reagent.to_string(),
+            formula: corrected_formula.clone(),
+            volatile: volatile.to_string(),
+            amount,
+            measured_at: Local::now().format("%Y-%m-%d %H:%M").to_string(),
+        });
+        write_overrides(&overrides)?;
+        explanation.push(format!(
+            "Saved as override: {} is weighed as {} in later calculations",
+            reagent, corrected_formula
+        ));
+    }
+
+    Ok(HydrationOutput {
+        reagent: reagent.to_string(),
+        effective_molar_mass: mass_of(&corrected_formula)?,
+        corrected_formula,
+        volatile: volatile.to_string(),
+        amount,
+        nominal_molar_mass,
+        saved: input.save,
+        explanation,
+    })
+}
+
+#[tauri::command]
+pub fn get_reagent_overrides() -> Result<Vec<ReagentOverride>, String> {
+    read_overrides()
+}
+
+#[tauri::command]
+pub fn delete_reagent_override(reagent: String) -> Result<Vec<ReagentOverride>, String> {
+    let mut overrides = read_overrides()?;
+    overrides.retain(|o| o.reagent != reagent.trim());
+    write_overrides(&overrides)?;
+    Ok(overrides)
+}
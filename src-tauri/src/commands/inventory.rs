@@ -0,0 +1,365 @@
+use chrono::Local;
+use directories::ProjectDirs;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+
+use crate::commands::calc_helpers::Precision;
+use crate::commands::export_types::CalculationOutput;
+use crate::commands::library::{read_library, Compound};
+use crate::commands::settings::read_settings;
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Container {
+    #[serde(default)]
+    pub id: u32,
+    pub compound_id: u32,
+    pub label: String,
+    pub mass: f64,
+    #[serde(default)]
+    pub low_threshold: Option<f64>,
+    #[serde(default)]
+    pub location: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct LedgerEntry {
+    pub container_id: u32,
+    pub date: String,
+    pub reagent: String,
+    pub mass: f64,
+    pub remaining: f64,
+    pub target_formula: String,
+    #[serde(default)]
+    pub note: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct Inventory {
+    #[serde(default)]
+    pub containers: Vec<Container>,
+    #[serde(default)]
+    pub ledger: Vec<LedgerEntry>,
+}
+
+#[derive(Deserialize)]
+pub struct Allocation {
+    pub reagent: String,
+    pub container_id: u32,
+}
+
+#[derive(Deserialize)]
+pub struct CommitInput {
+    pub output: CalculationOutput,
+    #[serde(default)]
+    pub allocations: Vec<Allocation>,
+    #[serde(default)]
+    pub note: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct StockWarning {
+    pub reagent: String,
+    pub container_id: Option<u32>,
+    pub required: f64,
+    pub available: f64,
+    pub message: String,
+}
+
+#[derive(Serialize)]
+pub struct CommitOutput {
+    pub withdrawals: Vec<LedgerEntry>,
+    pub warnings: Vec<StockWarning>,
+}
+
+fn get_inventory_path() -> PathBuf {
+    let proj_dirs =
+        ProjectDirs::from("com", "chooinet", "MassCalc").expect("Cannot get project directories");
+    let data_dir = proj_dirs.data_local_dir();
+    fs::create_dir_all(data_dir).expect("Cannot create data directory");
+    data_dir.join("inventory.json")
+}
+
+pub fn read_inventory() -> Result<Inventory, String> {
+    let path = get_inventory_path();
+    if !path.exists() {
+        return Ok(Inventory::default());
+    }
+    let raw = fs::read_to_string(path).map_err(|e| e.to_string())?;
+    serde_json::from_str(&raw).map_err(|e| e.to_string())
+}
+
+fn write_inventory(inventory: &Inventory) -> Result<(), String> {
+    let data = serde_json::to_string_pretty(inventory).map_err(|e| e.to_string())?;
+    fs::write(get_inventory_path(), data).map_err(|e| e.to_string())
+}
+
+/// Mass taken from the bottle: the solution for liquids weighed by mass,
+/// the solid otherwise. `None` for a solution dispensed by volume without a
+/// density, whose mass is unknown.
+pub fn weighed_mass(mass: f64, solution_mass: Option<f64>, volume_ml: Option<f64>) -> Option<f64> {
+    match (solution_mass, volume_ml) {
+        (Some(m), _) => Some(m),
+        (None, Some(_)) => None,
+        (None, None) => Some(mass),
+    }
+}
+
+/// Picks the container a reagent is drawn from: an explicit allocation, else
+/// the first container of a matching library compound holding enough, else
+/// the fullest one. An allocation must name an existing container holding
+/// that reagent.
+fn pick_container(
+    inventory: &Inventory,
+    library: &[Compound],
+    allocations: &[Allocation],
+    reagent: &str,
+    required: f64,
+) -> Result<Option<usize>, String> {
+    if let Some(allocation) = allocations.iter().find(|a| a.reagent.trim() == reagent) {
+        let idx = inventory
+            .containers
+            .iter()
+            .position(|c| c.id == allocation.container_id)
+            .ok_or_else(|| format!("Unknown container: {}", allocation.container_id))?;
+        let container = &inventory.containers[idx];
+        let holds = library
+            .iter()
+            .find(|compound| compound.id == container.compound_id)
+            .map(|compound| compound.formula.as_str());
+        if holds != Some(reagent) {
+            return Err(format!(
+                "{} holds {}, not {}",
+                container.label,
+                holds.unwrap_or("an unknown compound"),
+                reagent
+            ));
+        }
+        return Ok(Some(idx));
+    }
+    let candidates: Vec<usize> = inventory
+        .containers
+        .iter()
+        .enumerate()
+        .filter(|(_, c)| {
+            library
+                .iter()
+                .any(|compound| compound.id == c.compound_id && compound.formula == reagent)
+        })
+        .map(|(idx, _)| idx)
+        .collect();
+    Ok(candidates
+        .iter()
+        .copied()
+        .find(|idx| inventory.containers[*idx].mass >= required)
+        .or_else(|| {
+            candidates.iter().copied().max_by(|a, b| {
+                inventory.containers[*a]
+                    .mass
+                    .total_cmp(&inventory.containers[*b].mass)
+            })
+        }))
+}
+
+/// Container index, mass and reagent of each withdrawal, with the warnings.
+type StockPlan = (Vec<(usize, f64, String)>, Vec<StockWarning>);
+
+fn stock_plan(
+    inventory: &Inventory,
+    library: &[Compound],
+    output: &CalculationOutput,
+    allocations: &[Allocation],
+    precision: &Precision,
+) -> Result<StockPlan, String> {
+    let mut plan: Vec<(usize, f64, String)> = Vec::new();
+    let mut warnings = Vec::new();
+    for reagent in &output.reagents {
+        let name = reagent.reagent.trim();
+        let Some(required) = weighed_mass(reagent.mass, reagent.solution_mass, reagent.volume_ml)
+        else {
+            warnings.push(StockWarning {
+                reagent: name.to_string(),
+                container_id: None,
+                required: 0.0,
+                available: 0.0,
+                message: format!(
+                    "{} is dispensed by volume without a density; its stock is not deducted",
+                    name
+                ),
+            });
+            continue;
+        };
+        if required <= 0.0 {
+            continue;
+        }
+        let Some(idx) = pick_container(inventory, library, allocations, name, required)? else {
+            warnings.push(StockWarning {
+                reagent: name.to_string(),
+                container_id: None,
+                required,
+                available: 0.0,
+                message: format!("{} is not tracked in the inventory", name),
+            });
+            continue;
+        };
+        let container = &inventory.containers[idx];
+        let already: f64 = plan
+            .iter()
+            .filter(|(i, _, _)| *i == idx)
+            .map(|(_, m, _)| m)
+            .sum();
+        let available = container.mass - already;
+        if required > available {
+            warnings.push(StockWarning {
+                reagent: name.to_string(),
+                container_id: Some(container.id),
+                required,
+                available,
+                message: format!(
+                    "{} needs {} but {} holds {}",
+                    name,
+                    precision.display_mass(required),
+                    container.label,
+                    precision.display_mass(available)
+                ),
+            });
+        } else if let Some(threshold) = container.low_threshold {
+            if available - required < threshold {
+                warnings.push(StockWarning {
+                    reagent: name.to_string(),
+                    container_id: Some(container.id),
+                    required,
+                    available,
+                    message: format!(
+                        "{} will drop to {}, below its {} threshold",
+                        container.label,
+                        precision.display_mass(available - required),
+                        precision.display_mass(threshold)
+                    ),
+                });
+            }
+        }
+        plan.push((idx, required, name.to_string()));
+    }
+    Ok((plan, warnings))
+}
+
+#[tauri::command]
+pub fn get_inventory() -> Result<Inventory, String> {
+    read_inventory()
+}
+
+/// Adds a container (id 0) or replaces the one with the same id.
+#[tauri::command]
+pub fn save_container(container: Container) -> Result<Container, String> {
+    if container.mass < 0.0 {
+        return Err("Container mass cannot be negative".to_string());
+    }
+    if !read_library()?
+        .iter()
+        .any(|c| c.id == container.compound_id)
+    {
+        return Err(format!(
+            "Unknown library compound: #{}",
+            container.compound_id
+        ));
+    }
+    let mut inventory = read_inventory()?;
+    let mut container = container;
+    container.label = container.label.trim().to_string();
+    if container.id == 0 {
+        container.id = inventory.containers.iter().map(|c| c.id).max().unwrap_or(0) + 1;
+        inventory.containers.push(container.clone());
+    } else {
+        let existing = inventory
+            .containers
+            .iter_mut()
+            .find(|c| c.id == container.id)
+            .ok_or_else(|| format!("Unknown container: {}", container.id))?;
+        *existing = container.clone();
+    }
+    write_inventory(&inventory)?;
+    Ok(container)
+}
+
+#[tauri::command]
+pub fn delete_container(id: u32) -> Result<Inventory, String> {
+    let mut inventory = read_inventory()?;
+    let before = inventory.containers.len();
+    inventory.containers.retain(|c| c.id != id);
+    if inventory.containers.len() == before {
+        return Err(format!("Unknown container: {}", id));
+    }
+    write_inventory(&inventory)?;
+    Ok(inventory)
+}
+
+#[tauri::command]
+pub fn get_ledger(container_id: Option<u32>) -> Result<Vec<LedgerEntry>, String> {
+    Ok(read_inventory()?
+        .ledger
+        .into_iter()
+        .filter(|e| container_id.is_none_or(|id| e.container_id == id))
+        .collect())
+}
+
+/// Compares the masses of a calculation with the stock without changing it.
+#[tauri::command]
+pub fn check_stock(input: CommitInput) -> Result<Vec<StockWarning>, String> {
+    let inventory = read_inventory()?;
+    let library = read_library()?;
+    let precision = read_settings()?.precision();
+    Ok(stock_plan(
+        &inventory,
+        &library,
+        &input.output,
+        &input.allocations,
+        &precision,
+    )?
+    .1)
+}
+
+/// Deducts the weighed masses from their containers and records each
+/// withdrawal in the ledger. Nothing is deducted if any container is short.
+#[tauri::command]
+pub fn commit_synthesis(input: CommitInput) -> Result<CommitOutput, String> {
+    let mut inventory = read_inventory()?;
+    let library = read_library()?;
+    let precision = read_settings()?.precision();
+    let (plan, warnings) = stock_plan(
+        &inventory,
+        &library,
+        &input.output,
+        &input.allocations,
+        &precision,
+    )?;
+    if let Some(short) = warnings
+        .iter()
+        .find(|w| w.container_id.is_some() && w.required > w.available)
+    {
+        return Err(format!("Insufficient stock: {}", short.message));
+    }
+
+    let date = Local::now().format("%Y-%m-%d %H:%M").to_string();
+    let mut withdrawals = Vec::new();
+    for (idx, mass, reagent) in plan {
+        let container = &mut inventory.containers[idx];
+        container.mass -= mass;
+        let entry = LedgerEntry {
+            container_id: container.id,
+            date: date.clone(),
+            reagent,
+            mass,
+            remaining: container.mass,
+            target_formula: input.output.target_formula.clone(),
+            note: input.note.clone(),
+        };
+        inventory.ledger.push(entry.clone());
+        withdrawals.push(entry);
+    }
+    write_inventory(&inventory)?;
+    Ok(CommitOutput {
+        withdrawals,
+        warnings,
+    })
+}
@@ -0,0 +1,270 @@
+use directories::ProjectDirs;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+
+use crate::chem::mass::collapse_formula;
+use crate::chem::parse::parse_formula;
+use crate::commands::hydration::crystal_water;
+
+/// Hydrate states and the crystal water per formula unit they imply.
+const HYDRATE_STATES: [(&str, f64); 13] = [
+    ("anhydrous", 0.0),
+    ("hemihydrate", 0.5),
+    ("monohydrate", 1.0),
+    ("sesquihydrate", 1.5),
+    ("dihydrate", 2.0),
+    ("trihydrate", 3.0),
+    ("tetrahydrate", 4.0),
+    ("pentahydrate", 5.0),
+    ("hexahydrate", 6.0),
+    ("heptahydrate", 7.0),
+    ("octahydrate", 8.0),
+    ("nonahydrate", 9.0),
+    ("decahydrate", 10.0),
+];
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Compound {
+    #[serde(default)]
+    pub id: u32,
+    pub name: String,
+    pub formula: String,
+    #[serde(default)]
+    pub cas: Option<String>,
+    #[serde(default)]
+    pub purity: Option<f64>,
+    #[serde(default)]
+    pub supplier: Option<String>,
+    #[serde(default)]
+    pub lot: Option<String>,
+    /// `anhydrous`, `monohydrate`, `hexahydrate`, ...; must agree with the
+    /// crystal water written in `formula`.
+    #[serde(default)]
+    pub hydrate_state: Option<String>,
+    #[serde(default)]
+    pub density: Option<f64>,
+    #[serde(default)]
+    pub notes: Option<String>,
+    #[serde(default)]
+    pub price_per_gram: Option<f64>,
+    #[serde(default)]
+    pub currency: Option<String>,
+    #[serde(default)]
+    pub pack_size: Option<f64>,
+}
+
+fn get_library_path() -> PathBuf {
+    let proj_dirs =
+        ProjectDirs::from("com", "chooinet", "MassCalc").expect("Cannot get project directories");
+    let data_dir = proj_dirs.data_local_dir();
+    fs::create_dir_all(data_dir).expect("Cannot create data directory");
+    data_dir.join("compounds.json")
+}
+
+pub fn read_library() -> Result<Vec<Compound>, String> {
+    let path = get_library_path();
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let raw = fs::read_to_string(path).map_err(|e| e.to_string())?;
+    serde_json::from_str(&raw).map_err(|e| e.to_string())
+}
+
+fn write_library(library: &[Compound]) -> Result<(), String> {
+    let data = serde_json::to_string_pretty(library).map_err(|e| e.to_string())?;
+    fs::write(get_library_path(), data).map_err(|e| e.to_string())
+}
+
+/// Resolves a starting-material entry. Library compounds are referenced as
+/// `#<id>`; anything else is taken as a plain formula.
+pub fn resolve_compound<'a>(
+    library: &'a [Compound],
+    entry: &str,
+) -> Result<Option<&'a Compound>, String> {
+    let Some(id) = entry.trim().strip_prefix('#') else {
+        return Ok(None);
+    };
+    let id: u32 = id
+        .trim()
+        .parse()
+        .map_err(|_| format!("Invalid library reference: {}", entry.trim()))?;
+    library
+        .iter()
+        .find(|c| c.id == id)
+        .map(Some)
+        .ok_or_else(|| format!("Unknown library compound: #{}", id))
+}
+
+/// Price per gram and currency of a compound, when it has one.
+pub fn compound_price(compound: &Compound) -> Option<(f64, String)> {
+    compound
+        .price_per_gram
+        .map(|price| (price, compound.currency.clone().unwrap_or_default()))
+}
+
+/// First priced library entry with this formula.
+pub fn priced_compound<'a>(library: &'a [Compound], formula: &str) -> Option<&'a Compound> {
+    library
+        .iter()
+        .find(|c| c.formula == formula.trim() && c.price_per_gram.is_some())
+}
+
+/// Price of the first priced library entry with this formula.
+pub fn library_price(library: &[Compound], formula: &str) -> Option<(f64, String)> {
+    priced_compound(library, formula).and_then(compound_price)
+}
+
+fn validate(compound: &Compound) -> Result<(), String> {
+    if compound.name.trim().is_empty() {
+        return Err("Compound name is required".to_string());
+    }
+    let formula = compound.formula.trim();
+    let composition = collapse_formula(&parse_formula(formula)?);
+    if let Some(state) = compound
+        .hydrate_state
+        .as_deref()
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+    {
+        let (_, water) = HYDRATE_STATES
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(state))
+            .ok_or_else(|| {
+                format!(
+                    "Unknown hydrate state: '{}' (use anhydrous, monohydrate, dihydrate, ...)",
+                    state
+                )
+            })?;
+        let written = crystal_water(formula, &composition)?;
+        if (written - water).abs() > 1e-9 {
+            return Err(format!(
+                "{} is {} but {} carries {} H2O per formula unit",
+                compound.name.trim(),
+                state,
+                formula,
+                written
+            ));
+        }
+    }
+    if let Some(purity) = compound.purity {
+        if purity <= 0.0 || purity > 100.0 {
+            return Err("Purity must be between 0 and 100%".to_string());
+        }
+    }
+    if let Some(density) = compound.density {
+        if density <= 0.0 {
+            return Err("Density must be positive".to_string());
+        }
+    }
+    if compound.price_per_gram.is_some_and(|p| p < 0.0) {
+        return Err("Price cannot be negative".to_string());
+    }
+    if compound.pack_size.is_some_and(|s| s <= 0.0) {
+        return Err("Pack size must be positive".to_string());
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub fn get_compounds() -> Result<Vec<Compound>, String> {
+    read_library()
+}
+
+/// Adds a compound (id 0) or replaces the one with the same id.
+#[tauri::command]
+pub fn save_compound(compound: Compound) -> Result<Compound, String> {
+    validate(&compound)?;
+    let mut library = read_library()?;
+    let mut compound = compound;
+    compound.name = compound.name.trim().to_string();
+    compound.formula = compound.formula.trim().to_string();
+    if compound.id == 0 {
+        compound.id = library.iter().map(|c| c.id).max().unwrap_or(0) + 1;
+        library.push(compound.clone());
+    } else {
+        let existing = library
+            .iter_mut()
+            .find(|c| c.id == compound.id)
+            .ok_or_else(|| format!("Unknown library compound: #{}", compound.id))?;
+        *existing = compound.clone();
+    }
+    write_library(&library)?;
+    Ok(compound)
+}
+
+#[tauri::command]
+pub fn delete_compound(id: u32) -> Result<Vec<Compound>, String> {
+    let mut library = read_library()?;
+    let before = library.len();
+    library.retain(|c| c.id != id);
+    if library.len() == before {
+        return Err(format!("Unknown library compound: #{}", id));
+    }
+    write_library(&library)?;
+    Ok(library)
+}
+
+/// Case-insensitive substring search over name, formula and CAS number.
+#[tauri::command]
+pub fn search_compounds(query: String) -> Result<Vec<Compound>, String> {
+    let query = query.trim().to_lowercase();
+    let library = read_library()?;
+    if query.is_empty() {
+        return Ok(library);
+    }
+    Ok(library
+        .into_iter()
+        .filter(|c| {
+            c.name.to_lowercase().contains(&query)
+                || c.formula.to_lowercase().contains(&query)
+                || c.cas
+                    .as_deref()
+                    .is_some_and(|cas| cas.to_lowercase().contains(&query))
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn compound(formula: &str, hydrate_state: Option<&str>) -> Compound {
+        Compound {
+            id: 0,
+            name: "Copper sulfate".to_string(),
+            formula: formula.to_string(),
+            cas: None,
+            purity: None,
+            supplier: None,
+            lot: None,
+            hydrate_state: hydrate_state.map(str::to_string),
+            density: None,
+            notes: None,
+            price_per_gram: None,
+            currency: None,
+            pack_size: None,
+        }
+    }
+
+    #[test]
+    fn hydrate_state_must_match_the_formula() {
+        assert!(validate(&compound("CuSO4·5H2O", Some("Pentahydrate"))).is_ok());
+        assert!(validate(&compound("CuSO4", Some("anhydrous"))).is_ok());
+        assert!(validate(&compound("CuSO4", None)).is_ok());
+        assert_eq!(
+            validate(&compound("CuSO4", Some("pentahydrate"))).err(),
+            Some(
+                "Copper sulfate is pentahydrate but CuSO4 carries 0 H2O per formula unit"
+                    .to_string()
+            )
+        );
+        assert_eq!(
+            validate(&compound("CuSO4·5H2O", Some("hydrated"))).err(),
+            Some(
+                "Unknown hydrate state: 'hydrated' (use anhydrous, monohydrate, dihydrate, ...)"
+                    .to_string()
+            )
+        );
+    }
+}
@@ -0,0 +1,231 @@
+use serde::Serialize;
+use std::collections::HashMap;
+
+use crate::chem::mass::{collapse_formula, molar_mass};
+use crate::chem::parse::parse_formula;
+use crate::commands::calc_helpers::format_value;
+use crate::commands::calculate::{run_calculation, CalculationInput};
+use crate::commands::fetch_elements::get_atomic_masses;
+use crate::commands::hydration::{crystal_water, effective_formula, read_overrides};
+use crate::commands::settings::read_settings;
+
+/// Decomposition steps in the order they usually appear on heating. The
+/// last one takes whatever mass the gases before it leave unexplained.
+const STEPS: [(&str, &str); 10] = [
+    ("Dehydration (crystal water)", "H2O"),
+    ("Dehydroxylation / combustion water", "H2O"),
+    ("Nitrate decomposition", "NO2"),
+    ("Decarbonation", "CO2"),
+    ("Sulfate decomposition", "SO2"),
+    ("Halide decomposition", "F2"),
+    ("Halide decomposition", "Cl2"),
+    ("Halide decomposition", "Br2"),
+    ("Halide decomposition", "I2"),
+    ("Oxygen release", "O2"),
+];
+const REMAINDER: usize = STEPS.len() - 1;
+
+/// Elements with a gas in `STEPS`.
+const GAS_ELEMENTS: [&str; 9] = ["H", "N", "C", "S", "F", "Cl", "Br", "I", "O"];
+
+#[derive(Serialize)]
+pub struct MassLossStep {
+    pub step: String,
+    pub gas: String,
+    /// Zero for other volatiles, which are not a single gas.
+    pub moles: f64,
+    pub mass: f64,
+    pub percent: f64,
+    pub residual_mass: f64,
+    pub residual_percent: f64,
+}
+
+#[derive(Serialize)]
+pub struct ReagentLoss {
+    pub reagent: String,
+    /// Mass of the pure compound, without purity correction; the basis of
+    /// `loss_percent`, like `green_mass`.
+    pub mass: f64,
+    pub loss_mass: f64,
+    pub loss_percent: f64,
+}
+
+#[derive(Serialize)]
+pub struct MassLossOutput {
+    pub target_formula: String,
+    pub green_mass: f64,
+    pub product_mass: f64,
+    pub total_loss: f64,
+    pub total_loss_percent: f64,
+    pub steps: Vec<MassLossStep>,
+    pub reagents: Vec<ReagentLoss>,
+    pub explanation: Vec<String>,
+}
+
+/// Predicts the mass loss on firing the precursor mix returned by
+/// `calculate`. Crystal water and remaining hydrogen leave as H2O, nitrogen
+/// as NO2, carbon as CO2, sulfur as SO2 and halogens as X2; whatever is left
+/// of the mass-check delta is booked as O2 released (or taken up, when
+/// negative), or as other volatiles when further elements are left over.
+#[tauri::command]
+pub async fn mass_loss_profile(input: CalculationInput) -> Result<MassLossOutput, String> {
+    let calc = run_calculation(input).await?;
+    let masses = get_atomic_masses().await?;
+    let overrides = read_overrides()?;
+    let precision = read_settings()?.precision();
+    let gas_mass = |formula: &str| -> Result<f64, String> {
+        molar_mass(&collapse_formula(&parse_formula(formula)?), &masses)
+    };
+
+    let mut gas_moles = [0.0; STEPS.len()];
+    let mut reagents = Vec::new();
+    let mut explanation = Vec::new();
+    for item in &calc.reagents {
+        if item.moles <= 0.0 {
+            continue;
+        }
+        let formula = effective_formula(&overrides, &item.reagent);
+        let composition = collapse_formula(&parse_formula(&formula)?);
+        let water = crystal_water(&formula, &composition)?;
+        let count = |el: &str| composition.get(el).copied().unwrap_or(0.0);
+        let per_unit = [
+            water,
+            (count("H") - 2.0 * water) / 2.0,
+            count("N"),
+            count("C"),
+            count("S"),
+            count("F") / 2.0,
+            count("Cl") / 2.0,
+            count("Br") / 2.0,
+            count("I") / 2.0,
+        ];
+        let mut loss_mass = 0.0;
+        let mut parts = Vec::new();
+        for (idx, n) in per_unit.iter().enumerate() {
+            if *n <= 1e-12 {
+                continue;
+            }
+            let moles = n * item.moles;
+            let mass = moles * gas_mass(STEPS[idx].1)?;
+            gas_moles[idx] += moles;
+            loss_mass += mass;
+            parts.push(format!("{} {}", format_value(*n), STEPS[idx].1));
+        }
+        if !parts.is_empty() {
+            explanation.push(format!(
+                "{} releases {} per formula unit -> {}",
+                item.reagent,
+                parts.join(" + "),
+                precision.display_mass(loss_mass)
+            ));
+        }
+        let pure_mass = item.moles * item.molar_mass;
+        reagents.push(ReagentLoss {
+            reagent: item.reagent.clone(),
+            mass: pure_mass,
+            loss_mass,
+            loss_percent: if pure_mass > 0.0 {
+                loss_mass / pure_mass * 100.0
+            } else {
+                0.0
+            },
+        });
+    }
+
+    let green_mass: f64 = calc.reagents.iter().map(|r| r.moles * r.molar_mass).sum();
+    let product_mass = calc.mass_check.target_mass;
+    let total_loss = green_mass - product_mass;
+    let gas_loss: f64 = gas_moles
+        .iter()
+        .zip(STEPS.iter())
+        .take(REMAINDER)
+        .map(|(n, (_, gas))| gas_mass(gas).map(|m| n * m))
+        .sum::<Result<f64, String>>()?;
+    gas_moles[REMAINDER] = (total_loss - gas_loss) / gas_mass("O2")?;
+
+    // Elements the precursors bring in beyond the product and without a gas
+    // above; their mass hides in the remainder, so it is not all oxygen.
+    let mut leftover: HashMap<String, f64> = HashMap::new();
+    for item in calc.reagents.iter().filter(|r| r.moles > 0.0) {
+        let formula = effective_formula(&overrides, &item.reagent);
+        for (el, c) in collapse_formula(&parse_formula(&formula)?) {
+            *leftover.entry(el).or_insert(0.0) += c * item.moles;
+        }
+    }
+    for el in &calc.parsed_formula {
+        *leftover.entry(el.element.clone()).or_insert(0.0) -= el.coefficient * calc.target_moles;
+    }
+    let mut other: Vec<String> = leftover
+        .into_iter()
+        .filter(|(el, n)| *n > 1e-9 && !GAS_ELEMENTS.contains(&el.as_str()))
+        .map(|(el, _)| el)
+        .collect();
+    other.sort();
+
+    explanation.insert(
+        0,
+        format!(
+            "Green mix {} -> {} {}: total loss {} ({:.4}%), i.e. the mass-check delta",
+            precision.display_mass(green_mass),
+            calc.target_formula,
+            precision.display_mass(product_mass),
+            precision.display_mass(total_loss),
+            total_loss / green_mass * 100.0
+        ),
+    );
+
+    let mut steps = Vec::new();
+    let mut residual = green_mass;
+    for (idx, (step, gas)) in STEPS.iter().enumerate() {
+        let mut moles = gas_moles[idx];
+        let mass = moles * gas_mass(gas)?;
+        if mass.abs() < 1e-9 {
+            continue;
+        }
+        residual -= mass;
+        let mut gas = gas.to_string();
+        let label = if idx == REMAINDER && !other.is_empty() {
+            moles = 0.0;
+            gas = format!("{}, O", other.join(", "));
+            format!("Other volatiles ({})", gas)
+        } else if idx == REMAINDER && moles < 0.0 {
+            "Oxygen uptake".to_string()
+        } else {
+            step.to_string()
+        };
+        let amount = if moles == 0.0 {
+            String::new()
+        } else {
+            format!("{} mol {} = ", precision.format_significant(moles), gas)
+        };
+        explanation.push(format!(
+            "{}: {}{} ({:.4}%), residue {} ({:.4}%)",
+            label,
+            amount,
+            precision.display_mass(mass),
+            mass / green_mass * 100.0,
+            precision.display_mass(residual),
+            residual / green_mass * 100.0
+        ));
+        steps.push(MassLossStep {
+            step: label,
+            gas,
+            moles,
+            mass,
+            percent: mass / green_mass * 100.0,
+            residual_mass: residual,
+            residual_percent: residual / green_mass * 100.0,
+        });
+    }
+
+    Ok(MassLossOutput {
+        target_formula: calc.target_formula,
+        green_mass,
+        product_mass,
+        total_loss,
+        total_loss_percent: total_loss / green_mass * 100.0,
+        steps,
+        reagents,
+        explanation,
+    })
+}
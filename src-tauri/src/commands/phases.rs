@@ -0,0 +1,307 @@
+use serde::{Deserialize, Serialize};
+
+use crate::chem::mass::{collapse_formula, molar_mass};
+use crate::chem::parse::{format_formula, ordered_unique_elements, parse_formula};
+use crate::commands::batch::merge_batch_advice;
+use crate::commands::calc_helpers::{format_value, parse_quantity};
+use crate::commands::calculate::{
+    calculate_single, merge_reagent_results, CalculationInput, CalculationOutput, ElementCoeff,
+    MassCheck, MatrixDiagnostics,
+};
+use crate::commands::composition::element_composition;
+use crate::commands::fetch_elements::get_atomic_masses;
+use crate::commands::settings::read_settings;
+use crate::commands::uncertainty::{format_uncertain, CompositionUncertainty};
+
+#[derive(Deserialize, Clone)]
+pub struct PhaseFraction {
+    pub formula: String,
+    pub fraction: f64,
+    #[serde(default)]
+    pub starting_materials: Vec<String>,
+}
+
+#[derive(Serialize)]
+pub struct PhaseResult {
+    pub formula: String,
+    pub mole_fraction: f64,
+    pub weight_fraction: f64,
+    pub molar_mass: f64,
+    pub moles: f64,
+    pub mass: f64,
+}
+
+fn composite_label(phases: &[PhaseResult], by_weight: bool) -> String {
+    phases
+        .iter()
+        .map(|p| {
+            let fraction = if by_weight {
+                p.weight_fraction
+            } else {
+                p.mole_fraction
+            };
+            format!(
+                "{} {}",
+                format_value(fraction)
+                    .trim_end_matches('0')
+                    .trim_end_matches('.'),
+                p.formula
+            )
+        })
+        .collect::<Vec<_>>()
+        .join(" + ")
+}
+
+/// Solves a composite target given as phases with mole (`phase_basis` =
+/// "mol") or weight ("wt") fractions, either one-pot from the merged
+/// formula or phase by phase when `per_phase` is set.
+pub async fn calculate_phases(input: CalculationInput) -> Result<CalculationOutput, String> {
+    let by_weight = match input.phase_basis.as_str() {
+        "mol" => false,
+        "wt" => true,
+        other => return Err(format!("Unknown phase basis: {}", other)),
+    };
+    let target_quantity =
+        parse_quantity(&input.target_mass).map_err(|e| format!("Target quantity: {}", e))?;
+    let masses = get_atomic_masses().await?;
+
+    let mut parsed_phases = Vec::new();
+    for phase in &input.phases {
+        let formula = phase.formula.trim();
+        if formula.is_empty() {
+            continue;
+        }
+        if phase.fraction <= 0.0 {
+            return Err(format!("Fraction for {} must be positive", formula));
+        }
+        let parsed = parse_formula(formula)?;
+        let phase_molar_mass = molar_mass(&collapse_formula(&parsed), &masses)?;
+        parsed_phases.push((phase, parsed, phase_molar_mass));
+    }
+    if parsed_phases.is_empty() {
+        return Err("No phases provided".to_string());
+    }
+
+    let relative_moles: Vec<f64> = parsed_phases
+        .iter()
+        .map(|(phase, _, m)| {
+            if by_weight {
+                phase.fraction / m
+            } else {
+                phase.fraction
+            }
+        })
+        .collect();
+    let mole_total: f64 = relative_moles.iter().sum();
+    let mixture_molar_mass: f64 = parsed_phases
+        .iter()
+        .zip(relative_moles.iter())
+        .map(|((_, _, m), n)| n / mole_total * m)
+        .sum();
+    let target_mass = target_quantity.grams(mixture_molar_mass);
+    let mixture_moles = target_mass / mixture_molar_mass;
+
+    let phase_results: Vec<PhaseResult> = parsed_phases
+        .iter()
+        .zip(relative_moles.iter())
+        .map(|((phase, _, m), n)| {
+            let mole_fraction = n / mole_total;
+            PhaseResult {
+                formula: phase.formula.trim().to_string(),
+                mole_fraction,
+                weight_fraction: mole_fraction * m / mixture_molar_mass,
+                molar_mass: *m,
+                moles: mole_fraction * mixture_moles,
+                mass: mole_fraction * m * mixture_moles,
+            }
+        })
+        .collect();
+
+    let mut merged = Vec::new();
+    for ((_, parsed, _), result) in parsed_phases.iter().zip(phase_results.iter()) {
+        for (el, coeff) in parsed {
+            merged.push((el.clone(), coeff * result.mole_fraction));
+        }
+    }
+    let merged_composition = collapse_formula(&merged);
+    let merged_parsed: Vec<(String, f64)> = ordered_unique_elements(&merged)
+        .into_iter()
+        .map(|el| {
+            let coeff = merged_composition[&el];
+            (el, coeff)
+        })
+        .collect();
+    let merged_formula = format_formula(&merged_parsed);
+
+    let mut explanation = vec![
+        format!(
+            "Composite target ({} fractions): {}",
+            input.phase_basis,
+            composite_label(&phase_results, by_weight)
+        ),
+        format!(
+            "Phase masses in product (g): {}",
+            phase_results
+                .iter()
+                .map(|p| format!(
+                    "{}={} ({} mol, {:.4} wt%)",
+                    p.formula,
+                    format_value(p.mass),
+                    format_value(p.moles),
+                    p.weight_fraction * 100.0
+                ))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+    ];
+
+    let mut output = if !input.per_phase {
+        explanation.push(format!(
+            "One-pot synthesis of merged formula {} ({} g/mol per formula unit)",
+            merged_formula,
+            format_value(mixture_molar_mass)
+        ));
+        calculate_single(CalculationInput {
+            target_formula: merged_formula,
+            target_composition: Some(merged_parsed),
+            target_mass: serde_json::json!(target_mass),
+            phases: Vec::new(),
+            ..input
+        })
+        .await?
+    } else {
+        let mut parts = Vec::new();
+        for ((phase, _, _), result) in parsed_phases.iter().zip(phase_results.iter()) {
+            let starting_materials = if phase.starting_materials.is_empty() {
+                input.starting_materials.clone()
+            } else {
+                phase.starting_materials.clone()
+            };
+            let part = calculate_single(CalculationInput {
+                target_formula: result.formula.clone(),
+                target_composition: None,
+                target_mass: serde_json::json!(result.mass),
+                starting_materials,
+                phases: Vec::new(),
+                ..input.clone()
+            })
+            .await
+            .map_err(|e| format!("{}: {}", result.formula, e))?;
+            parts.push(part);
+        }
+        let reagent_lists: Vec<_> = parts.iter().map(|p| p.reagents.as_slice()).collect();
+        let reagents = merge_reagent_results(&reagent_lists);
+        let total_reagent_mass: f64 = parts.iter().map(|p| p.mass_check.total_reagent_mass).sum();
+        let mut part_explanation = Vec::new();
+        for part in &parts {
+            for line in &part.explanation {
+                part_explanation.push(format!("[{}] {}", part.target_formula, line));
+            }
+        }
+        let equations: Vec<&str> = parts.iter().filter_map(|p| p.equation.as_deref()).collect();
+        let equation = (!equations.is_empty()).then(|| equations.join("; "));
+        // Report the worst-conditioned part; a rank-deficient one has no
+        // condition number and ranks worst.
+        let matrix = parts
+            .iter()
+            .filter_map(|p| p.matrix.as_ref())
+            .max_by(|a, b| {
+                let key = |m: &MatrixDiagnostics| m.condition_number.unwrap_or(f64::INFINITY);
+                key(a).total_cmp(&key(b))
+            })
+            .map(|m| MatrixDiagnostics {
+                rows: m.rows,
+                columns: m.columns,
+                rank: m.rank,
+                condition_number: m.condition_number,
+            });
+        let currency = parts[0].currency.clone();
+        let total_cost = if parts.iter().all(|p| p.currency == currency) {
+            parts.iter().map(|p| p.total_cost).sum()
+        } else {
+            None
+        };
+        // Parts are weighed separately, so their composition uncertainties
+        // add in quadrature, scaled by each phase's mole fraction.
+        let composition_uncertainty: Vec<CompositionUncertainty> = match input.uncertainty {
+            Some(_) => merged_parsed
+                .iter()
+                .filter_map(|(el, coeff)| {
+                    let variances: Vec<f64> = parts
+                        .iter()
+                        .zip(phase_results.iter())
+                        .filter_map(|(part, result)| {
+                            part.composition_uncertainty
+                                .iter()
+                                .find(|c| &c.element == el)
+                                .map(|c| (c.uncertainty * result.mole_fraction).powi(2))
+                        })
+                        .collect();
+                    (!variances.is_empty()).then(|| CompositionUncertainty {
+                        element: el.clone(),
+                        coefficient: *coeff,
+                        uncertainty: variances.iter().sum::<f64>().sqrt(),
+                    })
+                })
+                .collect(),
+            None => Vec::new(),
+        };
+        if !composition_uncertainty.is_empty() {
+            part_explanation.push(format!(
+                "Composite composition with standard uncertainty (parts weighed independently): {}",
+                composition_uncertainty
+                    .iter()
+                    .map(|c| format!(
+                        "{} = {}",
+                        c.element,
+                        format_uncertain(c.coefficient, c.uncertainty)
+                    ))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ));
+        }
+        let precision = read_settings()?.precision();
+        let batch_advice = merge_batch_advice(
+            parts
+                .into_iter()
+                .zip(phase_results.iter())
+                .filter_map(|(part, result)| part.batch_advice.map(|a| (a, result.mass)))
+                .collect(),
+            target_mass,
+            &precision,
+        );
+        CalculationOutput {
+            target_formula: merged_formula,
+            parsed_formula: merged_parsed
+                .iter()
+                .map(|(el, coeff)| ElementCoeff {
+                    element: el.clone(),
+                    coefficient: *coeff,
+                })
+                .collect(),
+            molar_mass: mixture_molar_mass,
+            target_moles: mixture_moles,
+            composition: element_composition(&merged_parsed, &masses)?,
+            reagents,
+            mass_check: MassCheck {
+                target_mass,
+                total_reagent_mass,
+                delta: total_reagent_mass - target_mass,
+            },
+            phases: Vec::new(),
+            total_cost,
+            currency,
+            composition_uncertainty,
+            batch_advice,
+            equation,
+            matrix,
+            explanation: part_explanation,
+        }
+    };
+
+    explanation.append(&mut output.explanation);
+    output.explanation = explanation;
+    output.target_formula = composite_label(&phase_results, by_weight);
+    output.phases = phase_results;
+    Ok(output)
+}
@@ -0,0 +1,440 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::chem::balance::{solve_element_balance, Reagent};
+use crate::chem::mass::{collapse_formula, molar_mass};
+use crate::chem::parse::{ordered_unique_elements, parse_formula};
+use crate::commands::fetch_elements::get_atomic_masses;
+use crate::commands::hydration::{effective_formula, read_overrides};
+use crate::commands::inventory::read_inventory;
+use crate::commands::library::{compound_price, priced_compound, read_library};
+use crate::commands::settings::read_settings;
+
+const DEFAULT_VOLATILE: [&str; 4] = ["C", "H", "N", "O"];
+const HALOGENS: [&str; 4] = ["F", "Cl", "Br", "I"];
+/// Candidate sets solved before the search gives up.
+const MAX_SETS: usize = 20_000;
+
+#[derive(Deserialize)]
+pub struct SuggestInput {
+    pub target_formula: String,
+    #[serde(default = "default_target_mass")]
+    pub target_mass: f64,
+    #[serde(default)]
+    pub candidates: Vec<String>,
+    #[serde(default)]
+    pub volatile_elements: Option<Vec<String>>,
+    #[serde(default = "default_max_reagents")]
+    pub max_reagents: usize,
+    #[serde(default = "default_rank_by")]
+    pub rank_by: String,
+    #[serde(default = "default_limit")]
+    pub limit: usize,
+}
+
+#[derive(Serialize)]
+pub struct SuggestedReagent {
+    pub reagent: String,
+    pub moles: f64,
+    pub molar_mass: f64,
+    pub mass: f64,
+    pub in_stock: f64,
+    pub cost: Option<f64>,
+    pub currency: Option<String>,
+    /// Whole packs to buy for what the shelf lacks; `None` when stock is
+    /// short and the library gives no pack size.
+    pub packs_to_buy: Option<u32>,
+}
+
+#[derive(Serialize)]
+pub struct Suggestion {
+    pub starting_materials: Vec<String>,
+    pub reagents: Vec<SuggestedReagent>,
+    pub reagent_count: usize,
+    pub stability_penalty: u32,
+    pub total_mass: f64,
+    /// `None` when a reagent is unpriced or the prices mix currencies.
+    pub total_cost: Option<f64>,
+    pub currency: Option<String>,
+    /// Price of the whole packs to buy, in `currency`.
+    pub purchase_cost: Option<f64>,
+    pub sufficient_stock: bool,
+}
+
+#[derive(Serialize)]
+pub struct SuggestOutput {
+    pub target_formula: String,
+    pub volatile_elements: Vec<String>,
+    pub suggestions: Vec<Suggestion>,
+    pub explanation: Vec<String>,
+}
+
+fn default_target_mass() -> f64 {
+    1.0
+}
+
+fn default_max_reagents() -> usize {
+    4
+}
+
+fn default_rank_by() -> String {
+    "count".to_string()
+}
+
+fn default_limit() -> usize {
+    10
+}
+
+/// Rough handling penalty: hydrates and nitrates drift in water content,
+/// halides are hygroscopic and bare metals oxidise in air. Oxides and
+/// carbonates score 0.
+fn stability_penalty(formula: &str, composition: &HashMap<String, f64>) -> u32 {
+    let mut penalty = 0;
+    if formula.contains(['·', '*']) || composition.contains_key("H") {
+        penalty += 1;
+    }
+    if composition.contains_key("N") && composition.contains_key("O") {
+        penalty += 1;
+    }
+    if composition.keys().any(|el| HALOGENS.contains(&el.as_str())) {
+        penalty += 1;
+    }
+    if composition.len() == 1 && !composition.contains_key("O") {
+        penalty += 2;
+    }
+    penalty
+}
+
+/// Visits the `size`-member subsets of `0..n` in lexicographic order until
+/// `visit` returns false. Returns false if it was stopped.
+fn combinations(n: usize, size: usize, visit: &mut dyn FnMut(&[usize]) -> bool) -> bool {
+    fn step(
+        start: usize,
+        n: usize,
+        size: usize,
+        current: &mut Vec<usize>,
+        visit: &mut dyn FnMut(&[usize]) -> bool,
+    ) -> bool {
+        if current.len() == size {
+            return visit(current);
+        }
+        for idx in start..n {
+            current.push(idx);
+            let go_on = step(idx + 1, n, size, current, visit);
+            current.pop();
+            if !go_on {
+                return false;
+            }
+        }
+        true
+    }
+    step(0, n, size, &mut Vec::new(), visit)
+}
+
+/// Solves one candidate set; `None` unless every reagent is needed in a
+/// positive amount and the non-volatile elements balance exactly.
+fn solve_combination(
+    reagents: &[Reagent],
+    order: &[String],
+    required: &HashMap<String, f64>,
+) -> Option<Vec<f64>> {
+    let mut remaining = required.clone();
+    let amounts = solve_element_balance(reagents, order, &mut remaining, &mut Vec::new()).ok()?;
+    if amounts.iter().any(|n| *n < 1e-10) {
+        return None;
+    }
+    for el in order {
+        let actual: f64 = reagents
+            .iter()
+            .zip(amounts.iter())
+            .map(|(r, n)| n * r.composition.get(el).copied().unwrap_or(0.0))
+            .sum();
+        if (actual - required[el]).abs() > 1e-6 {
+            return None;
+        }
+    }
+    Some(amounts)
+}
+
+/// Enumerates precursor sets from the shelf (containers holding stock, or
+/// explicit `candidates`) that balance the non-volatile elements of the
+/// target, keeping only minimal sets.
+#[tauri::command]
+pub async fn suggest_precursors(input: SuggestInput) -> Result<SuggestOutput, String> {
+    if input.target_mass <= 0.0 {
+        return Err("Target mass must be positive".to_string());
+    }
+    let masses = get_atomic_masses().await?;
+    let precision = read_settings()?.precision();
+    let overrides = read_overrides()?;
+    let inventory = read_inventory()?;
+    let library = read_library()?;
+
+    let target = input.target_formula.trim();
+    let parsed_target = parse_formula(target)?;
+    let target_composition = collapse_formula(&parsed_target);
+    let target_moles = input.target_mass / molar_mass(&target_composition, &masses)?;
+    let volatile: Vec<String> = match &input.volatile_elements {
+        Some(list) => list.iter().map(|el| el.trim().to_string()).collect(),
+        None => DEFAULT_VOLATILE.iter().map(|el| el.to_string()).collect(),
+    };
+    let order: Vec<String> = ordered_unique_elements(&parsed_target)
+        .into_iter()
+        .filter(|el| !volatile.contains(el))
+        .collect();
+    if order.is_empty() {
+        return Err("Target has no non-volatile elements to balance".to_string());
+    }
+    let required: HashMap<String, f64> = order
+        .iter()
+        .map(|el| (el.clone(), target_composition[el] * target_moles))
+        .collect();
+
+    let mut stock: HashMap<String, f64> = HashMap::new();
+    for container in inventory.containers.iter().filter(|c| c.mass > 0.0) {
+        if let Some(compound) = library.iter().find(|c| c.id == container.compound_id) {
+            *stock.entry(compound.formula.clone()).or_insert(0.0) += container.mass;
+        }
+    }
+    let mut names: Vec<String> = if input.candidates.is_empty() {
+        stock.keys().cloned().collect()
+    } else {
+        input
+            .candidates
+            .iter()
+            .map(|c| c.trim().to_string())
+            .collect()
+    };
+    names.sort();
+    names.dedup();
+
+    let mut explanation = Vec::new();
+    let mut candidates = Vec::new();
+    let mut rejected = Vec::new();
+    for name in names.iter().filter(|n| !n.is_empty()) {
+        let formula = effective_formula(&overrides, name);
+        let composition = collapse_formula(&parse_formula(&formula)?);
+        let useful = composition.keys().any(|el| order.contains(el));
+        let foreign = composition
+            .keys()
+            .any(|el| !volatile.contains(el) && !target_composition.contains_key(el));
+        if !useful || foreign {
+            rejected.push(name.clone());
+            continue;
+        }
+        let penalty = stability_penalty(&formula, &composition);
+        candidates.push((
+            Reagent {
+                name: name.clone(),
+                molar_mass: molar_mass(&composition, &masses)?,
+                composition,
+            },
+            penalty,
+        ));
+    }
+    explanation.push(format!(
+        "Balancing {} with volatile {}; {} usable candidate(s): {}",
+        order.join(", "),
+        volatile.join(", "),
+        candidates.len(),
+        candidates
+            .iter()
+            .map(|(r, _)| r.name.clone())
+            .collect::<Vec<_>>()
+            .join(", ")
+    ));
+    if !rejected.is_empty() {
+        explanation.push(format!(
+            "Skipped (no target element or foreign element): {}",
+            rejected.join(", ")
+        ));
+    }
+
+    let mut feasible: Vec<(Vec<usize>, Vec<f64>)> = Vec::new();
+    // A minimal set never needs more reagents than there are elements to
+    // balance, so larger sets are not enumerated.
+    let max_size = input.max_reagents.max(1).min(order.len());
+    let mut solved = 0;
+    // Smaller sets first, so any superset of a feasible set can be skipped.
+    for size in 1..=max_size {
+        let finished = combinations(candidates.len(), size, &mut |set| {
+            if feasible
+                .iter()
+                .any(|(found, _)| found.iter().all(|idx| set.contains(idx)))
+            {
+                return true;
+            }
+            let covers = order.iter().all(|el| {
+                set.iter()
+                    .any(|idx| candidates[*idx].0.composition.contains_key(el))
+            });
+            if !covers {
+                return true;
+            }
+            if solved == MAX_SETS {
+                return false;
+            }
+            solved += 1;
+            let reagents: Vec<Reagent> = set.iter().map(|idx| candidates[*idx].0.clone()).collect();
+            if let Some(amounts) = solve_combination(&reagents, &order, &required) {
+                feasible.push((set.to_vec(), amounts));
+            }
+            true
+        });
+        if !finished {
+            explanation.push(format!(
+                "Search stopped after {} candidate sets; narrow the candidates for a complete list",
+                MAX_SETS
+            ));
+            break;
+        }
+    }
+
+    let mut suggestions: Vec<Suggestion> = feasible
+        .into_iter()
+        .map(|(set, amounts)| {
+            let (reagents, purchases): (Vec<SuggestedReagent>, Vec<Option<f64>>) = set
+                .iter()
+                .zip(amounts.iter())
+                .map(|(idx, n)| {
+                    let reagent = &candidates[*idx].0;
+                    let mass = n * reagent.molar_mass;
+                    let in_stock = stock.get(&reagent.name).copied().unwrap_or(0.0);
+                    let compound = priced_compound(&library, &reagent.name);
+                    let price = compound.and_then(compound_price);
+                    let pack_size = compound.and_then(|c| c.pack_size);
+                    let packs_to_buy = if in_stock >= mass {
+                        Some(0)
+                    } else {
+                        pack_size.map(|size| ((mass - in_stock) / size).ceil() as u32)
+                    };
+                    let purchase = match (packs_to_buy, pack_size, &price) {
+                        (Some(0), _, _) => Some(0.0),
+                        (Some(packs), Some(size), Some((p, _))) => Some(packs as f64 * size * p),
+                        _ => None,
+                    };
+                    let reagent = SuggestedReagent {
+                        reagent: reagent.name.clone(),
+                        moles: *n,
+                        molar_mass: reagent.molar_mass,
+                        mass,
+                        in_stock,
+                        cost: price.as_ref().map(|(p, _)| mass * p),
+                        currency: price.map(|(_, currency)| currency),
+                        packs_to_buy,
+                    };
+                    (reagent, purchase)
+                })
+                .unzip();
+            let mut currencies: Vec<&str> = reagents
+                .iter()
+                .filter_map(|r| r.currency.as_deref())
+                .collect();
+            currencies.sort();
+            currencies.dedup();
+            let single_currency = currencies.len() <= 1;
+            Suggestion {
+                starting_materials: reagents.iter().map(|r| r.reagent.clone()).collect(),
+                reagent_count: reagents.len(),
+                stability_penalty: set.iter().map(|idx| candidates[*idx].1).sum(),
+                total_mass: reagents.iter().map(|r| r.mass).sum(),
+                total_cost: reagents
+                    .iter()
+                    .map(|r| r.cost)
+                    .sum::<Option<f64>>()
+                    .filter(|_| single_currency),
+                currency: currencies
+                    .first()
+                    .filter(|_| single_currency)
+                    .map(|c| c.to_string()),
+                purchase_cost: purchases
+                    .into_iter()
+                    .sum::<Option<f64>>()
+                    .filter(|_| single_currency),
+                sufficient_stock: reagents.iter().all(|r| r.in_stock >= r.mass),
+                reagents,
+            }
+        })
+        .collect();
+
+    match input.rank_by.as_str() {
+        "count" => suggestions.sort_by(|a, b| {
+            a.reagent_count
+                .cmp(&b.reagent_count)
+                .then(a.stability_penalty.cmp(&b.stability_penalty))
+        }),
+        "stability" => suggestions.sort_by(|a, b| {
+            a.stability_penalty
+                .cmp(&b.stability_penalty)
+                .then(a.reagent_count.cmp(&b.reagent_count))
+        }),
+        "cost" => {
+            let mut currencies: Vec<&str> = suggestions
+                .iter()
+                .filter(|s| s.total_cost.is_some())
+                .filter_map(|s| s.currency.as_deref())
+                .collect();
+            currencies.sort();
+            currencies.dedup();
+            if currencies.len() > 1 {
+                return Err(format!(
+                    "Cannot rank by cost: prices are in different currencies ({})",
+                    currencies.join(", ")
+                ));
+            }
+            suggestions.sort_by(|a, b| {
+                let a_cost = a.total_cost.unwrap_or(f64::INFINITY);
+                let b_cost = b.total_cost.unwrap_or(f64::INFINITY);
+                a_cost
+                    .total_cmp(&b_cost)
+                    .then(a.reagent_count.cmp(&b.reagent_count))
+            })
+        }
+        other => return Err(format!("Unknown ranking: {}", other)),
+    }
+    explanation.push(format!(
+        "{} feasible minimal set(s), ranked by {}",
+        suggestions.len(),
+        input.rank_by
+    ));
+    suggestions.truncate(input.limit);
+    for (rank, suggestion) in suggestions.iter().enumerate() {
+        explanation.push(format!(
+            "{}. {} (penalty {}, {} for {} g target{}{}{})",
+            rank + 1,
+            suggestion.starting_materials.join(" + "),
+            suggestion.stability_penalty,
+            precision.display_mass(suggestion.total_mass),
+            input.target_mass,
+            suggestion
+                .total_cost
+                .map(|c| format!(
+                    ", cost {:.2} {}",
+                    c,
+                    suggestion.currency.as_deref().unwrap_or_default()
+                ))
+                .unwrap_or_default(),
+            suggestion
+                .purchase_cost
+                .filter(|c| *c > 0.0)
+                .map(|c| format!(
+                    ", packs to buy {:.2} {}",
+                    c,
+                    suggestion.currency.as_deref().unwrap_or_default()
+                ))
+                .unwrap_or_default(),
+            if suggestion.sufficient_stock {
+                ""
+            } else {
+                ", insufficient stock"
+            }
+        ));
+    }
+
+    Ok(SuggestOutput {
+        target_formula: target.to_string(),
+        volatile_elements: volatile,
+        suggestions,
+        explanation,
+    })
+}
@@ -0,0 +1,321 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::chem::balance::{solve_element_balance, Reagent};
+use crate::chem::mass::{collapse_formula, molar_mass};
+use crate::chem::parse::{ordered_unique_elements, parse_formula};
+use crate::commands::calc_helpers::{format_value, parse_quantity, Quantity};
+use crate::commands::fetch_elements::get_atomic_masses;
+use crate::commands::hydration::{effective_formula, read_overrides};
+use crate::commands::library::{read_library, resolve_compound};
+use crate::commands::settings::read_settings;
+
+const DEFAULT_VOLATILE: [&str; 4] = ["C", "H", "N", "O"];
+
+#[derive(Deserialize)]
+pub struct WeighedReagent {
+    pub reagent: String,
+    /// Weighed quantity, e.g. `1.25 g` or `5 mmol`.
+    pub amount: serde_json::Value,
+    /// Purity in %, defaults to the library entry or 100.
+    #[serde(default)]
+    pub purity: Option<f64>,
+}
+
+#[derive(Deserialize)]
+pub struct YieldInput {
+    pub target_formula: String,
+    pub reagents: Vec<WeighedReagent>,
+    #[serde(default)]
+    pub volatile_elements: Option<Vec<String>>,
+    /// Product mass actually recovered.
+    #[serde(default)]
+    pub actual_mass: Option<serde_json::Value>,
+}
+
+#[derive(Serialize)]
+pub struct YieldReagent {
+    pub reagent: String,
+    pub mass: f64,
+    pub moles: f64,
+    /// Moles consumed at the theoretical yield, when the balance is solvable.
+    pub required_moles: Option<f64>,
+    pub excess_moles: Option<f64>,
+    pub excess_mass: Option<f64>,
+    pub excess_percent: Option<f64>,
+}
+
+#[derive(Serialize)]
+pub struct LeftoverElement {
+    pub element: String,
+    pub moles: f64,
+    pub mass: f64,
+}
+
+#[derive(Serialize)]
+pub struct YieldOutput {
+    pub target_formula: String,
+    pub molar_mass: f64,
+    pub limiting_element: String,
+    pub limiting_reagents: Vec<String>,
+    pub product_moles: f64,
+    pub theoretical_mass: f64,
+    pub reagents: Vec<YieldReagent>,
+    pub leftovers: Vec<LeftoverElement>,
+    pub actual_mass: Option<f64>,
+    pub percent_yield: Option<f64>,
+    pub explanation: Vec<String>,
+}
+
+/// Forward calculation: the product the weighed reagents can give, assuming
+/// every non-volatile element ends up in the target until one runs out.
+#[tauri::command]
+pub async fn theoretical_yield(input: YieldInput) -> Result<YieldOutput, String> {
+    let masses = get_atomic_masses().await?;
+    let precision = read_settings()?.precision();
+    let overrides = read_overrides()?;
+    let library = read_library()?;
+
+    let target = input.target_formula.trim();
+    let parsed_target = parse_formula(target)?;
+    let target_composition = collapse_formula(&parsed_target);
+    let target_molar_mass = molar_mass(&target_composition, &masses)?;
+    let volatile: Vec<String> = match &input.volatile_elements {
+        Some(list) => list.iter().map(|el| el.trim().to_string()).collect(),
+        None => DEFAULT_VOLATILE.iter().map(|el| el.to_string()).collect(),
+    };
+    let order: Vec<String> = ordered_unique_elements(&parsed_target)
+        .into_iter()
+        .filter(|el| !volatile.contains(el))
+        .collect();
+    if order.is_empty() {
+        return Err("Target has no non-volatile elements to balance".to_string());
+    }
+
+    let mut explanation = Vec::new();
+    let mut reagents = Vec::new();
+    let mut weighed = Vec::new();
+    for item in input
+        .reagents
+        .iter()
+        .filter(|r| !r.reagent.trim().is_empty())
+    {
+        let name = item.reagent.trim();
+        let compound = resolve_compound(&library, name)?;
+        let name = compound.map(|c| c.formula.as_str()).unwrap_or(name);
+        let purity = item
+            .purity
+            .or(compound.and_then(|c| c.purity))
+            .unwrap_or(100.0);
+        if purity <= 0.0 || purity > 100.0 {
+            return Err(format!("Purity of {} must be between 0 and 100%", name));
+        }
+        let composition = collapse_formula(&parse_formula(&effective_formula(&overrides, name))?);
+        let reagent_molar_mass = molar_mass(&composition, &masses)?;
+        let quantity =
+            parse_quantity(&item.amount).map_err(|e| format!("Amount of {}: {}", name, e))?;
+        let mass = match quantity {
+            Quantity::Mass(g) => g,
+            Quantity::Amount(mol) => mol * reagent_molar_mass / (purity / 100.0),
+        };
+        let moles = mass * purity / 100.0 / reagent_molar_mass;
+        explanation.push(format!(
+            "{}: {} at {}% = {} mol ({} g/mol)",
+            name,
+            precision.display_mass(mass),
+            purity,
+            format_value(moles),
+            format_value(reagent_molar_mass)
+        ));
+        reagents.push(Reagent {
+            name: name.to_string(),
+            composition,
+            molar_mass: reagent_molar_mass,
+        });
+        weighed.push((mass, moles));
+    }
+    if reagents.is_empty() {
+        return Err("No reagents provided".to_string());
+    }
+
+    let supplied = |el: &str| -> f64 {
+        reagents
+            .iter()
+            .zip(weighed.iter())
+            .map(|(r, (_, n))| n * r.composition.get(el).copied().unwrap_or(0.0))
+            .sum()
+    };
+    let mut limiting: Option<(String, f64)> = None;
+    for el in &order {
+        let available = supplied(el) / target_composition[el];
+        if limiting.as_ref().is_none_or(|(_, n)| available < *n) {
+            limiting = Some((el.clone(), available));
+        }
+    }
+    let (limiting_element, product_moles) = limiting.unwrap();
+    if product_moles <= 0.0 {
+        return Err(format!("No reagent supplies {}", limiting_element));
+    }
+    let theoretical_mass = product_moles * target_molar_mass;
+    explanation.push(format!(
+        "Product limited by {}: {} mol {} / {} per formula unit = {} mol",
+        limiting_element,
+        format_value(supplied(&limiting_element)),
+        limiting_element,
+        target_composition[&limiting_element],
+        format_value(product_moles)
+    ));
+    explanation.push(format!(
+        "Theoretical yield: {} mol x {} g/mol = {}",
+        format_value(product_moles),
+        format_value(target_molar_mass),
+        precision.display_mass(theoretical_mass)
+    ));
+
+    let mut required: HashMap<String, f64> = order
+        .iter()
+        .map(|el| (el.clone(), target_composition[el] * product_moles))
+        .collect();
+    let amounts = match solve_element_balance(&reagents, &order, &mut required, &mut Vec::new()) {
+        Ok(amounts)
+            if amounts
+                .iter()
+                .zip(weighed.iter())
+                .all(|(n, (_, given))| *n >= -1e-10 && *n <= given * (1.0 + 1e-6)) =>
+        {
+            Some(amounts)
+        }
+        _ => {
+            explanation.push(
+                "Reagent consumption cannot be assigned uniquely for this set; only element leftovers are reported"
+                    .to_string(),
+            );
+            None
+        }
+    };
+
+    let results: Vec<YieldReagent> = reagents
+        .iter()
+        .zip(weighed.iter())
+        .enumerate()
+        .map(|(idx, (reagent, (mass, moles)))| {
+            let required_moles = amounts.as_ref().map(|a| a[idx].max(0.0));
+            let excess_moles = required_moles.map(|r| moles - r);
+            YieldReagent {
+                reagent: reagent.name.clone(),
+                mass: *mass,
+                moles: *moles,
+                required_moles,
+                excess_moles,
+                excess_mass: excess_moles.map(|e| e / moles * mass),
+                excess_percent: required_moles
+                    .filter(|r| *r > 0.0)
+                    .map(|r| (moles - r) / r * 100.0),
+            }
+        })
+        .collect();
+    let limiting_reagents: Vec<String> = match &amounts {
+        // Reagents used up completely at the theoretical yield.
+        Some(amounts) => reagents
+            .iter()
+            .zip(weighed.iter())
+            .zip(amounts.iter())
+            .filter(|((_, (_, moles)), required)| {
+                **required > 0.0 && (moles - **required).abs() <= 1e-6 * **required
+            })
+            .map(|((r, _), _)| r.name.clone())
+            .collect(),
+        None => reagents
+            .iter()
+            .filter(|r| r.composition.contains_key(&limiting_element))
+            .map(|r| r.name.clone())
+            .collect(),
+    };
+    explanation.push(format!(
+        "Limiting reagent: {}",
+        limiting_reagents.join(", ")
+    ));
+    for result in results
+        .iter()
+        .filter(|r| !limiting_reagents.contains(&r.reagent))
+    {
+        if let (Some(excess), Some(mass)) = (result.excess_moles, result.excess_mass) {
+            explanation.push(format!(
+                "{}: {} mol in excess ({}{})",
+                result.reagent,
+                format_value(excess),
+                precision.display_mass(mass),
+                result
+                    .excess_percent
+                    .map(|p| format!(", {:.2}%", p))
+                    .unwrap_or_default()
+            ));
+        }
+    }
+
+    let mut elements: Vec<String> = Vec::new();
+    for reagent in &reagents {
+        for el in reagent.composition.keys() {
+            if !volatile.contains(el) && !elements.contains(el) {
+                elements.push(el.clone());
+            }
+        }
+    }
+    elements.sort();
+    let leftovers: Vec<LeftoverElement> = elements
+        .iter()
+        .filter_map(|el| {
+            let used = target_composition.get(el).copied().unwrap_or(0.0) * product_moles;
+            let moles = supplied(el) - used;
+            if moles <= 1e-12 {
+                return None;
+            }
+            Some(LeftoverElement {
+                element: el.clone(),
+                moles,
+                mass: moles * masses.get(el).copied().unwrap_or(0.0),
+            })
+        })
+        .collect();
+    if !leftovers.is_empty() {
+        explanation.push(format!(
+            "Leftover elements: {}",
+            leftovers
+                .iter()
+                .map(|l| format!("{} {} mol", l.element, format_value(l.moles)))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ));
+    }
+
+    let actual_mass = match &input.actual_mass {
+        Some(value) => match parse_quantity(value).map_err(|e| format!("Actual mass: {}", e))? {
+            Quantity::Mass(g) => Some(g),
+            Quantity::Amount(mol) => Some(mol * target_molar_mass),
+        },
+        None => None,
+    };
+    let percent_yield = actual_mass.map(|m| m / theoretical_mass * 100.0);
+    if let (Some(actual), Some(percent)) = (actual_mass, percent_yield) {
+        explanation.push(format!(
+            "Yield: {} / {} = {:.2}%",
+            precision.display_mass(actual),
+            precision.display_mass(theoretical_mass),
+            percent
+        ));
+    }
+
+    Ok(YieldOutput {
+        target_formula: target.to_string(),
+        molar_mass: target_molar_mass,
+        limiting_element,
+        limiting_reagents,
+        product_moles,
+        theoretical_mass,
+        reagents: results,
+        leftovers,
+        actual_mass,
+        percent_yield,
+        explanation,
+    })
+}
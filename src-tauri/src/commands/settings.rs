@@ -3,6 +3,8 @@ use serde::{Deserialize, Serialize};
 use std::fs;
 use std::path::PathBuf;
 
+use crate::commands::calc_helpers::{MassUnit, Precision};
+
 #[derive(Serialize, Deserialize, Debug)]
 pub struct AppSettings {
     pub theme_mode: String,
@@ -10,6 +12,15 @@ pub struct AppSettings {
     pub auto_fill_starting_materials: bool,
     #[serde(default = "default_export_format")]
     pub export_format: String,
+    /// Readability of the balance in g.
+    #[serde(default = "default_balance_readability")]
+    pub balance_readability: f64,
+    /// Significant figures for moles and molar masses.
+    #[serde(default = "default_significant_figures")]
+    pub significant_figures: u32,
+    /// Unit masses are displayed in: mg, g or kg.
+    #[serde(default = "default_mass_unit")]
+    pub mass_unit: String,
 }
 
 fn get_settings_path() -> PathBuf {
@@ -26,6 +37,9 @@ fn default_settings() -> AppSettings {
         detailed_report: false,
         auto_fill_starting_materials: true,
         export_format: default_export_format(),
+        balance_readability: default_balance_readability(),
+        significant_figures: default_significant_figures(),
+        mass_unit: default_mass_unit(),
     }
 }
 
@@ -33,6 +47,28 @@ fn default_export_format() -> String {
     "pdf".to_string()
 }
 
+fn default_balance_readability() -> f64 {
+    0.0001
+}
+
+fn default_significant_figures() -> u32 {
+    6
+}
+
+fn default_mass_unit() -> String {
+    "g".to_string()
+}
+
+impl AppSettings {
+    pub fn precision(&self) -> Precision {
+        Precision {
+            readability: self.balance_readability,
+            significant_figures: self.significant_figures,
+            mass_unit: MassUnit::parse(&self.mass_unit).unwrap_or(MassUnit::Gram),
+        }
+    }
+}
+
 pub fn read_settings() -> Result<AppSettings, String> {
     let path = get_settings_path();
     if !path.exists() {
@@ -50,6 +86,13 @@ pub fn get_settings() -> Result<AppSettings, String> {
 
 #[tauri::command]
 pub fn save_settings(input: AppSettings) -> Result<(), String> {
+    if input.balance_readability <= 0.0 {
+        return Err("Balance readability must be positive".to_string());
+    }
+    if !(1..=15).contains(&input.significant_figures) {
+        return Err("Significant figures must be between 1 and 15".to_string());
+    }
+    MassUnit::parse(&input.mass_unit)?;
     let path = get_settings_path();
     let data = serde_json::to_string_pretty(&input).map_err(|e| e.to_string())?;
     fs::write(&path, data).map_err(|e| e.to_string())?;
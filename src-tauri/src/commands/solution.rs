@@ -0,0 +1,435 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::chem::mass::{collapse_formula, molar_mass};
+use crate::chem::parse::parse_formula;
+use crate::commands::calc_helpers::format_value;
+use crate::commands::calculate::{reagent_result, ReagentResult};
+use crate::commands::fetch_elements::get_atomic_masses;
+use crate::commands::settings::read_settings;
+
+#[derive(Deserialize, Clone)]
+pub struct SolutionSpec {
+    pub reagent: String,
+    #[serde(default = "default_kind")]
+    pub kind: String,
+    #[serde(default)]
+    pub concentration: Option<f64>,
+    #[serde(default = "default_unit")]
+    pub unit: String,
+    #[serde(default)]
+    pub density: Option<f64>,
+}
+
+pub struct LiquidAmount {
+    pub solution_mass: Option<f64>,
+    pub volume_ml: f64,
+}
+
+fn default_kind() -> String {
+    "solution".to_string()
+}
+
+fn default_unit() -> String {
+    "mol/L".to_string()
+}
+
+fn require_density(spec: &SolutionSpec) -> Result<f64, String> {
+    match spec.density {
+        Some(d) if d > 0.0 => Ok(d),
+        Some(_) => Err(format!("Density for {} must be positive", spec.reagent)),
+        None => Err(format!("Density is required for {}", spec.reagent)),
+    }
+}
+
+/// Converts an amount of the dissolved (or neat) reagent into the mass and
+/// volume of liquid to dispense. Densities are in g/mL.
+pub fn liquid_amount(
+    spec: &SolutionSpec,
+    moles: f64,
+    molar_mass: f64,
+) -> Result<LiquidAmount, String> {
+    let solute_mass = moles * molar_mass;
+    if spec.kind == "neat" {
+        let density = require_density(spec)?;
+        return Ok(LiquidAmount {
+            solution_mass: Some(solute_mass),
+            volume_ml: solute_mass / density,
+        });
+    }
+    if spec.kind != "solution" {
+        return Err(format!("Unknown reagent kind: {}", spec.kind));
+    }
+    let concentration = match spec.concentration {
+        Some(c) if c > 0.0 => c,
+        _ => {
+            return Err(format!(
+                "Concentration for {} must be positive",
+                spec.reagent
+            ))
+        }
+    };
+    match spec.unit.as_str() {
+        "mol/L" => {
+            let volume_ml = moles / concentration * 1000.0;
+            Ok(LiquidAmount {
+                solution_mass: spec.density.map(|d| volume_ml * d),
+                volume_ml,
+            })
+        }
+        "mol/kg" => {
+            let density = require_density(spec)?;
+            let solution_mass = solute_mass + moles / concentration * 1000.0;
+            Ok(LiquidAmount {
+                solution_mass: Some(solution_mass),
+                volume_ml: solution_mass / density,
+            })
+        }
+        "wt%" => {
+            if concentration > 100.0 {
+                return Err(format!(
+                    "Concentration for {} exceeds 100 wt%",
+                    spec.reagent
+                ));
+            }
+            let density = require_density(spec)?;
+            let solution_mass = solute_mass / (concentration / 100.0);
+            Ok(LiquidAmount {
+                solution_mass: Some(solution_mass),
+                volume_ml: solution_mass / density,
+            })
+        }
+        other => Err(format!("Unknown concentration unit: {}", other)),
+    }
+}
+
+pub fn format_volume(volume_ml: f64) -> String {
+    if volume_ml < 1.0 {
+        format!("{:.1} µL", volume_ml * 1000.0)
+    } else {
+        format!("{:.4} mL", volume_ml)
+    }
+}
+
+#[derive(Deserialize)]
+pub struct PrepareSolutionInput {
+    pub solute: String,
+    pub volume_ml: f64,
+    pub concentration: f64,
+    #[serde(default = "default_unit")]
+    pub unit: String,
+    #[serde(default)]
+    pub element: Option<String>,
+    /// Solution density in g/mL; required for ppm and ppb.
+    #[serde(default)]
+    pub density: Option<f64>,
+}
+
+#[derive(Serialize)]
+pub struct PrepareSolutionOutput {
+    pub target_formula: String,
+    pub molar_mass: f64,
+    pub target_moles: f64,
+    pub volume_ml: f64,
+    pub molarity: f64,
+    pub mass_concentration: f64,
+    pub reagents: Vec<ReagentResult>,
+    pub explanation: Vec<String>,
+}
+
+#[derive(Deserialize)]
+pub struct DilutionInput {
+    pub stock_concentration: f64,
+    pub targets: Vec<f64>,
+    pub final_volume_ml: f64,
+    #[serde(default = "default_unit")]
+    pub unit: String,
+    #[serde(default)]
+    pub serial: bool,
+    #[serde(default)]
+    pub solute: Option<String>,
+    #[serde(default)]
+    pub stock_element: Option<String>,
+    #[serde(default)]
+    pub target_element: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct DilutionStep {
+    pub step: u32,
+    pub source_concentration: f64,
+    pub target_concentration: f64,
+    pub aliquot_ml: f64,
+    pub diluent_ml: f64,
+    pub final_volume_ml: f64,
+}
+
+#[derive(Serialize)]
+pub struct DilutionOutput {
+    pub target_formula: String,
+    pub molar_mass: f64,
+    pub dilution_steps: Vec<DilutionStep>,
+    pub explanation: Vec<String>,
+}
+
+struct ConcentrationUnit {
+    scale: f64,
+    /// Counts moles rather than grams of solute.
+    molar: bool,
+    /// Per gram of solution (ppm, ppb) rather than per litre.
+    by_mass: bool,
+}
+
+fn unit_scale(unit: &str) -> Result<ConcentrationUnit, String> {
+    let (scale, molar, by_mass) = match unit {
+        "mol/L" => (1.0, true, false),
+        "mmol/L" => (1e-3, true, false),
+        "g/L" => (1.0, false, false),
+        "ppm" => (1e-6, false, true),
+        "ppb" => (1e-9, false, true),
+        other => return Err(format!("Unknown concentration unit: {}", other)),
+    };
+    Ok(ConcentrationUnit {
+        scale,
+        molar,
+        by_mass,
+    })
+}
+
+/// Amount of `element` (moles or grams) contained in one mole or gram of the
+/// compound; 1 when the concentration refers to the compound itself.
+fn basis_factor(
+    element: Option<&str>,
+    composition: &HashMap<String, f64>,
+    compound_mass: f64,
+    molar: bool,
+    masses: &HashMap<String, f64>,
+) -> Result<f64, String> {
+    let Some(element) = element.map(str::trim).filter(|e| !e.is_empty()) else {
+        return Ok(1.0);
+    };
+    let coeff = composition
+        .get(element)
+        .copied()
+        .ok_or_else(|| format!("{} is not present in the solute", element))?;
+    if molar {
+        return Ok(coeff);
+    }
+    let atomic_mass = masses
+        .get(element)
+        .copied()
+        .ok_or_else(|| format!("Missing atomic mass for {}", element))?;
+    Ok(coeff * atomic_mass / compound_mass)
+}
+
+fn basis_label(element: Option<&str>) -> String {
+    match element.map(str::trim).filter(|e| !e.is_empty()) {
+        Some(el) => format!(" as {}", el),
+        None => String::new(),
+    }
+}
+
+#[tauri::command]
+pub async fn prepare_solution(
+    input: PrepareSolutionInput,
+) -> Result<PrepareSolutionOutput, String> {
+    if input.volume_ml <= 0.0 {
+        return Err("Volume must be positive".to_string());
+    }
+    if input.concentration <= 0.0 {
+        return Err("Concentration must be positive".to_string());
+    }
+    let unit = unit_scale(&input.unit)?;
+    let molar = unit.molar;
+    let solute = input.solute.trim();
+    let masses = get_atomic_masses().await?;
+    let precision = read_settings()?.precision();
+    let composition = collapse_formula(&parse_formula(solute)?);
+    let solute_molar_mass = molar_mass(&composition, &masses)?;
+    if solute_molar_mass <= 0.0 {
+        return Err("Solute molar mass is zero".to_string());
+    }
+    let element = input.element.as_deref();
+    let factor = basis_factor(element, &composition, solute_molar_mass, molar, &masses)?;
+
+    let volume_l = input.volume_ml / 1000.0;
+    let solution_mass = match (unit.by_mass, input.density) {
+        (false, _) => None,
+        (true, Some(d)) if d > 0.0 => Some(input.volume_ml * d),
+        (true, Some(_)) => return Err("Solution density must be positive".to_string()),
+        (true, None) => {
+            return Err(format!(
+                "{} is a mass fraction; the solution density is required",
+                input.unit
+            ))
+        }
+    };
+    let basis_amount = input.concentration * unit.scale * solution_mass.unwrap_or(volume_l);
+    let (moles, mass) = if molar {
+        let moles = basis_amount / factor;
+        (moles, moles * solute_molar_mass)
+    } else {
+        let mass = basis_amount / factor;
+        (mass / solute_molar_mass, mass)
+    };
+
+    let mut explanation = Vec::new();
+    explanation.push(format!(
+        "Solute {}: {} g/mol",
+        solute,
+        format_value(solute_molar_mass)
+    ));
+    explanation.push(format!(
+        "Target: {} {}{} in {} mL",
+        input.concentration,
+        input.unit,
+        basis_label(element),
+        input.volume_ml
+    ));
+    if let Some(solution_mass) = solution_mass {
+        explanation.push(format!(
+            "Solution mass: {} mL x {} g/mL = {} g",
+            input.volume_ml,
+            input.density.unwrap_or_default(),
+            format_value(solution_mass)
+        ));
+    }
+    if (factor - 1.0).abs() > 1e-12 {
+        explanation.push(format!(
+            "Conversion: 1 {} of {} contains {} {} of {}",
+            if molar { "mol" } else { "g" },
+            solute,
+            format_value(factor),
+            if molar { "mol" } else { "g" },
+            element.unwrap_or_default().trim()
+        ));
+    }
+    explanation.push(format!(
+        "Weigh {} ({} mol) of {} and make up to {} mL",
+        precision.display_mass(mass),
+        precision.format_significant(moles),
+        solute,
+        input.volume_ml
+    ));
+
+    Ok(PrepareSolutionOutput {
+        target_formula: solute.to_string(),
+        molar_mass: solute_molar_mass,
+        target_moles: moles,
+        volume_ml: input.volume_ml,
+        molarity: moles / volume_l,
+        mass_concentration: mass / volume_l,
+        reagents: vec![reagent_result(solute, moles, solute_molar_mass, None, &[])?],
+        explanation,
+    })
+}
+
+#[tauri::command]
+pub async fn serial_dilution(input: DilutionInput) -> Result<DilutionOutput, String> {
+    if input.final_volume_ml <= 0.0 {
+        return Err("Final volume must be positive".to_string());
+    }
+    if input.stock_concentration <= 0.0 {
+        return Err("Stock concentration must be positive".to_string());
+    }
+    if input.targets.is_empty() {
+        return Err("No target concentrations provided".to_string());
+    }
+    let unit = unit_scale(&input.unit)?;
+    let molar = unit.molar;
+    let stock_element = input.stock_element.as_deref();
+    let target_element = input.target_element.as_deref();
+
+    let mut explanation = Vec::new();
+    let solute = input.solute.as_deref().map(str::trim).unwrap_or_default();
+    let (solute_molar_mass, stock) = if solute.is_empty() {
+        if basis_label(stock_element) != basis_label(target_element) {
+            return Err("A solute formula is required to convert between bases".to_string());
+        }
+        (0.0, input.stock_concentration)
+    } else {
+        let masses = get_atomic_masses().await?;
+        let composition = collapse_formula(&parse_formula(solute)?);
+        let solute_molar_mass = molar_mass(&composition, &masses)?;
+        let stock_factor = basis_factor(
+            stock_element,
+            &composition,
+            solute_molar_mass,
+            molar,
+            &masses,
+        )?;
+        let target_factor = basis_factor(
+            target_element,
+            &composition,
+            solute_molar_mass,
+            molar,
+            &masses,
+        )?;
+        let stock = input.stock_concentration / stock_factor * target_factor;
+        if (stock - input.stock_concentration).abs() > 1e-12 {
+            explanation.push(format!(
+                "Stock {} {}{} = {} {}{}",
+                input.stock_concentration,
+                input.unit,
+                basis_label(stock_element),
+                format_value(stock),
+                input.unit,
+                basis_label(target_element)
+            ));
+        }
+        (solute_molar_mass, stock)
+    };
+
+    if unit.by_mass {
+        explanation.push(format!(
+            "{} is a mass fraction; volumes assume the stock and the dilutions share one density",
+            input.unit
+        ));
+    }
+
+    let mut steps = Vec::new();
+    let mut source = stock;
+    for (idx, target) in input.targets.iter().enumerate() {
+        if *target <= 0.0 {
+            return Err("Target concentrations must be positive".to_string());
+        }
+        if *target > source {
+            return Err(format!(
+                "Target {} {} exceeds its source concentration {}",
+                target,
+                input.unit,
+                format_value(source)
+            ));
+        }
+        let aliquot_ml = target * input.final_volume_ml / source;
+        explanation.push(format!(
+            "Step {}: {} of {} {} + {} diluent -> {} {} ({} mL)",
+            idx + 1,
+            format_volume(aliquot_ml),
+            format_value(source),
+            input.unit,
+            format_volume(input.final_volume_ml - aliquot_ml),
+            target,
+            input.unit,
+            input.final_volume_ml
+        ));
+        steps.push(DilutionStep {
+            step: (idx + 1) as u32,
+            source_concentration: source,
+            target_concentration: *target,
+            aliquot_ml,
+            diluent_ml: input.final_volume_ml - aliquot_ml,
+            final_volume_ml: input.final_volume_ml,
+        });
+        if input.serial {
+            source = *target;
+        }
+    }
+
+    Ok(DilutionOutput {
+        target_formula: solute.to_string(),
+        molar_mass: solute_molar_mass,
+        dilution_steps: steps,
+        explanation,
+    })
+}
@@ -0,0 +1,271 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::commands::empirical_formula::ElementAmount;
+
+/// Uncertainties beyond the weighing itself, whose readability comes from
+/// the `balance_readability` setting.
+#[derive(Deserialize, Clone)]
+pub struct UncertaintyModel {
+    /// Standard uncertainty of the purity, in percentage points.
+    #[serde(default)]
+    pub purity: f64,
+    /// Standard uncertainties of atomic weights in g/mol.
+    #[serde(default)]
+    pub atomic_weights: Vec<ElementAmount>,
+}
+
+#[derive(Serialize)]
+pub struct CompositionUncertainty {
+    pub element: String,
+    pub coefficient: f64,
+    pub uncertainty: f64,
+}
+
+/// One weighed row: composition, moles and purity in %.
+pub struct WeighedRow<'a> {
+    pub composition: &'a HashMap<String, f64>,
+    pub moles: f64,
+    pub purity: Option<f64>,
+}
+
+pub struct Propagation {
+    pub mass_uncertainties: Vec<f64>,
+    pub composition: Vec<CompositionUncertainty>,
+}
+
+fn formula_mass(composition: &HashMap<String, f64>, masses: &HashMap<String, f64>) -> f64 {
+    composition
+        .iter()
+        .map(|(el, c)| c * masses.get(el).copied().unwrap_or(0.0))
+        .sum()
+}
+
+/// Standard uncertainty of a single weighed mass: two readings (tare and
+/// gross), each with a rectangular error of half the readability.
+pub fn weighing_uncertainty(readability: f64) -> f64 {
+    readability / 6f64.sqrt()
+}
+
+/// Central-difference sensitivity of `f` to parameter `idx`, scaled by its
+/// standard uncertainty.
+fn contribution(params: &[f64], idx: usize, u: f64, f: &dyn Fn(&[f64]) -> f64) -> f64 {
+    if u <= 0.0 {
+        return 0.0;
+    }
+    let step = (params[idx].abs() * 1e-6).max(1e-12);
+    let mut up = params.to_vec();
+    let mut down = params.to_vec();
+    up[idx] += step;
+    down[idx] -= step;
+    (f(&up) - f(&down)) / (2.0 * step) * u
+}
+
+/// Propagates readability, purity and atomic-weight uncertainties (GUM,
+/// first order, uncorrelated inputs) to the mass of each row and to the
+/// coefficients of the `order` elements in the product. Coefficients are
+/// normalised to the same sum as in the target formula. Rows with no moles
+/// are not weighed and carry no uncertainty.
+pub fn propagate(
+    model: &UncertaintyModel,
+    readability: f64,
+    rows: &[WeighedRow],
+    target_composition: &HashMap<String, f64>,
+    target_mass: f64,
+    order: &[String],
+    masses: &HashMap<String, f64>,
+) -> Propagation {
+    let mut elements: Vec<String> = target_composition.keys().cloned().collect();
+    for row in rows {
+        for el in row.composition.keys() {
+            if !elements.contains(el) {
+                elements.push(el.clone());
+            }
+        }
+    }
+    elements.sort();
+    let u_atomic: Vec<f64> = elements
+        .iter()
+        .map(|el| {
+            model
+                .atomic_weights
+                .iter()
+                .find(|a| a.element.trim() == el)
+                .map(|a| a.value)
+                .unwrap_or(0.0)
+        })
+        .collect();
+
+    // Parameters: atomic weights, then purities, then weighed masses.
+    let n_el = elements.len();
+    let n_rows = rows.len();
+    let mut params: Vec<f64> = elements
+        .iter()
+        .map(|el| masses.get(el).copied().unwrap_or(0.0))
+        .collect();
+    params.extend(rows.iter().map(|r| r.purity.unwrap_or(100.0)));
+    let atomic = |p: &[f64]| -> HashMap<String, f64> {
+        elements
+            .iter()
+            .cloned()
+            .zip(p[..n_el].iter().copied())
+            .collect()
+    };
+    let target_moles = target_mass / formula_mass(target_composition, masses);
+    let weighed: Vec<f64> = rows
+        .iter()
+        .map(|r| {
+            r.moles * formula_mass(r.composition, masses) / (r.purity.unwrap_or(100.0) / 100.0)
+        })
+        .collect();
+    params.extend(weighed.iter().copied());
+    let u_weighing = weighing_uncertainty(readability);
+
+    let mass_uncertainties = rows
+        .iter()
+        .enumerate()
+        .map(|(i, row)| {
+            if row.moles <= 0.0 {
+                return 0.0;
+            }
+            let ratio = row.moles / target_moles;
+            let required = |p: &[f64]| -> f64 {
+                let a = atomic(p);
+                target_mass / formula_mass(target_composition, &a)
+                    * ratio
+                    * formula_mass(row.composition, &a)
+                    / (p[n_el + i] / 100.0)
+            };
+            let mut variance = u_weighing * u_weighing;
+            for (idx, u) in u_atomic.iter().enumerate() {
+                variance += contribution(&params, idx, *u, &required).powi(2);
+            }
+            variance += contribution(&params, n_el + i, model.purity, &required).powi(2);
+            variance.sqrt()
+        })
+        .collect();
+
+    let total: f64 = order
+        .iter()
+        .map(|el| target_composition.get(el).copied().unwrap_or(0.0))
+        .sum();
+    let composition = order
+        .iter()
+        .map(|el| {
+            let coefficient = |p: &[f64]| -> f64 {
+                let a = atomic(p);
+                let delivered = |element: &str| -> f64 {
+                    rows.iter()
+                        .enumerate()
+                        .map(|(i, row)| {
+                            p[n_el + n_rows + i] * p[n_el + i]
+                                / 100.0
+                                / formula_mass(row.composition, &a)
+                                * row.composition.get(element).copied().unwrap_or(0.0)
+                        })
+                        .sum()
+                };
+                let sum: f64 = order.iter().map(|e| delivered(e)).sum();
+                total * delivered(el) / sum
+            };
+            let mut variance = 0.0;
+            for (idx, u) in u_atomic.iter().enumerate() {
+                variance += contribution(&params, idx, *u, &coefficient).powi(2);
+            }
+            for i in (0..n_rows).filter(|i| rows[*i].moles > 0.0) {
+                variance += contribution(&params, n_el + i, model.purity, &coefficient).powi(2);
+                variance +=
+                    contribution(&params, n_el + n_rows + i, u_weighing, &coefficient).powi(2);
+            }
+            CompositionUncertainty {
+                element: el.clone(),
+                coefficient: coefficient(&params),
+                uncertainty: variance.sqrt(),
+            }
+        })
+        .collect();
+
+    Propagation {
+        mass_uncertainties,
+        composition,
+    }
+}
+
+/// Formats `value ± u` with the uncertainty rounded to two significant
+/// digits, e.g. "0.1000 ± 0.0021".
+pub fn format_uncertain(value: f64, u: f64) -> String {
+    if u <= 0.0 || !u.is_finite() {
+        return format!("{}", value);
+    }
+    let decimals = (1 - u.log10().floor() as i32).clamp(0, 10) as usize;
+    format!("{:.*} ± {:.*}", decimals, value, decimals, u)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn composition(pairs: &[(&str, f64)]) -> HashMap<String, f64> {
+        pairs.iter().map(|(el, n)| (el.to_string(), *n)).collect()
+    }
+
+    #[test]
+    fn weighing_uncertainty_combines_two_readings() {
+        let u = weighing_uncertainty(0.0001);
+        assert!((u - 0.0001 / 6f64.sqrt()).abs() < 1e-15);
+    }
+
+    #[test]
+    fn unweighed_rows_carry_no_uncertainty() {
+        let masses = composition(&[
+            ("Ba", 137.327),
+            ("Ti", 47.867),
+            ("O", 15.999),
+            ("C", 12.011),
+        ]);
+        let baco3 = composition(&[("Ba", 1.0), ("C", 1.0), ("O", 3.0)]);
+        let tio2 = composition(&[("Ti", 1.0), ("O", 2.0)]);
+        let ti = composition(&[("Ti", 1.0)]);
+        let target = composition(&[("Ba", 1.0), ("Ti", 1.0), ("O", 3.0)]);
+        let moles = 1.0 / formula_mass(&target, &masses);
+        let rows = [
+            WeighedRow {
+                composition: &baco3,
+                moles,
+                purity: None,
+            },
+            WeighedRow {
+                composition: &tio2,
+                moles,
+                purity: None,
+            },
+            WeighedRow {
+                composition: &ti,
+                moles: 0.0,
+                purity: None,
+            },
+        ];
+        let model = UncertaintyModel {
+            purity: 0.0,
+            atomic_weights: Vec::new(),
+        };
+        let order = vec!["Ba".to_string(), "Ti".to_string()];
+        let result = propagate(&model, 0.0001, &rows, &target, 1.0, &order, &masses);
+
+        let u = weighing_uncertainty(0.0001);
+        assert!((result.mass_uncertainties[0] - u).abs() < 1e-12);
+        assert!((result.mass_uncertainties[1] - u).abs() < 1e-12);
+        assert_eq!(result.mass_uncertainties[2], 0.0);
+        for item in &result.composition {
+            assert!((item.coefficient - 1.0).abs() < 1e-9);
+            assert!(item.uncertainty.is_finite() && item.uncertainty > 0.0);
+        }
+    }
+
+    #[test]
+    fn formats_value_to_two_significant_digits_of_uncertainty() {
+        assert_eq!(format_uncertain(0.1, 0.00214), "0.1000 ± 0.0021");
+        assert_eq!(format_uncertain(12.3456, 0.15), "12.35 ± 0.15");
+        assert_eq!(format_uncertain(1.5, 0.0), "1.5");
+    }
+}
@@ -3,23 +3,58 @@ use tauri::{Builder, LogicalSize, Manager, Size, WindowEvent};
 
 mod chem;
 mod commands {
+    pub mod additives;
+    pub mod alloy;
+    pub mod batch;
+    pub mod calc_helpers;
     pub mod calculate;
+    pub mod composition;
+    pub mod empirical_formula;
+    pub mod equation;
     pub mod export_excel;
     pub mod export_helpers;
     pub mod export_pdf;
     pub mod export_types;
     pub mod fetch_elements;
+    pub mod flux;
+    pub mod glass_batch;
+    pub mod hazards;
+    pub mod history;
+    pub mod hydration;
+    pub mod inventory;
+    pub mod library;
+    pub mod mass_loss;
     pub mod parse_formula;
+    pub mod phases;
+    pub mod precursors;
+    pub mod product_yield;
     pub mod settings;
+    pub mod solution;
+    pub mod uncertainty;
 }
 
 use commands::{
+    alloy::alloy_charge,
     calculate::calculate,
+    composition::composition,
+    empirical_formula::empirical_formula,
+    equation::balance_equation,
     export_excel::export_to_excel,
     export_pdf::export_to_pdf,
     fetch_elements::{get_elements, restore_elements, save_elements},
+    flux::flux_charge,
+    glass_batch::glass_batch,
+    hazards::{get_hazards, restore_hazards, save_hazards},
+    history::{delete_history_entry, diff_history_entries, get_history, get_history_entry, search_history, tag_history_entry},
+    hydration::{delete_reagent_override, determine_hydration, get_reagent_overrides},
+    inventory::{check_stock, commit_synthesis, delete_container, get_inventory, get_ledger, save_container},
+    library::{delete_compound, get_compounds, save_compound, search_compounds},
+    mass_loss::mass_loss_profile,
     parse_formula::parse_formula,
+    precursors::suggest_precursors,
+    product_yield::theoretical_yield,
     settings::{get_settings, save_settings},
+    solution::{prepare_solution, serial_dilution},
 };
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
@@ -37,6 +72,39 @@ pub fn run() {
             save_settings,
             save_elements,
             restore_elements,
+            empirical_formula,
+            composition,
+            glass_batch,
+            alloy_charge,
+            prepare_solution,
+            serial_dilution,
+            flux_charge,
+            mass_loss_profile,
+            determine_hydration,
+            get_reagent_overrides,
+            delete_reagent_override,
+            get_compounds,
+            save_compound,
+            delete_compound,
+            search_compounds,
+            get_inventory,
+            save_container,
+            delete_container,
+            get_ledger,
+            check_stock,
+            commit_synthesis,
+            suggest_precursors,
+            get_hazards,
+            save_hazards,
+            restore_hazards,
+            theoretical_yield,
+            balance_equation,
+            get_history,
+            search_history,
+            get_history_entry,
+            tag_history_entry,
+            delete_history_entry,
+            diff_history_entries,
         ])
         .setup(|app| {
             // Use the Manager trait to access the window by its label
@@ -13,6 +13,7 @@ import { invoke } from '@tauri-apps/api/core';
 import { useSelector } from 'react-redux';
 
 import { selectors as elementsSelectors } from '../../modules/elements/store';
+import { formatMass, formatSignificant } from '../../modules/settings/format';
 import { selectors as settingsSelectors } from '../../modules/settings/store';
 
 type ResultingTableProps = {
@@ -22,6 +23,14 @@ type ResultingTableProps = {
 const ResultingTable = ({ onOpenExplanation }: ResultingTableProps) => {
   const results = useSelector(elementsSelectors.selectResults);
   const exportFormat = useSelector(settingsSelectors.selectExportFormat);
+  const readability = useSelector(settingsSelectors.selectBalanceReadability);
+  const significantFigures = useSelector(
+    settingsSelectors.selectSignificantFigures,
+  );
+  const massUnit = useSelector(settingsSelectors.selectMassUnit);
+  const mass = (grams: number) => formatMass(grams, readability, massUnit);
+  const significant = (value: number) =>
+    formatSignificant(value, significantFigures);
 
   const saveExport = async () => {
     try {
@@ -65,7 +74,7 @@ const ResultingTable = ({ onOpenExplanation }: ResultingTableProps) => {
                 Moles
               </TableCell>
               <TableCell sx={{ backgroundColor: 'background.paper' }}>
-                Mass (g)
+                Mass ({massUnit})
               </TableCell>
               <TableCell></TableCell>
             </TableRow>
@@ -74,8 +83,8 @@ const ResultingTable = ({ onOpenExplanation }: ResultingTableProps) => {
             {results?.reagents?.map((row, index) => (
               <TableRow key={index}>
                 <TableCell>{row.reagent}</TableCell>
-                <TableCell>{row.moles.toFixed(10)}</TableCell>
-                <TableCell>{row.mass.toFixed(6)}</TableCell>
+                <TableCell>{significant(row.moles)}</TableCell>
+                <TableCell>{mass(row.mass)}</TableCell>
                 <TableCell></TableCell>
               </TableRow>
             ))}
@@ -98,8 +107,10 @@ const ResultingTable = ({ onOpenExplanation }: ResultingTableProps) => {
             <TableBody>
               {(results?.molar_mass ?? 0) > 0 && (
                 <TableRow>
-                  <TableCell>{results?.molar_mass.toFixed(6)}</TableCell>
-                  <TableCell>{results?.target_moles.toFixed(8)}</TableCell>
+                  <TableCell>{significant(results?.molar_mass ?? 0)}</TableCell>
+                  <TableCell>
+                    {significant(results?.target_moles ?? 0)}
+                  </TableCell>
                 </TableRow>
               )}
             </TableBody>
@@ -123,11 +134,13 @@ const ResultingTable = ({ onOpenExplanation }: ResultingTableProps) => {
               </TableHead>
               <TableBody>
                 <TableRow>
-                  <TableCell>{results?.mass_check?.target_mass}</TableCell>
                   <TableCell>
-                    {results?.mass_check?.total_reagent_mass.toFixed(8)}
+                    {mass(results?.mass_check?.target_mass ?? 0)}
+                  </TableCell>
+                  <TableCell>
+                    {mass(results?.mass_check?.total_reagent_mass ?? 0)}
                   </TableCell>
-                  <TableCell>{results?.mass_check?.delta}</TableCell>
+                  <TableCell>{mass(results?.mass_check?.delta ?? 0)}</TableCell>
                 </TableRow>
               </TableBody>
             </Table>
@@ -0,0 +1,27 @@
+import { Box, MenuItem, Select, Typography } from '@mui/material';
+
+import { MassUnit } from '../../modules/settings/types';
+
+type MassUnitSelectProps = {
+  value: MassUnit;
+  onChange: (next: MassUnit) => void;
+};
+
+const MassUnitSelect = ({ value, onChange }: MassUnitSelectProps) => {
+  return (
+    <Box display="flex" alignItems="center" gap={1} sx={{ mt: 2 }}>
+      <Typography variant="body1">Mass unit</Typography>
+      <Select
+        size="small"
+        value={value}
+        onChange={(event) => onChange(event.target.value as MassUnit)}
+      >
+        <MenuItem value="mg">mg</MenuItem>
+        <MenuItem value="g">g</MenuItem>
+        <MenuItem value="kg">kg</MenuItem>
+      </Select>
+    </Box>
+  );
+};
+
+export default MassUnitSelect;
@@ -0,0 +1,55 @@
+import { Box, MenuItem, Select, Typography } from '@mui/material';
+
+const READABILITIES = [0.1, 0.01, 0.001, 0.0001, 0.00001];
+const SIGNIFICANT_FIGURES = [3, 4, 5, 6, 7, 8, 10];
+
+type PrecisionSelectProps = {
+  readability: number;
+  significantFigures: number;
+  onReadabilityChange: (next: number) => void;
+  onSignificantFiguresChange: (next: number) => void;
+};
+
+const PrecisionSelect = ({
+  readability,
+  significantFigures,
+  onReadabilityChange,
+  onSignificantFiguresChange,
+}: PrecisionSelectProps) => {
+  return (
+    <>
+      <Box display="flex" alignItems="center" gap={1} sx={{ mt: 2 }}>
+        <Typography variant="body1">Balance readability</Typography>
+        <Select
+          size="small"
+          value={readability}
+          onChange={(event) => onReadabilityChange(Number(event.target.value))}
+        >
+          {READABILITIES.map((value) => (
+            <MenuItem key={value} value={value}>
+              {value} g
+            </MenuItem>
+          ))}
+        </Select>
+      </Box>
+      <Box display="flex" alignItems="center" gap={1} sx={{ mt: 2 }}>
+        <Typography variant="body1">Significant figures</Typography>
+        <Select
+          size="small"
+          value={significantFigures}
+          onChange={(event) =>
+            onSignificantFiguresChange(Number(event.target.value))
+          }
+        >
+          {SIGNIFICANT_FIGURES.map((value) => (
+            <MenuItem key={value} value={value}>
+              {value}
+            </MenuItem>
+          ))}
+        </Select>
+      </Box>
+    </>
+  );
+};
+
+export default PrecisionSelect;
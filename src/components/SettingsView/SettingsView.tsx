@@ -4,9 +4,11 @@ import { useDispatch, useSelector } from 'react-redux';
 import AutoFillToggle from './AutoFillToggle';
 import DetailedReportToggle from './DetailedReportToggle';
 import ExportFormatSelect from './ExportFormatSelect';
+import MassUnitSelect from './MassUnitSelect';
+import PrecisionSelect from './PrecisionSelect';
 import ThemeToggleRow from './ThemeToggleRow';
 import { actions, selectors } from '../../modules/settings/store';
-import { SettingsPayload } from '../../modules/settings/types';
+import { MassUnit, SettingsPayload } from '../../modules/settings/types';
 import { AppDispatch } from '../../store';
 
 type SettingsViewProps = {
@@ -22,6 +24,9 @@ const SettingsView = ({ onBack, onOpenElementsEditor }: SettingsViewProps) => {
     selectors.selectAutoFillStartingMaterials,
   );
   const exportFormat = useSelector(selectors.selectExportFormat);
+  const balanceReadability = useSelector(selectors.selectBalanceReadability);
+  const significantFigures = useSelector(selectors.selectSignificantFigures);
+  const massUnit = useSelector(selectors.selectMassUnit);
 
   const buildPayload = (
     overrides: Partial<SettingsPayload>,
@@ -30,6 +35,9 @@ const SettingsView = ({ onBack, onOpenElementsEditor }: SettingsViewProps) => {
     detailed_report: detailedReport,
     auto_fill_starting_materials: autoFillStartingMaterials,
     export_format: exportFormat,
+    balance_readability: balanceReadability,
+    significant_figures: significantFigures,
+    mass_unit: massUnit,
     ...overrides,
   });
 
@@ -59,6 +67,22 @@ const SettingsView = ({ onBack, onOpenElementsEditor }: SettingsViewProps) => {
     dispatch(actions.updateSettings(buildPayload({ export_format: next })));
   };
 
+  const handleReadabilityChange = (next: number) => {
+    dispatch(
+      actions.updateSettings(buildPayload({ balance_readability: next })),
+    );
+  };
+
+  const handleSignificantFiguresChange = (next: number) => {
+    dispatch(
+      actions.updateSettings(buildPayload({ significant_figures: next })),
+    );
+  };
+
+  const handleMassUnitChange = (next: MassUnit) => {
+    dispatch(actions.updateSettings(buildPayload({ mass_unit: next })));
+  };
+
   return (
     <Container sx={{ height: '100%', display: 'flex', alignItems: 'center' }}>
       <Box sx={{ width: '100%' }}>
@@ -74,6 +98,13 @@ const SettingsView = ({ onBack, onOpenElementsEditor }: SettingsViewProps) => {
           value={exportFormat}
           onChange={handleExportFormatChange}
         />
+        <PrecisionSelect
+          readability={balanceReadability}
+          significantFigures={significantFigures}
+          onReadabilityChange={handleReadabilityChange}
+          onSignificantFiguresChange={handleSignificantFiguresChange}
+        />
+        <MassUnitSelect value={massUnit} onChange={handleMassUnitChange} />
         <AutoFillToggle
           enabled={autoFillStartingMaterials}
           onToggle={handleToggleAutoFill}
@@ -0,0 +1,26 @@
+import { MassUnit } from './types';
+
+const UNIT_EXPONENT: Record<MassUnit, number> = { mg: 3, g: 0, kg: -3 };
+
+const readabilityDecimals = (readability: number) =>
+  Math.max(0, Math.ceil(-Math.log10(readability) - 1e-9));
+
+// Mass in g, shown in the display unit with the decimals the balance
+// resolves.
+export const formatMass = (
+  grams: number,
+  readability: number,
+  unit: MassUnit,
+) => {
+  const exponent = UNIT_EXPONENT[unit];
+  const decimals = Math.max(0, readabilityDecimals(readability) - exponent);
+  return (grams * 10 ** exponent).toFixed(decimals);
+};
+
+export const formatSignificant = (value: number, figures: number) => {
+  if (value === 0 || !Number.isFinite(value)) {
+    return String(value);
+  }
+  const magnitude = Math.floor(Math.log10(Math.abs(value)));
+  return value.toFixed(Math.min(100, Math.max(0, figures - 1 - magnitude)));
+};
@@ -9,4 +9,7 @@ export const applySettingsPayload = (
   state.detailedReport = payload.detailed_report;
   state.autoFillStartingMaterials = payload.auto_fill_starting_materials;
   state.exportFormat = payload.export_format === 'excel' ? 'excel' : 'pdf';
+  state.balanceReadability = payload.balance_readability;
+  state.significantFigures = payload.significant_figures;
+  state.massUnit = payload.mass_unit ?? 'g';
 };
@@ -7,3 +7,8 @@ export const selectAutoFillStartingMaterials = (state: RootState) =>
   state.settings.autoFillStartingMaterials;
 export const selectExportFormat = (state: RootState) =>
   state.settings.exportFormat;
+export const selectBalanceReadability = (state: RootState) =>
+  state.settings.balanceReadability;
+export const selectSignificantFigures = (state: RootState) =>
+  state.settings.significantFigures;
+export const selectMassUnit = (state: RootState) => state.settings.massUnit;
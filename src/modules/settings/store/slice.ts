@@ -9,6 +9,9 @@ export type SettingsState = {
   detailedReport: boolean;
   autoFillStartingMaterials: boolean;
   exportFormat: 'pdf' | 'excel';
+  balanceReadability: number;
+  significantFigures: number;
+  massUnit: 'mg' | 'g' | 'kg';
 };
 
 const initialState: SettingsState = {
@@ -16,6 +19,9 @@ const initialState: SettingsState = {
   detailedReport: defaultSettings.detailed_report,
   autoFillStartingMaterials: defaultSettings.auto_fill_starting_materials,
   exportFormat: defaultSettings.export_format,
+  balanceReadability: defaultSettings.balance_readability,
+  significantFigures: defaultSettings.significant_figures,
+  massUnit: defaultSettings.mass_unit,
 };
 
 export const settingsSlice = createSlice({
@@ -1,11 +1,15 @@
 export type ThemeMode = 'light' | 'dark' | 'system';
 export type ExportFormat = 'pdf' | 'excel';
+export type MassUnit = 'mg' | 'g' | 'kg';
 
 export type SettingsPayload = {
   theme_mode: ThemeMode;
   detailed_report: boolean;
   auto_fill_starting_materials: boolean;
   export_format: ExportFormat;
+  balance_readability: number;
+  significant_figures: number;
+  mass_unit: MassUnit;
 };
 
 export const defaultSettings: SettingsPayload = {
@@ -13,4 +17,7 @@ export const defaultSettings: SettingsPayload = {
   detailed_report: false,
   auto_fill_starting_materials: true,
   export_format: 'pdf',
+  balance_readability: 0.0001,
+  significant_figures: 6,
+  mass_unit: 'g',
 };